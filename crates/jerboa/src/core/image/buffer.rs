@@ -2,8 +2,11 @@ use crate::core::{
     image::{
         codec::{pnm, pnm::Encoding},
         error::ImageError,
-        iters::{Pixels, PixelsMut},
-        Bit, Pixel,
+        iters::{
+            Columns, EnumerateRows, Pixels, PixelsMut, Rows, RowsMut, Tiles, TilesMut, Windows,
+        },
+        par::{ParRowsMut, ParTilesMut},
+        Bit, Pixel, Sample,
     },
     Vec1, Vec2, Vec3, Vec4,
 };
@@ -76,13 +79,142 @@ impl<P: Pixel> PixelBuffer<P> {
         ))
     }
 
-    pub fn pixels(&self) -> Pixels<P> {
+    pub fn pixels(&self) -> Pixels<'_, P> {
         Pixels::new(&self.samples, self.width)
     }
 
-    pub fn pixels_mut(&mut self) -> PixelsMut<P> {
+    pub fn pixels_mut(&mut self) -> PixelsMut<'_, P> {
         PixelsMut::new(&mut self.samples, self.width)
     }
+
+    /// Returns an iterator over the rows of the buffer.
+    pub fn rows(&self) -> Rows<'_, P> {
+        Rows::new(&self.samples, self.width)
+    }
+
+    /// Returns an iterator over the mutable rows of the buffer.
+    pub fn rows_mut(&mut self) -> RowsMut<'_, P> {
+        RowsMut::new(&mut self.samples, self.width)
+    }
+
+    /// Returns an iterator over the rows of the buffer yielding the row index
+    /// and an iterator over the pixels of the row with their coordinates.
+    pub fn enumerate_rows(&self) -> EnumerateRows<'_, P> {
+        EnumerateRows::new(&self.samples, self.width)
+    }
+
+    /// Returns an iterator over the columns of the buffer.
+    pub fn columns(&self) -> Columns<'_, P> {
+        Columns::new(&self.samples, self.width)
+    }
+
+    /// Returns an iterator over `tile_width` x `tile_height` tiles of the
+    /// buffer.
+    pub fn tiles(&self, tile_width: u32, tile_height: u32) -> Tiles<'_, P> {
+        Tiles::new(&self.samples, self.width, tile_width, tile_height)
+    }
+
+    /// Returns an iterator over mutable `tile_width` x `tile_height` tiles of
+    /// the buffer.
+    pub fn tiles_mut(&mut self, tile_width: u32, tile_height: u32) -> TilesMut<'_, P> {
+        TilesMut::new(&mut self.samples, self.width, tile_width, tile_height)
+    }
+
+    /// Returns an iterator over the `(2 * radius + 1)^2` neighbourhood of
+    /// every pixel, clamped at the borders of the buffer.
+    pub fn windows(&self, radius: u32) -> Windows<'_, P> {
+        Windows::new(&self.samples, self.width, radius)
+    }
+
+    /// Returns a parallel iterator over the rows of the buffer.
+    pub fn par_rows_mut(&mut self) -> ParRowsMut<'_, P>
+    where
+        P::Subpixel: Send,
+    {
+        ParRowsMut::new(&mut self.samples, self.width)
+    }
+
+    /// Returns a parallel iterator over `tile_width` x `tile_height` tiles of
+    /// the buffer.
+    pub fn par_tiles_mut(&mut self, tile_width: u32, tile_height: u32) -> ParTilesMut<'_, P>
+    where
+        P::Subpixel: Send,
+    {
+        ParTilesMut::new(&mut self.samples, self.width, tile_width, tile_height)
+    }
+
+    /// Converts the samples of the buffer to another sample type, without
+    /// normalisation; see [`Sample::from_f32`].
+    pub fn convert<S: Sample>(&self) -> PixelBuffer<P::WithSubpixel<S>> {
+        PixelBuffer::from_samples(
+            self.width,
+            self.height,
+            self.samples
+                .iter()
+                .map(|s| S::from_f32(s.to_f32()))
+                .collect(),
+        )
+    }
+
+    /// Sets every pixel of the buffer to `pixel`.
+    pub fn fill(&mut self, pixel: P) {
+        for chunk in self.samples.chunks_exact_mut(P::N_CHANNELS) {
+            chunk.copy_from_slice(pixel.channels());
+        }
+    }
+
+    /// Creates a new buffer by applying `f` to each pixel.
+    pub fn map<Q: Pixel, F: FnMut(&P) -> Q>(&self, mut f: F) -> PixelBuffer<Q> {
+        let mut samples = Vec::with_capacity(self.samples.len() / P::N_CHANNELS * Q::N_CHANNELS);
+        for chunk in self.samples.chunks_exact(P::N_CHANNELS) {
+            samples.extend_from_slice(f(P::from_slice(chunk)).channels());
+        }
+        PixelBuffer::from_samples(self.width, self.height, samples)
+    }
+
+    /// Creates a new buffer by combining the pixels of two buffers of the
+    /// same dimensions with `f`.
+    pub fn zip_map<R: Pixel, Q: Pixel, F: FnMut(&P, &R) -> Q>(
+        &self,
+        other: &PixelBuffer<R>,
+        mut f: F,
+    ) -> PixelBuffer<Q> {
+        assert_eq!(
+            self.dimensions(),
+            other.dimensions(),
+            "buffer dimensions mismatch while zipping pixel buffers"
+        );
+        let mut samples = Vec::with_capacity(self.samples.len() / P::N_CHANNELS * Q::N_CHANNELS);
+        for (a, b) in self
+            .samples
+            .chunks_exact(P::N_CHANNELS)
+            .zip(other.samples.chunks_exact(R::N_CHANNELS))
+        {
+            samples.extend_from_slice(f(P::from_slice(a), R::from_slice(b)).channels());
+        }
+        PixelBuffer::from_samples(self.width, self.height, samples)
+    }
+
+    /// Combines the pixels of `other` into this buffer in place; `f` receives
+    /// the current pixel and the corresponding pixel of `other` and returns
+    /// the new value.
+    ///
+    /// Accumulating a sample buffer is `acc.blend(&src, |a, b| *a + *b)`.
+    pub fn blend<F: FnMut(&P, &P) -> P>(&mut self, other: &PixelBuffer<P>, mut f: F) {
+        assert_eq!(
+            self.dimensions(),
+            other.dimensions(),
+            "buffer dimensions mismatch while blending pixel buffers"
+        );
+        for (a, b) in self
+            .samples
+            .chunks_exact_mut(P::N_CHANNELS)
+            .zip(other.samples.chunks_exact(P::N_CHANNELS))
+        {
+            let dst = P::from_slice_mut(a);
+            *dst = f(dst, P::from_slice(b));
+        }
+    }
 }
 
 impl PixelBuffer<Vec1<Bit>> {
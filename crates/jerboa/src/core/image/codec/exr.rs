@@ -0,0 +1,279 @@
+//! Minimal OpenEXR encoder writing uncompressed single-part scanline images.
+//!
+//! Layers are stored as channels named `layer.channel`, the convention used by
+//! compositing packages for multi-layer files; a layer with an empty name
+//! stores its channels unprefixed, e.g. the beauty `R`, `G` and `B`.
+
+use crate::core::image::{
+    error::{EncodingError, ImageError},
+    ImageFormat,
+};
+use std::io;
+
+const MAGIC: u32 = 20000630;
+
+/// Single-part scanline file.
+const VERSION: u32 = 2;
+
+/// Version flag allowing attribute and channel names of up to 255 bytes,
+/// instead of 31.
+const LONG_NAMES: u32 = 0x400;
+
+/// Longest name allowed with [`LONG_NAMES`].
+const MAX_NAME_LENGTH: usize = 255;
+
+fn map_io_error_encoding(err: io::Error) -> ImageError {
+    ImageError::Encoding(EncodingError::new(ImageFormat::Exr, err))
+}
+
+fn invalid_input(message: String) -> ImageError {
+    map_io_error_encoding(io::Error::new(io::ErrorKind::InvalidInput, message))
+}
+
+/// Interleaved samples of a layer.
+#[derive(Debug, Copy, Clone)]
+pub enum ExrSamples<'a> {
+    Uint(&'a [u32]),
+    Float(&'a [f32]),
+}
+
+impl<'a> ExrSamples<'a> {
+    fn len(&self) -> usize {
+        match self {
+            ExrSamples::Uint(s) => s.len(),
+            ExrSamples::Float(s) => s.len(),
+        }
+    }
+
+    /// EXR pixel type identifier.
+    fn pixel_type(&self) -> i32 {
+        match self {
+            ExrSamples::Uint(_) => 0,
+            ExrSamples::Float(_) => 2,
+        }
+    }
+
+    fn write_le<W: io::Write>(&self, w: &mut W, i: usize) -> io::Result<()> {
+        match self {
+            ExrSamples::Uint(s) => w.write_all(&s[i].to_le_bytes()),
+            ExrSamples::Float(s) => w.write_all(&s[i].to_le_bytes()),
+        }
+    }
+}
+
+/// Named group of channels stored in an EXR file.
+#[derive(Debug, Copy, Clone)]
+pub struct ExrLayer<'a> {
+    pub name: &'a str,
+
+    /// Names of the channels, in the order they are interleaved in `samples`.
+    pub channels: &'a [&'a str],
+
+    pub samples: ExrSamples<'a>,
+}
+
+fn write_attribute<W: io::Write>(w: &mut W, name: &str, ty: &str, value: &[u8]) -> io::Result<()> {
+    w.write_all(name.as_bytes())?;
+    w.write_all(&[0])?;
+    w.write_all(ty.as_bytes())?;
+    w.write_all(&[0])?;
+    w.write_all(&(value.len() as i32).to_le_bytes())?;
+    w.write_all(value)
+}
+
+fn box2i(width: u32, height: u32) -> Vec<u8> {
+    [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect()
+}
+
+/// Writes the layers as an uncompressed EXR image.
+pub fn write_exr_to_stream<W: io::Write>(
+    stream: &mut W,
+    width: u32,
+    height: u32,
+    layers: &[ExrLayer],
+) -> Result<(), ImageError> {
+    let n_pixels = (width * height) as usize;
+    // Channels are stored sorted by name: (full name, layer, channel index).
+    let mut channels = Vec::new();
+    for (l, layer) in layers.iter().enumerate() {
+        if layer.samples.len() != n_pixels * layer.channels.len() {
+            return Err(invalid_input(format!(
+                "layer '{}' does not match the image size",
+                layer.name
+            )));
+        }
+        for (c, channel) in layer.channels.iter().enumerate() {
+            let name = if layer.name.is_empty() {
+                channel.to_string()
+            } else {
+                format!("{}.{}", layer.name, channel)
+            };
+            channels.push((name, l, c));
+        }
+    }
+    channels.sort_by(|a, b| a.0.cmp(&b.0));
+    let longest = channels.iter().map(|(name, ..)| name.len()).max();
+    if let Some((name, ..)) = channels
+        .iter()
+        .find(|(name, ..)| name.len() > MAX_NAME_LENGTH)
+    {
+        return Err(invalid_input(format!(
+            "channel name '{name}' is longer than {MAX_NAME_LENGTH} bytes"
+        )));
+    }
+    // Attribute names are all short.
+    let version = if longest.is_some_and(|len| len > 31) {
+        VERSION | LONG_NAMES
+    } else {
+        VERSION
+    };
+
+    let mut header = Vec::new();
+    header.extend(MAGIC.to_le_bytes());
+    header.extend(version.to_le_bytes());
+    let mut chlist = Vec::new();
+    for (name, l, _) in &channels {
+        chlist.extend(name.as_bytes());
+        chlist.push(0);
+        chlist.extend(layers[*l].samples.pixel_type().to_le_bytes());
+        // pLinear and reserved bytes, then x and y sampling.
+        chlist.extend([0u8; 4]);
+        chlist.extend(1i32.to_le_bytes());
+        chlist.extend(1i32.to_le_bytes());
+    }
+    chlist.push(0);
+    let one = 1f32.to_le_bytes();
+    write_attribute(&mut header, "channels", "chlist", &chlist)
+        .and_then(|_| write_attribute(&mut header, "compression", "compression", &[0]))
+        .and_then(|_| write_attribute(&mut header, "dataWindow", "box2i", &box2i(width, height)))
+        .and_then(|_| write_attribute(&mut header, "displayWindow", "box2i", &box2i(width, height)))
+        .and_then(|_| write_attribute(&mut header, "lineOrder", "lineOrder", &[0]))
+        .and_then(|_| write_attribute(&mut header, "pixelAspectRatio", "float", &one))
+        .and_then(|_| write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]))
+        .and_then(|_| write_attribute(&mut header, "screenWindowWidth", "float", &one))
+        .map_err(map_io_error_encoding)?;
+    header.push(0);
+
+    // Every sample type takes four bytes.
+    let line_size = width as usize * channels.len() * 4;
+    let chunk_size = 8 + line_size;
+    let first_chunk = header.len() + height as usize * 8;
+    for y in 0..height as usize {
+        header.extend(((first_chunk + y * chunk_size) as u64).to_le_bytes());
+    }
+    stream.write_all(&header).map_err(map_io_error_encoding)?;
+
+    let mut line = Vec::with_capacity(chunk_size);
+    for y in 0..height as usize {
+        line.clear();
+        line.extend((y as i32).to_le_bytes());
+        line.extend((line_size as i32).to_le_bytes());
+        for (_, l, c) in &channels {
+            let layer = &layers[*l];
+            let n_channels = layer.channels.len();
+            for x in 0..width as usize {
+                let i = (y * width as usize + x) * n_channels + c;
+                layer
+                    .samples
+                    .write_le(&mut line, i)
+                    .map_err(map_io_error_encoding)?;
+            }
+        }
+        stream.write_all(&line).map_err(map_io_error_encoding)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::image::ImageDecoder;
+
+    fn read_u32(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn layout_of_layers() {
+        let rgb = [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let ids = [7u32, 8];
+        let layers = [
+            ExrLayer {
+                name: "",
+                channels: &["R", "G", "B"],
+                samples: ExrSamples::Float(&rgb),
+            },
+            ExrLayer {
+                name: "id",
+                channels: &["id"],
+                samples: ExrSamples::Uint(&ids),
+            },
+        ];
+        let mut bytes = Vec::new();
+        write_exr_to_stream(&mut bytes, 2, 1, &layers).unwrap();
+        assert_eq!(read_u32(&bytes, 0), MAGIC);
+        assert_eq!(read_u32(&bytes, 4), VERSION);
+
+        // A single scanline of 8 + 2 * 4 * 4 bytes, preceded by its offset.
+        let at = bytes.len() - 48;
+        let offset = u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        assert_eq!(offset as usize, bytes.len() - 40);
+        let chunk = &bytes[offset as usize..];
+        assert_eq!(read_u32(chunk, 0), 0);
+        assert_eq!(read_u32(chunk, 4), 32);
+        // Channels sorted by name: B, G, R, id.id.
+        let values: Vec<u32> = (0..8).map(|i| read_u32(chunk, 8 + 4 * i)).collect();
+        assert_eq!(f32::from_bits(values[0]), 3.0);
+        assert_eq!(f32::from_bits(values[1]), 6.0);
+        assert_eq!(f32::from_bits(values[4]), 1.0);
+        assert_eq!(&values[6..], &[7, 8]);
+    }
+
+    #[test]
+    fn rejects_mismatched_layers() {
+        let layer = ExrLayer {
+            name: "depth",
+            channels: &["Z"],
+            samples: ExrSamples::Float(&[1.0]),
+        };
+        assert!(write_exr_to_stream(&mut Vec::new(), 2, 2, &[layer]).is_err());
+    }
+
+    #[test]
+    fn decoding_is_unsupported() {
+        let layer = ExrLayer {
+            name: "",
+            channels: &["Y"],
+            samples: ExrSamples::Float(&[1.0]),
+        };
+        let path = std::env::temp_dir().join(format!("jerboa-exr-{}.exr", std::process::id()));
+        let mut file = std::fs::File::create(&path).unwrap();
+        write_exr_to_stream(&mut file, 1, 1, &[layer]).unwrap();
+        let decoded = ImageDecoder::open(&path).unwrap().decode();
+        std::fs::remove_file(&path).unwrap();
+        match decoded {
+            Err(ImageError::UnsupportedFormat(message)) => assert!(message.contains("EXR")),
+            _ => panic!("EXR files must be reported as unsupported"),
+        }
+    }
+
+    #[test]
+    fn flags_long_names() {
+        let write = |name: &str| {
+            let layer = ExrLayer {
+                name,
+                channels: &["R"],
+                samples: ExrSamples::Float(&[1.0]),
+            };
+            let mut bytes = Vec::new();
+            write_exr_to_stream(&mut bytes, 1, 1, &[layer]).map(|_| read_u32(&bytes, 4))
+        };
+        // `layer.R` takes 31 bytes with a 29 byte layer name.
+        assert_eq!(write(&"a".repeat(29)).unwrap(), VERSION);
+        assert_eq!(write(&"a".repeat(30)).unwrap(), VERSION | LONG_NAMES);
+        assert!(write(&"a".repeat(254)).is_err());
+    }
+}
@@ -1 +1,2 @@
+pub mod exr;
 pub mod pnm;
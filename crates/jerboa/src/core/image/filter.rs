@@ -0,0 +1,444 @@
+//! Image filtering on pixel buffers of any pixel type.
+//!
+//! Filters work on the raw sample values converted to `f32` (no
+//! normalisation is applied), and results are converted back to the sample
+//! type of the input buffer, saturating for integer samples. Kernels are
+//! applied without flipping (cross-correlation), which is the usual
+//! convention for image processing.
+
+use crate::core::image::{Pixel, PixelBuffer, Sample};
+
+/// How samples outside of the image are obtained.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BorderMode {
+    /// Repeats the edge pixels: `aaa|abcd|ddd`.
+    Clamp,
+
+    /// Wraps around to the opposite edge: `bcd|abcd|abc`.
+    Wrap,
+
+    /// Reflects the image without repeating the edge pixels: `dcb|abcd|cba`.
+    Mirror,
+
+    /// Uses a constant sample value.
+    Constant(f32),
+}
+
+impl BorderMode {
+    /// Maps a possibly out-of-range index to an index in `[0, n)`. Returns
+    /// `None` if the sample should be taken from the constant border.
+    pub fn resolve(&self, i: i64, n: i64) -> Option<i64> {
+        if (0..n).contains(&i) {
+            return Some(i);
+        }
+        match self {
+            BorderMode::Clamp => Some(i.clamp(0, n - 1)),
+            BorderMode::Wrap => Some(i.rem_euclid(n)),
+            BorderMode::Mirror => {
+                if n == 1 {
+                    return Some(0);
+                }
+                let period = 2 * (n - 1);
+                let i = i.rem_euclid(period);
+                Some(if i < n { i } else { period - i })
+            }
+            BorderMode::Constant(_) => None,
+        }
+    }
+}
+
+/// Two dimensional filter kernel with odd dimensions, centred on its middle
+/// element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Kernel {
+    width: u32,
+    height: u32,
+    weights: Vec<f32>,
+}
+
+impl Kernel {
+    /// Creates a kernel from its weights stored in row-major order.
+    pub fn new(width: u32, height: u32, weights: Vec<f32>) -> Self {
+        assert!(
+            width % 2 == 1 && height % 2 == 1,
+            "kernel dimensions must be odd"
+        );
+        assert_eq!(
+            weights.len(),
+            (width * height) as usize,
+            "kernel weights length mismatch"
+        );
+        Kernel {
+            width,
+            height,
+            weights,
+        }
+    }
+
+    /// Creates the kernel `vertical^T * horizontal`.
+    pub fn from_separable(horizontal: &[f32], vertical: &[f32]) -> Self {
+        let weights = vertical
+            .iter()
+            .flat_map(|v| horizontal.iter().map(move |h| h * v))
+            .collect();
+        Kernel::new(horizontal.len() as u32, vertical.len() as u32, weights)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+
+    /// Returns a copy of the kernel whose weights sum to one.
+    pub fn normalized(&self) -> Self {
+        let sum: f32 = self.weights.iter().sum();
+        Kernel {
+            width: self.width,
+            height: self.height,
+            weights: self.weights.iter().map(|w| w / sum).collect(),
+        }
+    }
+}
+
+/// Returns a normalised 1D Gaussian kernel with a radius of `ceil(3 sigma)`.
+pub fn gaussian_kernel_1d(sigma: f32) -> Vec<f32> {
+    assert!(sigma > 0.0, "gaussian sigma must be positive");
+    let radius = (3.0 * sigma).ceil() as i32;
+    let weights = (-radius..=radius)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect::<Vec<_>>();
+    let sum: f32 = weights.iter().sum();
+    weights.into_iter().map(|w| w / sum).collect()
+}
+
+/// Returns a normalised 1D box kernel of `2 * radius + 1` elements.
+pub fn box_kernel_1d(radius: u32) -> Vec<f32> {
+    let n = 2 * radius + 1;
+    vec![1.0 / n as f32; n as usize]
+}
+
+/// Samples of a buffer converted to `f32`, interleaved as in the buffer.
+struct FloatImage {
+    width: u32,
+    height: u32,
+    n_channels: usize,
+    data: Vec<f32>,
+}
+
+impl FloatImage {
+    fn from_buffer<P: Pixel>(buffer: &PixelBuffer<P>) -> Self {
+        FloatImage {
+            width: buffer.width(),
+            height: buffer.height(),
+            n_channels: P::N_CHANNELS,
+            data: buffer.samples().iter().map(|s| s.to_f32()).collect(),
+        }
+    }
+
+    fn zeros_like(&self) -> Self {
+        FloatImage {
+            width: self.width,
+            height: self.height,
+            n_channels: self.n_channels,
+            data: vec![0.0; self.data.len()],
+        }
+    }
+
+    fn into_buffer<Q: Pixel>(self) -> PixelBuffer<Q> {
+        debug_assert_eq!(self.n_channels, Q::N_CHANNELS);
+        PixelBuffer::from_samples(
+            self.width,
+            self.height,
+            self.data.into_iter().map(Q::Subpixel::from_f32).collect(),
+        )
+    }
+
+    #[inline]
+    fn index(&self, x: usize, y: usize) -> usize {
+        (y * self.width as usize + x) * self.n_channels
+    }
+
+    /// Returns the sample of channel `c` at `(x, y)`, applying the border
+    /// mode for out-of-range coordinates.
+    #[inline]
+    fn get(&self, x: i64, y: i64, c: usize, border: BorderMode) -> f32 {
+        match (
+            border.resolve(x, self.width as i64),
+            border.resolve(y, self.height as i64),
+        ) {
+            (Some(x), Some(y)) => self.data[self.index(x as usize, y as usize) + c],
+            _ => match border {
+                BorderMode::Constant(val) => val,
+                _ => unreachable!(),
+            },
+        }
+    }
+
+    fn correlate(&self, kernel: &Kernel, border: BorderMode) -> FloatImage {
+        let mut out = self.zeros_like();
+        let (rx, ry) = ((kernel.width / 2) as i64, (kernel.height / 2) as i64);
+        for y in 0..self.height as i64 {
+            for x in 0..self.width as i64 {
+                let index = self.index(x as usize, y as usize);
+                for c in 0..self.n_channels {
+                    let mut sum = 0.0;
+                    for (j, row) in kernel
+                        .weights
+                        .chunks_exact(kernel.width as usize)
+                        .enumerate()
+                    {
+                        for (i, w) in row.iter().enumerate() {
+                            let sx = x + i as i64 - rx;
+                            let sy = y + j as i64 - ry;
+                            sum += w * self.get(sx, sy, c, border);
+                        }
+                    }
+                    out.data[index + c] = sum;
+                }
+            }
+        }
+        out
+    }
+
+    fn correlate_separable(
+        &self,
+        horizontal: &[f32],
+        vertical: &[f32],
+        border: BorderMode,
+    ) -> FloatImage {
+        let horizontal = Kernel::new(horizontal.len() as u32, 1, horizontal.to_vec());
+        let vertical = Kernel::new(1, vertical.len() as u32, vertical.to_vec());
+        self.correlate(&horizontal, border)
+            .correlate(&vertical, border)
+    }
+}
+
+/// Filters the buffer with a 2D kernel.
+pub fn convolve<P: Pixel>(
+    buffer: &PixelBuffer<P>,
+    kernel: &Kernel,
+    border: BorderMode,
+) -> PixelBuffer<P> {
+    FloatImage::from_buffer(buffer)
+        .correlate(kernel, border)
+        .into_buffer()
+}
+
+/// Filters the buffer with the separable kernel `vertical^T * horizontal`,
+/// applying the horizontal pass first.
+pub fn convolve_separable<P: Pixel>(
+    buffer: &PixelBuffer<P>,
+    horizontal: &[f32],
+    vertical: &[f32],
+    border: BorderMode,
+) -> PixelBuffer<P> {
+    FloatImage::from_buffer(buffer)
+        .correlate_separable(horizontal, vertical, border)
+        .into_buffer()
+}
+
+/// Blurs the buffer with a Gaussian of standard deviation `sigma` pixels.
+pub fn gaussian_blur<P: Pixel>(
+    buffer: &PixelBuffer<P>,
+    sigma: f32,
+    border: BorderMode,
+) -> PixelBuffer<P> {
+    let kernel = gaussian_kernel_1d(sigma);
+    convolve_separable(buffer, &kernel, &kernel, border)
+}
+
+/// Replaces each pixel by the mean of its `(2 * radius + 1)^2`
+/// neighbourhood.
+pub fn box_blur<P: Pixel>(
+    buffer: &PixelBuffer<P>,
+    radius: u32,
+    border: BorderMode,
+) -> PixelBuffer<P> {
+    let kernel = box_kernel_1d(radius);
+    convolve_separable(buffer, &kernel, &kernel, border)
+}
+
+/// Sharpens the buffer by adding `amount` times the difference between the
+/// image and its Gaussian blurred version.
+pub fn unsharp_mask<P: Pixel>(
+    buffer: &PixelBuffer<P>,
+    sigma: f32,
+    amount: f32,
+    border: BorderMode,
+) -> PixelBuffer<P> {
+    let src = FloatImage::from_buffer(buffer);
+    let kernel = gaussian_kernel_1d(sigma);
+    let mut out = src.correlate_separable(&kernel, &kernel, border);
+    for (blurred, orig) in out.data.iter_mut().zip(src.data.iter()) {
+        *blurred = orig + amount * (orig - *blurred);
+    }
+    out.into_buffer()
+}
+
+/// Horizontal and vertical derivatives of an image, stored as `f32` samples.
+pub type Gradients<P> = (
+    PixelBuffer<<P as Pixel>::WithSubpixel<f32>>,
+    PixelBuffer<<P as Pixel>::WithSubpixel<f32>>,
+);
+
+fn gradients<P: Pixel>(
+    buffer: &PixelBuffer<P>,
+    smooth: &[f32],
+    border: BorderMode,
+) -> Gradients<P> {
+    let src = FloatImage::from_buffer(buffer);
+    let derivative = [-1.0, 0.0, 1.0];
+    let gx = src.correlate_separable(&derivative, smooth, border);
+    let gy = src.correlate_separable(smooth, &derivative, border);
+    (gx.into_buffer(), gy.into_buffer())
+}
+
+/// Computes the horizontal and vertical image derivatives per channel with
+/// the 3x3 Sobel operator.
+pub fn sobel<P: Pixel>(buffer: &PixelBuffer<P>, border: BorderMode) -> Gradients<P> {
+    gradients(buffer, &[1.0, 2.0, 1.0], border)
+}
+
+/// Computes the horizontal and vertical image derivatives per channel with
+/// the 3x3 Scharr operator, which is more rotationally symmetric than Sobel.
+pub fn scharr<P: Pixel>(buffer: &PixelBuffer<P>, border: BorderMode) -> Gradients<P> {
+    gradients(buffer, &[3.0, 10.0, 3.0], border)
+}
+
+/// Replaces each channel of each pixel by the median of its
+/// `(2 * radius + 1)^2` neighbourhood.
+pub fn median_filter<P: Pixel>(
+    buffer: &PixelBuffer<P>,
+    radius: u32,
+    border: BorderMode,
+) -> PixelBuffer<P> {
+    let src = FloatImage::from_buffer(buffer);
+    let mut out = src.zeros_like();
+    let r = radius as i64;
+    let mut values = Vec::with_capacity(((2 * r + 1) * (2 * r + 1)) as usize);
+    for y in 0..src.height as i64 {
+        for x in 0..src.width as i64 {
+            let index = src.index(x as usize, y as usize);
+            for c in 0..src.n_channels {
+                values.clear();
+                for dy in -r..=r {
+                    for dx in -r..=r {
+                        values.push(src.get(x + dx, y + dy, c, border));
+                    }
+                }
+                let mid = values.len() / 2;
+                let (_, median, _) = values.select_nth_unstable_by(mid, |a, b| a.total_cmp(b));
+                out.data[index + c] = *median;
+            }
+        }
+    }
+    out.into_buffer()
+}
+
+/// Edge-preserving smoothing: each pixel is replaced by a weighted mean of
+/// its neighbours, weighted both by their spatial distance (`sigma_spatial`,
+/// in pixels) and by the euclidean distance between the pixel values
+/// (`sigma_range`, in sample units).
+pub fn bilateral_filter<P: Pixel>(
+    buffer: &PixelBuffer<P>,
+    sigma_spatial: f32,
+    sigma_range: f32,
+    border: BorderMode,
+) -> PixelBuffer<P> {
+    assert!(
+        sigma_spatial > 0.0 && sigma_range > 0.0,
+        "bilateral filter sigmas must be positive"
+    );
+    let src = FloatImage::from_buffer(buffer);
+    let mut out = src.zeros_like();
+    let r = (2.0 * sigma_spatial).ceil() as i64;
+    let n = src.n_channels;
+    let mut sum = vec![0.0; n];
+    let mut neighbour = vec![0.0; n];
+    for y in 0..src.height as i64 {
+        for x in 0..src.width as i64 {
+            let index = src.index(x as usize, y as usize);
+            let center = &src.data[index..index + n];
+            sum.iter_mut().for_each(|s| *s = 0.0);
+            let mut weight_sum = 0.0;
+            for dy in -r..=r {
+                for dx in -r..=r {
+                    let mut dist2 = 0.0;
+                    for c in 0..n {
+                        neighbour[c] = src.get(x + dx, y + dy, c, border);
+                        dist2 += (neighbour[c] - center[c]) * (neighbour[c] - center[c]);
+                    }
+                    let spatial =
+                        (dx * dx + dy * dy) as f32 / (2.0 * sigma_spatial * sigma_spatial);
+                    let range = dist2 / (2.0 * sigma_range * sigma_range);
+                    let w = (-spatial - range).exp();
+                    weight_sum += w;
+                    for (s, v) in sum.iter_mut().zip(&neighbour) {
+                        *s += w * v;
+                    }
+                }
+            }
+            for (o, s) in out.data[index..index + n].iter_mut().zip(&sum) {
+                *o = s / weight_sum;
+            }
+        }
+    }
+    out.into_buffer()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Vec1, Vec3};
+
+    #[test]
+    fn border_modes() {
+        assert_eq!(BorderMode::Clamp.resolve(-2, 4), Some(0));
+        assert_eq!(BorderMode::Clamp.resolve(5, 4), Some(3));
+        assert_eq!(BorderMode::Wrap.resolve(-1, 4), Some(3));
+        assert_eq!(BorderMode::Wrap.resolve(4, 4), Some(0));
+        assert_eq!(BorderMode::Mirror.resolve(-1, 4), Some(1));
+        assert_eq!(BorderMode::Mirror.resolve(4, 4), Some(2));
+        assert_eq!(BorderMode::Constant(0.0).resolve(4, 4), None);
+    }
+
+    #[test]
+    fn blur_preserves_constant_image() {
+        let mut image = PixelBuffer::<Vec3<f32>>::new(9, 7);
+        image.fill(Vec3::from([0.25, 0.5, 1.0]));
+        for blurred in [
+            gaussian_blur(&image, 1.5, BorderMode::Mirror),
+            box_blur(&image, 2, BorderMode::Clamp),
+            bilateral_filter(&image, 1.0, 0.1, BorderMode::Wrap),
+        ] {
+            for (a, b) in blurred.samples().iter().zip(image.samples()) {
+                assert!((a - b).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn sobel_on_horizontal_ramp() {
+        let samples = (0..6 * 5).map(|i| (i % 6) as u8 * 10).collect();
+        let image = PixelBuffer::<Vec1<u8>>::from_samples(6, 5, samples);
+        let (gx, gy) = sobel(&image, BorderMode::Clamp);
+        assert_eq!(gx.pixel_at(2, 2).unwrap()[0], 80.0);
+        assert_eq!(gy.pixel_at(2, 2).unwrap()[0], 0.0);
+    }
+
+    #[test]
+    fn median_removes_outlier() {
+        let mut image = PixelBuffer::<Vec1<f32>>::new(5, 5);
+        image.fill(Vec1::from([1.0]));
+        image.pixel_at_mut(2, 2).unwrap()[0] = 1000.0;
+        let filtered = median_filter(&image, 1, BorderMode::Clamp);
+        assert!(filtered.samples().iter().all(|&s| s == 1.0));
+    }
+}
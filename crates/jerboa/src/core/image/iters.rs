@@ -1,5 +1,8 @@
 use crate::core::image::Pixel;
-use std::slice::{ChunksExact, ChunksExactMut};
+use std::{
+    iter::{Enumerate, Skip, StepBy},
+    slice::{ChunksExact, ChunksExactMut, IterMut},
+};
 
 /// Iterator over the pixels (reference) with coordinates.
 /// Pixel coordinates are in the range [0, width - 1] x [0, height - 1],
@@ -19,9 +22,15 @@ where
     P::Subpixel: 'a,
 {
     pub fn new(samples: &'a [P::Subpixel], width: u32) -> Self {
+        Self::starting_at(samples, width, 0)
+    }
+
+    /// Creates an iterator over a part of a buffer whose first pixel has the
+    /// flat index `first` in the buffer.
+    pub(crate) fn starting_at(samples: &'a [P::Subpixel], width: u32, first: usize) -> Self {
         Pixels {
             width,
-            count: 0,
+            count: first,
             chunks: samples.chunks_exact(P::N_CHANNELS),
         }
     }
@@ -97,9 +106,15 @@ where
     P::Subpixel: 'a,
 {
     pub fn new(samples: &'a mut [P::Subpixel], width: u32) -> Self {
+        Self::starting_at(samples, width, 0)
+    }
+
+    /// Creates an iterator over a part of a buffer whose first pixel has the
+    /// flat index `first` in the buffer.
+    pub(crate) fn starting_at(samples: &'a mut [P::Subpixel], width: u32, first: usize) -> Self {
         PixelsMut {
             width,
-            count: 0,
+            count: first,
             chunks: samples.chunks_exact_mut(P::N_CHANNELS),
         }
     }
@@ -148,3 +163,858 @@ where
         ))
     }
 }
+
+/// View of a single row of a pixel buffer.
+#[derive(Debug, Clone)]
+pub struct Row<'a, P: Pixel + 'a>
+where
+    P::Subpixel: 'a,
+{
+    y: u32,
+    samples: &'a [P::Subpixel],
+}
+
+impl<'a, P: Pixel> Row<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    pub fn new(y: u32, samples: &'a [P::Subpixel]) -> Self {
+        Row { y, samples }
+    }
+
+    /// Vertical coordinate of the row inside the buffer.
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+
+    /// Number of pixels in the row.
+    pub fn width(&self) -> u32 {
+        (self.samples.len() / P::N_CHANNELS) as u32
+    }
+
+    pub fn samples(&self) -> &'a [P::Subpixel] {
+        self.samples
+    }
+
+    pub fn pixel_at(&self, x: u32) -> Option<&'a P> {
+        let index = x as usize * P::N_CHANNELS;
+        self.samples
+            .get(index..index + P::N_CHANNELS)
+            .map(P::from_slice)
+    }
+
+    /// Iterates over the pixels of the row with their coordinates inside
+    /// the buffer.
+    pub fn pixels(&self) -> Pixels<'a, P> {
+        let width = self.width();
+        Pixels::starting_at(self.samples, width, self.y as usize * width as usize)
+    }
+}
+
+/// Mutable view of a single row of a pixel buffer.
+#[derive(Debug)]
+pub struct RowMut<'a, P: Pixel + 'a>
+where
+    P::Subpixel: 'a,
+{
+    y: u32,
+    samples: &'a mut [P::Subpixel],
+}
+
+impl<'a, P: Pixel> RowMut<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    pub fn new(y: u32, samples: &'a mut [P::Subpixel]) -> Self {
+        RowMut { y, samples }
+    }
+
+    /// Vertical coordinate of the row inside the buffer.
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+
+    /// Number of pixels in the row.
+    pub fn width(&self) -> u32 {
+        (self.samples.len() / P::N_CHANNELS) as u32
+    }
+
+    pub fn samples_mut(&mut self) -> &mut [P::Subpixel] {
+        self.samples
+    }
+
+    pub fn pixel_at_mut(&mut self, x: u32) -> Option<&mut P> {
+        let index = x as usize * P::N_CHANNELS;
+        self.samples
+            .get_mut(index..index + P::N_CHANNELS)
+            .map(P::from_slice_mut)
+    }
+
+    /// Iterates over the pixels of the row with their coordinates inside
+    /// the buffer.
+    pub fn pixels_mut(&mut self) -> PixelsMut<'_, P> {
+        let width = self.width();
+        let first = self.y as usize * width as usize;
+        PixelsMut::starting_at(self.samples, width, first)
+    }
+}
+
+/// Mutable view of a rectangular region of a pixel buffer.
+///
+/// The region is stored as one slice per row, so tiles of the same buffer
+/// never alias and can be handed out to different threads.
+#[derive(Debug)]
+pub struct TileMut<'a, P: Pixel + 'a>
+where
+    P::Subpixel: 'a,
+{
+    origin: (u32, u32),
+    width: u32,
+    rows: Vec<&'a mut [P::Subpixel]>,
+}
+
+impl<'a, P: Pixel> TileMut<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    pub fn new(origin: (u32, u32), width: u32, rows: Vec<&'a mut [P::Subpixel]>) -> Self {
+        TileMut {
+            origin,
+            width,
+            rows,
+        }
+    }
+
+    /// Coordinates of the top-left pixel of the tile inside the buffer.
+    pub fn origin(&self) -> (u32, u32) {
+        self.origin
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.rows.len() as u32
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width(), self.height())
+    }
+
+    pub(crate) fn push_row(&mut self, row: &'a mut [P::Subpixel]) {
+        self.rows.push(row);
+    }
+
+    /// Returns the pixel at the given coordinates relative to the tile
+    /// origin.
+    pub fn pixel_at_mut(&mut self, x: u32, y: u32) -> Option<&mut P> {
+        if x >= self.width {
+            return None;
+        }
+        let index = x as usize * P::N_CHANNELS;
+        self.rows
+            .get_mut(y as usize)
+            .map(|row| P::from_slice_mut(&mut row[index..index + P::N_CHANNELS]))
+    }
+
+    /// Iterates over the pixels of the tile with their coordinates inside
+    /// the buffer.
+    pub fn pixels_mut(&mut self) -> TilePixelsMut<'_, 'a, P> {
+        TilePixelsMut {
+            origin: (self.origin.0 as usize, self.origin.1 as usize),
+            next_row: 0,
+            rows: self.rows.iter_mut(),
+            current: None,
+        }
+    }
+}
+
+/// Iterator over the pixels (mutable reference) of a tile with pixel
+/// coordinates inside the buffer.
+#[derive(Debug)]
+pub struct TilePixelsMut<'t, 'a, P: Pixel + 'a>
+where
+    P::Subpixel: 'a,
+{
+    origin: (usize, usize),
+    next_row: usize,
+    rows: IterMut<'t, &'a mut [P::Subpixel]>,
+    current: Option<(usize, Enumerate<ChunksExactMut<'t, P::Subpixel>>)>,
+}
+
+impl<'t, 'a, P: Pixel + 'a> Iterator for TilePixelsMut<'t, 'a, P>
+where
+    P::Subpixel: 'a,
+{
+    type Item = ((usize, usize), &'t mut P);
+
+    fn next(&mut self) -> Option<((usize, usize), &'t mut P)> {
+        loop {
+            if let Some((y, chunks)) = &mut self.current {
+                if let Some((x, chunk)) = chunks.next() {
+                    return Some(((self.origin.0 + x, *y), P::from_slice_mut(chunk)));
+                }
+            }
+            let row = self.rows.next()?;
+            self.current = Some((
+                self.origin.1 + self.next_row,
+                row.chunks_exact_mut(P::N_CHANNELS).enumerate(),
+            ));
+            self.next_row += 1;
+        }
+    }
+}
+
+/// Iterator over the rows of a pixel buffer, from top to bottom.
+#[derive(Debug, Clone)]
+pub struct Rows<'a, P: Pixel + 'a>
+where
+    P::Subpixel: 'a,
+{
+    y: u32,
+    chunks: ChunksExact<'a, P::Subpixel>,
+}
+
+impl<'a, P: Pixel> Rows<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    pub fn new(samples: &'a [P::Subpixel], width: u32) -> Self {
+        Rows {
+            y: 0,
+            chunks: samples.chunks_exact((width as usize * P::N_CHANNELS).max(1)),
+        }
+    }
+}
+
+impl<'a, P: Pixel + 'a> Iterator for Rows<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    type Item = Row<'a, P>;
+
+    fn next(&mut self) -> Option<Row<'a, P>> {
+        let chunk = self.chunks.next()?;
+        self.y += 1;
+        Some(Row::new(self.y - 1, chunk))
+    }
+}
+
+impl<'a, P: Pixel + 'a> ExactSizeIterator for Rows<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    fn len(&self) -> usize {
+        self.chunks.len()
+    }
+}
+
+/// Iterator over the rows (mutable) of a pixel buffer, from top to bottom.
+#[derive(Debug)]
+pub struct RowsMut<'a, P: Pixel + 'a>
+where
+    P::Subpixel: 'a,
+{
+    y: u32,
+    chunks: ChunksExactMut<'a, P::Subpixel>,
+}
+
+impl<'a, P: Pixel> RowsMut<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    pub fn new(samples: &'a mut [P::Subpixel], width: u32) -> Self {
+        RowsMut {
+            y: 0,
+            chunks: samples.chunks_exact_mut((width as usize * P::N_CHANNELS).max(1)),
+        }
+    }
+}
+
+impl<'a, P: Pixel + 'a> Iterator for RowsMut<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    type Item = RowMut<'a, P>;
+
+    fn next(&mut self) -> Option<RowMut<'a, P>> {
+        let chunk = self.chunks.next()?;
+        self.y += 1;
+        Some(RowMut::new(self.y - 1, chunk))
+    }
+}
+
+impl<'a, P: Pixel + 'a> ExactSizeIterator for RowsMut<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    fn len(&self) -> usize {
+        self.chunks.len()
+    }
+}
+
+/// Iterator over the rows of a pixel buffer yielding the row index together
+/// with an iterator over the pixels of the row and their coordinates.
+#[derive(Debug, Clone)]
+pub struct EnumerateRows<'a, P: Pixel + 'a>
+where
+    P::Subpixel: 'a,
+{
+    rows: Rows<'a, P>,
+}
+
+impl<'a, P: Pixel> EnumerateRows<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    pub fn new(samples: &'a [P::Subpixel], width: u32) -> Self {
+        EnumerateRows {
+            rows: Rows::new(samples, width),
+        }
+    }
+}
+
+impl<'a, P: Pixel + 'a> Iterator for EnumerateRows<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    type Item = (usize, Pixels<'a, P>);
+
+    fn next(&mut self) -> Option<(usize, Pixels<'a, P>)> {
+        let row = self.rows.next()?;
+        Some((row.y() as usize, row.pixels()))
+    }
+}
+
+/// View of a single column of a pixel buffer.
+#[derive(Debug, Clone)]
+pub struct Column<'a, P: Pixel + 'a>
+where
+    P::Subpixel: 'a,
+{
+    x: u32,
+    width: u32,
+    samples: &'a [P::Subpixel],
+}
+
+impl<'a, P: Pixel> Column<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    pub(crate) fn new(x: u32, width: u32, samples: &'a [P::Subpixel]) -> Self {
+        Column { x, width, samples }
+    }
+
+    /// Horizontal coordinate of the column inside the buffer.
+    pub fn x(&self) -> u32 {
+        self.x
+    }
+
+    /// Number of pixels in the column.
+    pub fn height(&self) -> u32 {
+        (self.samples.len() / (self.width as usize * P::N_CHANNELS)) as u32
+    }
+
+    pub fn pixel_at(&self, y: u32) -> Option<&'a P> {
+        let index = (y as usize * self.width as usize + self.x as usize) * P::N_CHANNELS;
+        self.samples
+            .get(index..index + P::N_CHANNELS)
+            .map(P::from_slice)
+    }
+
+    /// Iterates over the pixels of the column, from top to bottom, with
+    /// their coordinates inside the buffer.
+    pub fn pixels(&self) -> ColumnPixels<'a, P> {
+        ColumnPixels {
+            x: self.x as usize,
+            y: 0,
+            chunks: self
+                .samples
+                .chunks_exact(P::N_CHANNELS)
+                .skip(self.x as usize)
+                .step_by(self.width as usize),
+        }
+    }
+}
+
+/// Iterator over the pixels of a column with coordinates.
+#[derive(Debug, Clone)]
+pub struct ColumnPixels<'a, P: Pixel + 'a>
+where
+    P::Subpixel: 'a,
+{
+    x: usize,
+    y: usize,
+    chunks: StepBy<Skip<ChunksExact<'a, P::Subpixel>>>,
+}
+
+impl<'a, P: Pixel + 'a> Iterator for ColumnPixels<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    type Item = ((usize, usize), &'a P);
+
+    fn next(&mut self) -> Option<((usize, usize), &'a P)> {
+        let chunk = self.chunks.next()?;
+        self.y += 1;
+        Some(((self.x, self.y - 1), P::from_slice(chunk)))
+    }
+}
+
+/// Iterator over the columns of a pixel buffer, from left to right.
+#[derive(Debug, Clone)]
+pub struct Columns<'a, P: Pixel + 'a>
+where
+    P::Subpixel: 'a,
+{
+    x: u32,
+    width: u32,
+    samples: &'a [P::Subpixel],
+}
+
+impl<'a, P: Pixel> Columns<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    pub fn new(samples: &'a [P::Subpixel], width: u32) -> Self {
+        Columns {
+            x: 0,
+            width,
+            samples,
+        }
+    }
+}
+
+impl<'a, P: Pixel + 'a> Iterator for Columns<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    type Item = Column<'a, P>;
+
+    fn next(&mut self) -> Option<Column<'a, P>> {
+        if self.x >= self.width {
+            return None;
+        }
+        self.x += 1;
+        Some(Column::new(self.x - 1, self.width, self.samples))
+    }
+}
+
+/// View of a rectangular region of a pixel buffer.
+#[derive(Debug, Clone)]
+pub struct Tile<'a, P: Pixel + 'a>
+where
+    P::Subpixel: 'a,
+{
+    origin: (u32, u32),
+    width: u32,
+    height: u32,
+    stride: u32,
+    samples: &'a [P::Subpixel],
+}
+
+impl<'a, P: Pixel> Tile<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    /// Creates a view of the region of `width` x `height` pixels starting at
+    /// `origin` inside a buffer of width `stride`.
+    pub fn new(
+        origin: (u32, u32),
+        width: u32,
+        height: u32,
+        stride: u32,
+        samples: &'a [P::Subpixel],
+    ) -> Self {
+        Tile {
+            origin,
+            width,
+            height,
+            stride,
+            samples,
+        }
+    }
+
+    /// Coordinates of the top-left pixel of the tile inside the buffer.
+    pub fn origin(&self) -> (u32, u32) {
+        self.origin
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Returns the pixel at the given coordinates relative to the tile
+    /// origin.
+    pub fn pixel_at(&self, x: u32, y: u32) -> Option<&'a P> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let index = ((self.origin.1 + y) as usize * self.stride as usize
+            + (self.origin.0 + x) as usize)
+            * P::N_CHANNELS;
+        Some(P::from_slice(&self.samples[index..index + P::N_CHANNELS]))
+    }
+
+    /// Iterates over the pixels of the tile with their coordinates inside
+    /// the buffer.
+    pub fn pixels(&self) -> TilePixels<'a, P> {
+        TilePixels {
+            tile: self.clone(),
+            count: 0,
+        }
+    }
+}
+
+/// Iterator over the pixels of a tile with pixel coordinates inside the
+/// buffer.
+#[derive(Debug, Clone)]
+pub struct TilePixels<'a, P: Pixel + 'a>
+where
+    P::Subpixel: 'a,
+{
+    tile: Tile<'a, P>,
+    count: u32,
+}
+
+impl<'a, P: Pixel + 'a> Iterator for TilePixels<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    type Item = ((usize, usize), &'a P);
+
+    fn next(&mut self) -> Option<((usize, usize), &'a P)> {
+        if self.tile.width == 0 {
+            return None;
+        }
+        let (x, y) = (self.count % self.tile.width, self.count / self.tile.width);
+        let pixel = self.tile.pixel_at(x, y)?;
+        self.count += 1;
+        Some((
+            (
+                (self.tile.origin.0 + x) as usize,
+                (self.tile.origin.1 + y) as usize,
+            ),
+            pixel,
+        ))
+    }
+}
+
+/// Iterator over fixed-size tiles of a pixel buffer, in scanline order.
+///
+/// Tiles on the right and bottom borders are clipped to the buffer.
+#[derive(Debug, Clone)]
+pub struct Tiles<'a, P: Pixel + 'a>
+where
+    P::Subpixel: 'a,
+{
+    width: u32,
+    height: u32,
+    tile_width: u32,
+    tile_height: u32,
+    next: (u32, u32),
+    samples: &'a [P::Subpixel],
+}
+
+impl<'a, P: Pixel> Tiles<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    pub fn new(samples: &'a [P::Subpixel], width: u32, tile_width: u32, tile_height: u32) -> Self {
+        assert!(
+            tile_width > 0 && tile_height > 0,
+            "tile dimensions must be non-zero"
+        );
+        let height = if width == 0 {
+            0
+        } else {
+            (samples.len() / (width as usize * P::N_CHANNELS)) as u32
+        };
+        Tiles {
+            width,
+            height,
+            tile_width,
+            tile_height,
+            next: (0, 0),
+            samples,
+        }
+    }
+}
+
+impl<'a, P: Pixel + 'a> Iterator for Tiles<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    type Item = Tile<'a, P>;
+
+    fn next(&mut self) -> Option<Tile<'a, P>> {
+        let (x, y) = self.next;
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.next = if x + self.tile_width >= self.width {
+            (0, y + self.tile_height)
+        } else {
+            (x + self.tile_width, y)
+        };
+        Some(Tile::new(
+            (x, y),
+            self.tile_width.min(self.width - x),
+            self.tile_height.min(self.height - y),
+            self.width,
+            self.samples,
+        ))
+    }
+}
+
+/// Iterator over fixed-size mutable tiles of a pixel buffer, in scanline
+/// order.
+///
+/// Tiles on the right and bottom borders are clipped to the buffer.
+#[derive(Debug)]
+pub struct TilesMut<'a, P: Pixel + 'a>
+where
+    P::Subpixel: 'a,
+{
+    tiles: std::vec::IntoIter<TileMut<'a, P>>,
+}
+
+impl<'a, P: Pixel> TilesMut<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    pub fn new(
+        samples: &'a mut [P::Subpixel],
+        width: u32,
+        tile_width: u32,
+        tile_height: u32,
+    ) -> Self {
+        assert!(
+            tile_width > 0 && tile_height > 0,
+            "tile dimensions must be non-zero"
+        );
+        let mut tiles = Vec::new();
+        if width > 0 {
+            let n_tiles_x = width.div_ceil(tile_width);
+            let row_len = width as usize * P::N_CHANNELS;
+            let tile_row_len = tile_width as usize * P::N_CHANNELS;
+            for (ty, band) in samples
+                .chunks_mut(row_len * tile_height as usize)
+                .enumerate()
+            {
+                let first = tiles.len();
+                for tx in 0..n_tiles_x {
+                    tiles.push(TileMut::new(
+                        (tx * tile_width, ty as u32 * tile_height),
+                        tile_width.min(width - tx * tile_width),
+                        Vec::with_capacity(tile_height as usize),
+                    ));
+                }
+                for row in band.chunks_exact_mut(row_len) {
+                    for (tile, part) in tiles[first..].iter_mut().zip(row.chunks_mut(tile_row_len))
+                    {
+                        tile.push_row(part);
+                    }
+                }
+            }
+        }
+        TilesMut {
+            tiles: tiles.into_iter(),
+        }
+    }
+}
+
+impl<'a, P: Pixel + 'a> Iterator for TilesMut<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    type Item = TileMut<'a, P>;
+
+    fn next(&mut self) -> Option<TileMut<'a, P>> {
+        self.tiles.next()
+    }
+}
+
+impl<'a, P: Pixel + 'a> ExactSizeIterator for TilesMut<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    fn len(&self) -> usize {
+        self.tiles.len()
+    }
+}
+
+/// Square neighbourhood of `(2 * radius + 1)^2` pixels centred on a pixel.
+///
+/// Accesses outside of the buffer are clamped to the nearest edge pixel.
+#[derive(Debug, Clone)]
+pub struct Window<'a, P: Pixel + 'a>
+where
+    P::Subpixel: 'a,
+{
+    center: (u32, u32),
+    radius: u32,
+    width: u32,
+    height: u32,
+    samples: &'a [P::Subpixel],
+}
+
+impl<'a, P: Pixel> Window<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    /// Coordinates of the centre pixel inside the buffer.
+    pub fn center(&self) -> (u32, u32) {
+        self.center
+    }
+
+    pub fn radius(&self) -> u32 {
+        self.radius
+    }
+
+    /// Returns the pixel at offset `(dx, dy)` from the centre.
+    pub fn at(&self, dx: i32, dy: i32) -> &'a P {
+        let x = (self.center.0 as i64 + dx as i64).clamp(0, self.width as i64 - 1) as usize;
+        let y = (self.center.1 as i64 + dy as i64).clamp(0, self.height as i64 - 1) as usize;
+        let index = (y * self.width as usize + x) * P::N_CHANNELS;
+        P::from_slice(&self.samples[index..index + P::N_CHANNELS])
+    }
+
+    /// Iterates over the pixels of the neighbourhood in scanline order,
+    /// together with their offsets from the centre.
+    pub fn pixels(&self) -> impl Iterator<Item = ((i32, i32), &'a P)> + '_ {
+        let r = self.radius as i32;
+        (-r..=r).flat_map(move |dy| (-r..=r).map(move |dx| ((dx, dy), self.at(dx, dy))))
+    }
+}
+
+/// Iterator over the neighbourhoods of every pixel of a buffer, in scanline
+/// order.
+#[derive(Debug, Clone)]
+pub struct Windows<'a, P: Pixel + 'a>
+where
+    P::Subpixel: 'a,
+{
+    radius: u32,
+    width: u32,
+    height: u32,
+    count: usize,
+    samples: &'a [P::Subpixel],
+}
+
+impl<'a, P: Pixel> Windows<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    pub fn new(samples: &'a [P::Subpixel], width: u32, radius: u32) -> Self {
+        let height = if width == 0 {
+            0
+        } else {
+            (samples.len() / (width as usize * P::N_CHANNELS)) as u32
+        };
+        Windows {
+            radius,
+            width,
+            height,
+            count: 0,
+            samples,
+        }
+    }
+}
+
+impl<'a, P: Pixel + 'a> Iterator for Windows<'a, P>
+where
+    P::Subpixel: 'a,
+{
+    type Item = ((usize, usize), Window<'a, P>);
+
+    fn next(&mut self) -> Option<((usize, usize), Window<'a, P>)> {
+        if self.count >= self.width as usize * self.height as usize {
+            return None;
+        }
+        let (x, y) = (
+            self.count % self.width as usize,
+            self.count / self.width as usize,
+        );
+        self.count += 1;
+        Some((
+            (x, y),
+            Window {
+                center: (x as u32, y as u32),
+                radius: self.radius,
+                width: self.width,
+                height: self.height,
+                samples: self.samples,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{image::PixelBuffer, Vec1};
+
+    fn indexed(width: u32, height: u32) -> PixelBuffer<Vec1<u32>> {
+        PixelBuffer::from_samples(width, height, (0..width * height).collect())
+    }
+
+    #[test]
+    fn rows_and_columns_coordinates() {
+        let image = indexed(7, 5);
+        for (y, pixels) in image.enumerate_rows() {
+            for ((px, py), pixel) in pixels {
+                assert_eq!(py, y);
+                assert_eq!(pixel[0], (py * 7 + px) as u32);
+            }
+        }
+        assert_eq!(image.columns().count(), 7);
+        for column in image.columns() {
+            assert_eq!(column.height(), 5);
+            for ((x, y), pixel) in column.pixels() {
+                assert_eq!(x, column.x() as usize);
+                assert_eq!(pixel[0], (y * 7 + x) as u32);
+            }
+        }
+    }
+
+    #[test]
+    fn tiles_cover_buffer_once() {
+        let mut image = indexed(10, 7);
+        let mut visited = vec![0; 70];
+        for tile in image.tiles(4, 3) {
+            for ((x, y), pixel) in tile.pixels() {
+                assert_eq!(pixel[0], (y * 10 + x) as u32);
+                visited[y * 10 + x] += 1;
+            }
+        }
+        assert!(visited.iter().all(|&n| n == 1));
+        assert_eq!(image.tiles_mut(4, 3).len(), 9);
+        for mut tile in image.tiles_mut(4, 3) {
+            for (_, pixel) in tile.pixels_mut() {
+                pixel[0] = 0;
+            }
+        }
+        assert!(image.samples().iter().all(|&s| s == 0));
+    }
+
+    #[test]
+    fn windows_clamp_to_edge() {
+        let image = indexed(4, 3);
+        let (_, window) = image.windows(1).next().unwrap();
+        assert_eq!(window.at(-1, -1)[0], 0);
+        assert_eq!(window.at(1, 1)[0], 5);
+        assert_eq!(window.pixels().count(), 9);
+        let ((x, y), last) = image.windows(2).last().unwrap();
+        assert_eq!((x, y), (3, 2));
+        assert_eq!(last.at(2, 2)[0], 11);
+    }
+}
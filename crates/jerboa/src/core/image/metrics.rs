@@ -0,0 +1,506 @@
+//! Image comparison metrics.
+//!
+//! Scalar scores are meant for regression tests comparing a rendered image
+//! against a reference, and the per-pixel error maps can be turned into a
+//! heatmap for visual inspection. All metrics treat raw sample values
+//! without normalisation, so `peak` arguments must be given in the units of
+//! the samples (e.g. 255 for 8-bit images, 1 for LDR float images).
+
+use crate::core::{
+    image::{
+        filter::{self, BorderMode, Kernel},
+        ImageBuffer, Pixel, PixelBuffer, Sample,
+    },
+    Vec1, Vec3,
+};
+
+fn assert_same_dimensions<P: Pixel, Q: Pixel>(a: &PixelBuffer<P>, b: &PixelBuffer<Q>) {
+    assert_eq!(
+        a.dimensions(),
+        b.dimensions(),
+        "buffer dimensions mismatch while comparing pixel buffers"
+    );
+}
+
+fn mean_of<I: Iterator<Item = f64>>(iter: I) -> f64 {
+    let (sum, n) = iter.fold((0.0, 0usize), |(sum, n), x| (sum + x, n + 1));
+    if n == 0 {
+        0.0
+    } else {
+        sum / n as f64
+    }
+}
+
+/// Mean squared error over all samples.
+pub fn mse<P: Pixel>(a: &PixelBuffer<P>, b: &PixelBuffer<P>) -> f64 {
+    assert_same_dimensions(a, b);
+    mean_of(a.samples().iter().zip(b.samples()).map(|(x, y)| {
+        let d = x.to_f32() as f64 - y.to_f32() as f64;
+        d * d
+    }))
+}
+
+/// Root mean squared error over all samples.
+pub fn rmse<P: Pixel>(a: &PixelBuffer<P>, b: &PixelBuffer<P>) -> f64 {
+    mse(a, b).sqrt()
+}
+
+/// Peak signal-to-noise ratio in decibels, infinite for identical images.
+pub fn psnr<P: Pixel>(a: &PixelBuffer<P>, b: &PixelBuffer<P>, peak: f64) -> f64 {
+    let mse = mse(a, b);
+    if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (peak * peak / mse).log10()
+    }
+}
+
+/// Relative mean squared error of `test` with respect to `reference`:
+/// the mean of `(test - ref)^2 / (ref^2 + 0.01)`.
+///
+/// Unlike the MSE, the error is not dominated by the brightest pixels, which
+/// makes it suitable for HDR images.
+pub fn relative_mse<P: Pixel>(test: &PixelBuffer<P>, reference: &PixelBuffer<P>) -> f64 {
+    assert_same_dimensions(test, reference);
+    mean_of(
+        test.samples()
+            .iter()
+            .zip(reference.samples())
+            .map(|(t, r)| {
+                let (t, r) = (t.to_f32() as f64, r.to_f32() as f64);
+                (t - r) * (t - r) / (r * r + 0.01)
+            }),
+    )
+}
+
+/// Mean structural similarity index, computed per channel with an 11x11
+/// Gaussian window (sigma 1.5) and averaged over all pixels and channels.
+///
+/// Returns 1 for identical images.
+pub fn ssim<P: Pixel>(a: &PixelBuffer<P>, b: &PixelBuffer<P>, peak: f64) -> f64 {
+    assert_same_dimensions(a, b);
+    let c1 = ((0.01 * peak) * (0.01 * peak)) as f32;
+    let c2 = ((0.03 * peak) * (0.03 * peak)) as f32;
+    let a = a.convert::<f32>();
+    let b = b.convert::<f32>();
+    let product = |x: &PixelBuffer<P::WithSubpixel<f32>>, y: &PixelBuffer<P::WithSubpixel<f32>>| {
+        PixelBuffer::<P::WithSubpixel<f32>>::from_samples(
+            x.width(),
+            x.height(),
+            x.samples()
+                .iter()
+                .zip(y.samples())
+                .map(|(x, y)| x * y)
+                .collect(),
+        )
+    };
+    let blur =
+        |x: &PixelBuffer<P::WithSubpixel<f32>>| filter::gaussian_blur(x, 1.5, BorderMode::Mirror);
+    let mu_a = blur(&a);
+    let mu_b = blur(&b);
+    let aa = blur(&product(&a, &a));
+    let bb = blur(&product(&b, &b));
+    let ab = blur(&product(&a, &b));
+    mean_of((0..mu_a.samples().len()).map(|i| {
+        let (ma, mb) = (mu_a.samples()[i], mu_b.samples()[i]);
+        let var_a = aa.samples()[i] - ma * ma;
+        let var_b = bb.samples()[i] - mb * mb;
+        let cov = ab.samples()[i] - ma * mb;
+        (((2.0 * ma * mb + c1) * (2.0 * cov + c2))
+            / ((ma * ma + mb * mb + c1) * (var_a + var_b + c2))) as f64
+    }))
+}
+
+/// Absolute difference between two buffers, per channel.
+pub fn abs_difference<P: Pixel>(
+    a: &PixelBuffer<P>,
+    b: &PixelBuffer<P>,
+) -> PixelBuffer<P::WithSubpixel<f32>> {
+    assert_same_dimensions(a, b);
+    PixelBuffer::from_samples(
+        a.width(),
+        a.height(),
+        a.samples()
+            .iter()
+            .zip(b.samples())
+            .map(|(x, y)| (x.to_f32() - y.to_f32()).abs())
+            .collect(),
+    )
+}
+
+/// Per-pixel error: the mean absolute difference over the channels.
+pub fn error_map<P: Pixel>(a: &PixelBuffer<P>, b: &PixelBuffer<P>) -> PixelBuffer<Vec1<f32>> {
+    abs_difference(a, b).map(|p| {
+        let channels = p.channels();
+        Vec1::from([channels.iter().sum::<f32>() / channels.len() as f32])
+    })
+}
+
+/// Control points of the magma colour map.
+const MAGMA: [[f32; 3]; 9] = [
+    [0.0, 0.0, 4.0],
+    [28.0, 16.0, 68.0],
+    [79.0, 18.0, 123.0],
+    [129.0, 37.0, 129.0],
+    [181.0, 54.0, 122.0],
+    [229.0, 80.0, 100.0],
+    [251.0, 135.0, 97.0],
+    [254.0, 194.0, 135.0],
+    [252.0, 253.0, 191.0],
+];
+
+/// Maps a single channel error map to colours with the magma colour map.
+///
+/// Errors are divided by `max`, or by the largest error of the map if `None`,
+/// and clamped to `[0, 1]`. NaNs are shown in pure green so they stand out.
+pub fn heatmap(errors: &PixelBuffer<Vec1<f32>>, max: Option<f32>) -> PixelBuffer<Vec3<u8>> {
+    let max = max.unwrap_or_else(|| {
+        errors
+            .samples()
+            .iter()
+            .filter(|e| e.is_finite())
+            .fold(0.0f32, |m, &e| m.max(e))
+    });
+    let scale = if max > 0.0 { 1.0 / max } else { 0.0 };
+    errors.map(|e| {
+        if e[0].is_nan() {
+            return Vec3::from([0, 255, 0]);
+        }
+        let t = (e[0] * scale).clamp(0.0, 1.0) * (MAGMA.len() - 1) as f32;
+        let i = (t as usize).min(MAGMA.len() - 2);
+        let a = Vec3::from(MAGMA[i]);
+        let b = Vec3::from(MAGMA[i + 1]);
+        a.lerp(b, t - i as f32).map(|c| c.round() as u8)
+    })
+}
+
+/// Parameters of the [FLIP](https://research.nvidia.com/publication/2020-07_flip-difference-evaluator-alternating-images)
+/// perceptual error.
+mod flip_params {
+    /// Exponent applied to the colour difference.
+    pub const QC: f32 = 0.7;
+    /// Exponent applied to the feature difference.
+    pub const QF: f32 = 0.5;
+    /// Fraction of the maximum colour difference mapped to `PT`.
+    pub const PC: f32 = 0.4;
+    pub const PT: f32 = 0.95;
+    /// Width of the feature detection filters in degrees.
+    pub const FEATURE_WIDTH: f32 = 0.082;
+
+    /// Contrast sensitivity function parameters `(a1, b1, a2, b2)` of the
+    /// achromatic, red-green and blue-yellow channels.
+    pub const CSF: [(f32, f32, f32, f32); 3] = [
+        (1.0, 0.0047, 0.0, 1e-5),
+        (1.0, 0.0053, 0.0, 1e-5),
+        (34.1, 0.04, 13.5, 0.025),
+    ];
+}
+
+const D65_WHITE: [f32; 3] = [0.950_428_5, 1.0, 1.088_900_4];
+
+fn linear_rgb_to_xyz(c: [f32; 3]) -> [f32; 3] {
+    [
+        0.412_456_4 * c[0] + 0.357_576_1 * c[1] + 0.180_437_5 * c[2],
+        0.212_672_9 * c[0] + 0.715_152_2 * c[1] + 0.072_175 * c[2],
+        0.019_333_9 * c[0] + 0.119_192 * c[1] + 0.950_304_1 * c[2],
+    ]
+}
+
+fn xyz_to_linear_rgb(c: [f32; 3]) -> [f32; 3] {
+    [
+        3.240_454_2 * c[0] - 1.537_138_5 * c[1] - 0.498_531_4 * c[2],
+        -0.969_266 * c[0] + 1.876_010_8 * c[1] + 0.041_556 * c[2],
+        0.055_643_4 * c[0] - 0.204_025_9 * c[1] + 1.057_225_2 * c[2],
+    ]
+}
+
+fn xyz_to_ycxcz(c: [f32; 3]) -> [f32; 3] {
+    let [x, y, z] = [
+        c[0] / D65_WHITE[0],
+        c[1] / D65_WHITE[1],
+        c[2] / D65_WHITE[2],
+    ];
+    [116.0 * y - 16.0, 500.0 * (x - y), 200.0 * (y - z)]
+}
+
+fn ycxcz_to_xyz(c: [f32; 3]) -> [f32; 3] {
+    let y = (c[0] + 16.0) / 116.0;
+    [
+        (y + c[1] / 500.0) * D65_WHITE[0],
+        y * D65_WHITE[1],
+        (y - c[2] / 200.0) * D65_WHITE[2],
+    ]
+}
+
+/// Hunt-adjusted CIELab of a linear RGB colour.
+fn hunt_lab(rgb: [f32; 3]) -> [f32; 3] {
+    let f = |t: f32| {
+        let delta = 6.0f32 / 29.0;
+        if t > delta * delta * delta {
+            t.cbrt()
+        } else {
+            t / (3.0 * delta * delta) + 4.0 / 29.0
+        }
+    };
+    let xyz = linear_rgb_to_xyz(rgb);
+    let [fx, fy, fz] = [
+        f(xyz[0] / D65_WHITE[0]),
+        f(xyz[1] / D65_WHITE[1]),
+        f(xyz[2] / D65_WHITE[2]),
+    ];
+    let l = 116.0 * fy - 16.0;
+    [
+        l,
+        0.01 * l * 500.0 * (fx - fy),
+        0.01 * l * 200.0 * (fy - fz),
+    ]
+}
+
+fn hyab(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (a[0] - b[0]).abs() + ((a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+/// Spatial filter approximating the contrast sensitivity of a channel.
+fn csf_kernel(pixels_per_degree: f32, (a1, b1, a2, b2): (f32, f32, f32, f32)) -> Kernel {
+    let b_max = flip_params::CSF
+        .iter()
+        .fold(0.0f32, |m, &(_, b1, _, b2)| m.max(b1).max(b2));
+    let r = (3.0 * (b_max / (2.0 * std::f32::consts::PI.powi(2))).sqrt() * pixels_per_degree).ceil()
+        as i32;
+    let pi2 = std::f32::consts::PI.powi(2);
+    let weights = (-r..=r)
+        .flat_map(|y| (-r..=r).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let d2 = (x * x + y * y) as f32 / (pixels_per_degree * pixels_per_degree);
+            a1 * (std::f32::consts::PI / b1).sqrt() * (-pi2 * d2 / b1).exp()
+                + a2 * (std::f32::consts::PI / b2).sqrt() * (-pi2 * d2 / b2).exp()
+        })
+        .collect();
+    Kernel::new((2 * r + 1) as u32, (2 * r + 1) as u32, weights).normalized()
+}
+
+/// Edge (first derivative of Gaussian) or point (second derivative) detection
+/// kernels along x and y, with positive and negative weights normalised
+/// separately.
+fn feature_kernels(pixels_per_degree: f32, second_order: bool) -> (Kernel, Kernel) {
+    let sd = 0.5 * flip_params::FEATURE_WIDTH * pixels_per_degree;
+    let r = (3.0 * sd).ceil() as i32;
+    let n = (2 * r + 1) as u32;
+    let gx = (-r..=r)
+        .flat_map(|y| (-r..=r).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let (x, y) = (x as f32, y as f32);
+            let g = (-(x * x + y * y) / (2.0 * sd * sd)).exp();
+            if second_order {
+                (x * x / (sd * sd) - 1.0) * g
+            } else {
+                -x * g
+            }
+        })
+        .collect::<Vec<_>>();
+    let pos: f32 = gx.iter().filter(|w| **w > 0.0).sum();
+    let neg: f32 = -gx.iter().filter(|w| **w < 0.0).sum::<f32>();
+    let gx = gx
+        .into_iter()
+        .map(|w| if w > 0.0 { w / pos } else { w / neg })
+        .collect::<Vec<_>>();
+    let gy = (0..n as usize)
+        .flat_map(|y| (0..n as usize).map(move |x| (x, y)))
+        .map(|(x, y)| gx[x * n as usize + y])
+        .collect();
+    (Kernel::new(n, n, gx), Kernel::new(n, n, gy))
+}
+
+fn feature_magnitude(luminance: &PixelBuffer<Vec1<f32>>, kernels: &(Kernel, Kernel)) -> Vec<f32> {
+    let gx = filter::convolve(luminance, &kernels.0, BorderMode::Clamp);
+    let gy = filter::convolve(luminance, &kernels.1, BorderMode::Clamp);
+    gx.samples()
+        .iter()
+        .zip(gy.samples())
+        .map(|(x, y)| (x * x + y * y).sqrt())
+        .collect()
+}
+
+/// FLIP-style perceptual error map between two linear RGB images with
+/// values in `[0, 1]` (tone map HDR images first), viewed at
+/// `pixels_per_degree` (67 for a 0.7 m viewing distance on a 24" 4K
+/// monitor).
+///
+/// Colour differences are computed after filtering both images with
+/// contrast sensitivity functions in an opponent colour space, and are
+/// amplified where the images differ in edges or points. The error is in
+/// `[0, 1]`.
+pub fn flip_error(
+    test: &PixelBuffer<Vec3<f32>>,
+    reference: &PixelBuffer<Vec3<f32>>,
+    pixels_per_degree: f32,
+) -> PixelBuffer<Vec1<f32>> {
+    assert_same_dimensions(test, reference);
+    let (width, height) = test.dimensions();
+    let csf = flip_params::CSF.map(|params| csf_kernel(pixels_per_degree, params));
+    let edges = feature_kernels(pixels_per_degree, false);
+    let points = feature_kernels(pixels_per_degree, true);
+
+    // Filters the opponent channels of an image, returning the filtered
+    // colours in linear RGB and the luminance used for feature detection.
+    let preprocess = |image: &PixelBuffer<Vec3<f32>>| {
+        let ycxcz = image.map(|p| Vec3::from(xyz_to_ycxcz(linear_rgb_to_xyz(**p))));
+        let filtered = (0..3)
+            .map(|c| {
+                let channel = ycxcz.map(|p| Vec1::from([p[c]]));
+                filter::convolve(&channel, &csf[c], BorderMode::Clamp)
+            })
+            .collect::<Vec<_>>();
+        let colors = (0..(width * height) as usize)
+            .map(|i| {
+                let c = [
+                    filtered[0].samples()[i],
+                    filtered[1].samples()[i],
+                    filtered[2].samples()[i],
+                ];
+                hunt_lab(xyz_to_linear_rgb(ycxcz_to_xyz(c)).map(|v| v.clamp(0.0, 1.0)))
+            })
+            .collect::<Vec<_>>();
+        let luminance = ycxcz.map(|p| Vec1::from([(p[0] + 16.0) / 116.0]));
+        let edges = feature_magnitude(&luminance, &edges);
+        let points = feature_magnitude(&luminance, &points);
+        (colors, edges, points)
+    };
+
+    let (test_colors, test_edges, test_points) = preprocess(test);
+    let (ref_colors, ref_edges, ref_points) = preprocess(reference);
+
+    let c_max = hyab(hunt_lab([0.0, 1.0, 0.0]), hunt_lab([0.0, 0.0, 1.0])).powf(flip_params::QC);
+    let samples = (0..(width * height) as usize)
+        .map(|i| {
+            let d = hyab(test_colors[i], ref_colors[i]).powf(flip_params::QC);
+            let de_c = if d < flip_params::PC * c_max {
+                flip_params::PT / (flip_params::PC * c_max) * d
+            } else {
+                flip_params::PT
+                    + (d - flip_params::PC * c_max) / (c_max - flip_params::PC * c_max)
+                        * (1.0 - flip_params::PT)
+            };
+            let de_f = ((test_edges[i] - ref_edges[i])
+                .abs()
+                .max((test_points[i] - ref_points[i]).abs())
+                / std::f32::consts::SQRT_2)
+                .powf(flip_params::QF);
+            de_c.powf(1.0 - de_f)
+        })
+        .collect();
+    PixelBuffer::from_samples(width, height, samples)
+}
+
+/// Mean of the [FLIP-style error map](flip_error).
+pub fn flip(
+    test: &PixelBuffer<Vec3<f32>>,
+    reference: &PixelBuffer<Vec3<f32>>,
+    pixels_per_degree: f32,
+) -> f64 {
+    mean_of(
+        flip_error(test, reference, pixels_per_degree)
+            .samples()
+            .iter()
+            .map(|&e| e as f64),
+    )
+}
+
+/// Applies `$f` to the pixel buffers of two image buffers holding the same
+/// pixel type, or evaluates to `None` if the pixel types differ.
+macro_rules! dispatch_pair {
+    ($a:expr, $b:expr, |$x:ident, $y:ident| $f:expr) => {
+        match ($a, $b) {
+            (ImageBuffer::Bitmap($x), ImageBuffer::Bitmap($y)) => Some($f),
+            (ImageBuffer::Luma8($x), ImageBuffer::Luma8($y)) => Some($f),
+            (ImageBuffer::LumaA8($x), ImageBuffer::LumaA8($y)) => Some($f),
+            (ImageBuffer::Luma16($x), ImageBuffer::Luma16($y)) => Some($f),
+            (ImageBuffer::LumaA16($x), ImageBuffer::LumaA16($y)) => Some($f),
+            (ImageBuffer::Luma32F($x), ImageBuffer::Luma32F($y)) => Some($f),
+            (ImageBuffer::Rgb8($x), ImageBuffer::Rgb8($y)) => Some($f),
+            (ImageBuffer::RgbA8($x), ImageBuffer::RgbA8($y)) => Some($f),
+            (ImageBuffer::Rgb16($x), ImageBuffer::Rgb16($y)) => Some($f),
+            (ImageBuffer::RgbA16($x), ImageBuffer::RgbA16($y)) => Some($f),
+            (ImageBuffer::Rgb32F($x), ImageBuffer::Rgb32F($y)) => Some($f),
+            _ => None,
+        }
+    };
+}
+
+/// Comparisons of decoded images. Each returns `None` if the images don't
+/// have the same pixel type, and panics if their dimensions differ.
+impl ImageBuffer {
+    pub fn mse(&self, other: &ImageBuffer) -> Option<f64> {
+        dispatch_pair!(self, other, |a, b| mse(a, b))
+    }
+
+    pub fn rmse(&self, other: &ImageBuffer) -> Option<f64> {
+        dispatch_pair!(self, other, |a, b| rmse(a, b))
+    }
+
+    pub fn psnr(&self, other: &ImageBuffer, peak: f64) -> Option<f64> {
+        dispatch_pair!(self, other, |a, b| psnr(a, b, peak))
+    }
+
+    pub fn relative_mse(&self, reference: &ImageBuffer) -> Option<f64> {
+        dispatch_pair!(self, reference, |a, b| relative_mse(a, b))
+    }
+
+    pub fn ssim(&self, other: &ImageBuffer, peak: f64) -> Option<f64> {
+        dispatch_pair!(self, other, |a, b| ssim(a, b, peak))
+    }
+
+    pub fn error_map(&self, other: &ImageBuffer) -> Option<PixelBuffer<Vec1<f32>>> {
+        dispatch_pair!(self, other, |a, b| error_map(a, b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::image::PixelBufferRgb32f;
+
+    fn gradient(width: u32, height: u32) -> PixelBufferRgb32f {
+        let mut image = PixelBufferRgb32f::new(width, height);
+        for ((x, y), pixel) in image.pixels_mut() {
+            *pixel = Vec3::from([x as f32 / width as f32, y as f32 / height as f32, 0.5]);
+        }
+        image
+    }
+
+    #[test]
+    fn identical_images() {
+        let image = gradient(16, 12);
+        assert_eq!(mse(&image, &image), 0.0);
+        assert_eq!(psnr(&image, &image, 1.0), f64::INFINITY);
+        assert!((ssim(&image, &image, 1.0) - 1.0).abs() < 1e-6);
+        assert!(flip(&image, &image, 67.0) < 1e-6);
+    }
+
+    #[test]
+    fn known_errors() {
+        let a = PixelBuffer::<Vec1<u8>>::from_samples(2, 2, vec![0, 0, 0, 0]);
+        let b = PixelBuffer::<Vec1<u8>>::from_samples(2, 2, vec![2, 2, 2, 2]);
+        assert_eq!(mse(&a, &b), 4.0);
+        assert_eq!(rmse(&a, &b), 2.0);
+        assert!((psnr(&a, &b, 255.0) - 10.0 * (255.0f64 * 255.0 / 4.0).log10()).abs() < 1e-9);
+        assert_eq!(error_map(&a, &b).samples(), &[2.0; 4]);
+    }
+
+    #[test]
+    fn errors_grow_with_noise() {
+        let reference = gradient(24, 24);
+        let mut small = reference.clone();
+        let mut large = reference.clone();
+        for (i, (s, l)) in small.pixels_mut().zip(large.pixels_mut()).enumerate() {
+            let sign = if i % 2 == 0 { 1.0 } else { -1.0 };
+            *s.1 = (*s.1 + Vec3::splat(0.02 * sign)).clamp(0.0, 1.0);
+            *l.1 = (*l.1 + Vec3::splat(0.2 * sign)).clamp(0.0, 1.0);
+        }
+        assert!(ssim(&small, &reference, 1.0) > ssim(&large, &reference, 1.0));
+        assert!(relative_mse(&small, &reference) < relative_mse(&large, &reference));
+        assert!(flip(&small, &reference, 67.0) < flip(&large, &reference, 67.0));
+        let heat = heatmap(&error_map(&large, &reference), None);
+        assert_eq!(heat.dimensions(), reference.dimensions());
+    }
+}
@@ -14,7 +14,12 @@ use std::{
 pub mod buffer;
 pub mod codec;
 pub mod error;
+pub mod filter;
 pub mod iters;
+pub mod metrics;
+pub mod par;
+pub mod stats;
+pub mod texture;
 
 pub use buffer::*;
 
@@ -27,6 +32,13 @@ pub trait Sample: Copy + Clone + Default + Display + Debug {
     fn n_bytes() -> usize {
         Self::N_BYTES
     }
+
+    /// Converts the sample to a floating point value without normalisation.
+    fn to_f32(self) -> f32;
+
+    /// Converts a floating point value to a sample, rounding to the nearest
+    /// value and saturating at the bounds of integer sample types.
+    fn from_f32(val: f32) -> Self;
 }
 
 #[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
@@ -54,25 +66,58 @@ impl Display for Bit {
 
 impl Sample for Bit {
     const N_BYTES: usize = 1;
-}
 
-impl Sample for u8 {
-    const N_BYTES: usize = 1;
+    fn to_f32(self) -> f32 {
+        self.0 as f32
+    }
+
+    fn from_f32(val: f32) -> Self {
+        Bit((val >= 0.5) as u8)
+    }
 }
-impl Sample for u16 {
-    const N_BYTES: usize = 2;
+
+macro_rules! impl_sample_trait_for_int {
+    ($($t:ty, $n:expr;)*) => {
+        $(
+            impl Sample for $t {
+                const N_BYTES: usize = $n;
+
+                fn to_f32(self) -> f32 {
+                    self as f32
+                }
+
+                fn from_f32(val: f32) -> Self {
+                    // Float to int casts saturate and map NaN to zero.
+                    val.round() as $t
+                }
+            }
+        )*
+    };
 }
-impl Sample for u32 {
-    const N_BYTES: usize = 4;
+
+impl_sample_trait_for_int! {
+    u8, 1; u16, 2; u32, 4;
 }
+
 impl Sample for f32 {
     const N_BYTES: usize = 4;
+
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    fn from_f32(val: f32) -> Self {
+        val
+    }
 }
 
 pub trait Pixel: Copy + Clone + Default {
     /// Type of each channel.
     type Subpixel: Sample;
 
+    /// Pixel with the same number of channels but a different sample type.
+    type WithSubpixel<S: Sample>: Pixel<Subpixel = S>;
+
     /// Number of channels in the pixel.
     const N_CHANNELS: usize;
 
@@ -84,6 +129,12 @@ pub trait Pixel: Copy + Clone + Default {
 
     /// Returns a mutable view of the pixel into a slice.
     fn from_slice_mut(slice: &mut [Self::Subpixel]) -> &mut Self;
+
+    /// Returns the channels of the pixel as a slice.
+    fn channels(&self) -> &[Self::Subpixel];
+
+    /// Returns the channels of the pixel as a mutable slice.
+    fn channels_mut(&mut self) -> &mut [Self::Subpixel];
 }
 
 macro_rules! impl_pixel_trait {
@@ -92,6 +143,8 @@ macro_rules! impl_pixel_trait {
             impl<S: Sample> Pixel for $name<S> {
                 type Subpixel = S;
 
+                type WithSubpixel<T: Sample> = $name<T>;
+
                 const N_CHANNELS: usize = $channels;
 
                 fn from_slice(slice: &[S]) -> &Self {
@@ -107,6 +160,14 @@ macro_rules! impl_pixel_trait {
                         &mut *(slice as *mut [S] as *mut Self)
                     }
                 }
+
+                fn channels(&self) -> &[S] {
+                    &self.0
+                }
+
+                fn channels_mut(&mut self) -> &mut [S] {
+                    &mut self.0
+                }
             }
         )*
     };
@@ -121,12 +182,16 @@ impl_pixel_trait! {
 pub enum ImageFormat {
     /// Portable any-map format. See [Netpbm](https://en.wikipedia.org/wiki/Netpbm).
     Pnm,
+
+    /// OpenEXR format, encoding only. See [OpenEXR](https://openexr.com).
+    Exr,
 }
 
 impl Display for ImageFormat {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             ImageFormat::Pnm => write!(f, "Pnm"),
+            ImageFormat::Exr => write!(f, "Exr"),
         }
     }
 }
@@ -151,7 +216,8 @@ impl ImageFormat {
 
     fn _from_extension(ext: &str) -> Option<Self> {
         match ext.to_ascii_lowercase().as_ref() {
-            "pnm" => Some(ImageFormat::Pnm),
+            "pnm" | "pbm" | "pgm" | "ppm" | "pam" | "pfm" => Some(ImageFormat::Pnm),
+            "exr" => Some(ImageFormat::Exr),
             _ => None,
         }
     }
@@ -243,7 +309,10 @@ impl ImageDecoder<BufReader<File>> {
     pub fn decode(self) -> Result<ImageBuffer, ImageError> {
         match self.format {
             Some(ImageFormat::Pnm) => self.decode_pnm(),
-            _ => Err(ImageError::UnsupportedFormat("empty".to_string())),
+            Some(ImageFormat::Exr) => Err(ImageError::UnsupportedFormat(
+                "EXR decoding not supported".to_string(),
+            )),
+            None => Err(ImageError::UnsupportedFormat("empty".to_string())),
         }
     }
 
@@ -0,0 +1,157 @@
+//! Parallel traversal of pixel buffers.
+//!
+//! Work is split into disjoint mutable regions (rows or tiles) which are
+//! distributed dynamically to a pool of scoped threads, so regions that are
+//! expensive to compute don't stall the others.
+
+use crate::core::image::{
+    iters::{RowMut, RowsMut, TileMut, TilesMut},
+    Pixel,
+};
+use std::{num::NonZeroUsize, sync::Mutex, thread};
+
+/// Returns the number of threads used by default for parallel traversal.
+pub fn default_n_threads() -> usize {
+    thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1)
+}
+
+/// Runs `f` on every item using `n_threads` scoped threads pulling from a
+/// shared work queue.
+pub(crate) fn run_parallel<T: Send, F: Fn(T) + Sync>(items: Vec<T>, n_threads: usize, f: F) {
+    let n_threads = n_threads.clamp(1, items.len().max(1));
+    if n_threads == 1 {
+        items.into_iter().for_each(f);
+        return;
+    }
+    let queue = Mutex::new(items.into_iter());
+    thread::scope(|s| {
+        for _ in 0..n_threads {
+            s.spawn(|| loop {
+                // The lock guard is dropped at the end of the statement, so the
+                // work itself runs unlocked.
+                let item = queue.lock().unwrap().next();
+                match item {
+                    Some(item) => f(item),
+                    None => break,
+                }
+            });
+        }
+    });
+}
+
+/// Parallel iterator over the rows of a pixel buffer.
+pub struct ParRowsMut<'a, P: Pixel + 'a>
+where
+    P::Subpixel: 'a,
+{
+    rows: Vec<RowMut<'a, P>>,
+    n_threads: usize,
+}
+
+impl<'a, P: Pixel> ParRowsMut<'a, P>
+where
+    P::Subpixel: Send + 'a,
+{
+    pub fn new(samples: &'a mut [P::Subpixel], width: u32) -> Self {
+        ParRowsMut {
+            rows: RowsMut::new(samples, width).collect(),
+            n_threads: default_n_threads(),
+        }
+    }
+
+    /// Sets the number of worker threads.
+    pub fn with_threads(mut self, n_threads: usize) -> Self {
+        self.n_threads = n_threads;
+        self
+    }
+
+    /// Calls `f` on every row, in no particular order.
+    pub fn for_each<F: Fn(RowMut<'a, P>) + Sync>(self, f: F) {
+        run_parallel(self.rows, self.n_threads, f)
+    }
+}
+
+/// Parallel iterator over fixed-size tiles of a pixel buffer.
+///
+/// Tiles on the right and bottom borders are clipped to the buffer.
+pub struct ParTilesMut<'a, P: Pixel + 'a>
+where
+    P::Subpixel: 'a,
+{
+    tiles: Vec<TileMut<'a, P>>,
+    n_threads: usize,
+}
+
+impl<'a, P: Pixel> ParTilesMut<'a, P>
+where
+    P::Subpixel: Send + 'a,
+{
+    pub fn new(
+        samples: &'a mut [P::Subpixel],
+        width: u32,
+        tile_width: u32,
+        tile_height: u32,
+    ) -> Self {
+        ParTilesMut {
+            tiles: TilesMut::new(samples, width, tile_width, tile_height).collect(),
+            n_threads: default_n_threads(),
+        }
+    }
+
+    /// Sets the number of worker threads.
+    pub fn with_threads(mut self, n_threads: usize) -> Self {
+        self.n_threads = n_threads;
+        self
+    }
+
+    /// Calls `f` on every tile, in no particular order.
+    pub fn for_each<F: Fn(TileMut<'a, P>) + Sync>(self, f: F) {
+        run_parallel(self.tiles, self.n_threads, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{
+        image::{PixelBuffer, PixelBufferRgb32f},
+        Vec1,
+    };
+
+    #[test]
+    fn tiles_cover_buffer_once() {
+        let mut image = PixelBuffer::<Vec1<u32>>::new(37, 21);
+        image
+            .par_tiles_mut(8, 5)
+            .with_threads(4)
+            .for_each(|mut tile| {
+                for (_, pixel) in tile.pixels_mut() {
+                    pixel[0] += 1;
+                }
+            });
+        assert!(image.samples().iter().all(|&s| s == 1));
+    }
+
+    #[test]
+    fn coordinates_match_sequential() {
+        let mut image = PixelBufferRgb32f::new(19, 13);
+        image.par_rows_mut().for_each(|mut row| {
+            for ((x, y), pixel) in row.pixels_mut() {
+                pixel[0] = x as f32;
+                pixel[1] = y as f32;
+            }
+        });
+        let mut tiled = PixelBufferRgb32f::new(19, 13);
+        tiled.par_tiles_mut(4, 4).for_each(|mut tile| {
+            for ((x, y), pixel) in tile.pixels_mut() {
+                pixel[0] = x as f32;
+                pixel[1] = y as f32;
+            }
+        });
+        for ((x, y), pixel) in image.pixels() {
+            assert_eq!((pixel[0], pixel[1]), (x as f32, y as f32));
+        }
+        assert_eq!(image.samples(), tiled.samples());
+    }
+}
@@ -0,0 +1,382 @@
+//! Statistics, histograms and exposure analysis of pixel buffers.
+//!
+//! Statistics are computed on raw sample values converted to `f32`. NaNs
+//! and infinities are counted separately and excluded from every other
+//! statistic, so that a single firefly or NaN doesn't hide the rest of the
+//! image.
+
+use crate::core::{
+    image::{Pixel, PixelBuffer, Sample},
+    rounding::CompensatedSum,
+    Vec1,
+};
+
+/// Statistics of the finite samples of one channel.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ChannelStats {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f64,
+    /// Population variance.
+    pub variance: f64,
+    /// Number of finite samples.
+    pub count: usize,
+    /// Number of NaN samples.
+    pub nan_count: usize,
+    /// Number of infinite samples, of either sign.
+    pub inf_count: usize,
+}
+
+impl ChannelStats {
+    pub fn std_dev(&self) -> f64 {
+        self.variance.sqrt()
+    }
+
+    /// Computes the statistics of a sequence of samples.
+    pub fn from_values<I: IntoIterator<Item = f32>>(values: I) -> Self {
+        let mut stats = ChannelStats {
+            min: f32::INFINITY,
+            max: f32::NEG_INFINITY,
+            mean: 0.0,
+            variance: 0.0,
+            count: 0,
+            nan_count: 0,
+            inf_count: 0,
+        };
+        // Welford's online algorithm; `m2` is the sum of squared deviations.
+        let mut m2 = 0.0;
+        for v in values {
+            if v.is_nan() {
+                stats.nan_count += 1;
+            } else if v.is_infinite() {
+                stats.inf_count += 1;
+            } else {
+                stats.count += 1;
+                stats.min = stats.min.min(v);
+                stats.max = stats.max.max(v);
+                let delta = v as f64 - stats.mean;
+                stats.mean += delta / stats.count as f64;
+                m2 += delta * (v as f64 - stats.mean);
+            }
+        }
+        if stats.count > 0 {
+            stats.variance = m2 / stats.count as f64;
+        }
+        stats
+    }
+}
+
+fn channel_values<P: Pixel>(
+    buffer: &PixelBuffer<P>,
+    channel: usize,
+) -> impl Iterator<Item = f32> + Clone + '_ {
+    assert!(channel < P::N_CHANNELS, "channel index out of range");
+    buffer
+        .samples()
+        .iter()
+        .skip(channel)
+        .step_by(P::N_CHANNELS)
+        .map(|s| s.to_f32())
+}
+
+/// Computes the statistics of every channel of the buffer.
+pub fn channel_stats<P: Pixel>(buffer: &PixelBuffer<P>) -> Vec<ChannelStats> {
+    (0..P::N_CHANNELS)
+        .map(|c| ChannelStats::from_values(channel_values(buffer, c)))
+        .collect()
+}
+
+/// Sums the finite samples of every channel, with compensated summation so
+/// that large buffers don't lose the contribution of small values.
+pub fn channel_sums<P: Pixel>(buffer: &PixelBuffer<P>) -> Vec<f64> {
+    (0..P::N_CHANNELS)
+        .map(|c| {
+            channel_values(buffer, c)
+                .filter(|v| v.is_finite())
+                .map(f64::from)
+                .collect::<CompensatedSum<f64>>()
+                .value()
+        })
+        .collect()
+}
+
+/// Returns the values of the finite samples of a channel at the given
+/// percentiles (in `[0, 100]`), using linear interpolation between the
+/// closest ranks. Returns NaNs if the channel has no finite samples.
+pub fn percentiles<P: Pixel>(buffer: &PixelBuffer<P>, channel: usize, ps: &[f32]) -> Vec<f32> {
+    let mut values = channel_values(buffer, channel)
+        .filter(|v| v.is_finite())
+        .collect::<Vec<_>>();
+    values.sort_unstable_by(f32::total_cmp);
+    ps.iter()
+        .map(|&p| {
+            if values.is_empty() {
+                return f32::NAN;
+            }
+            let rank = (p.clamp(0.0, 100.0) / 100.0) * (values.len() - 1) as f32;
+            let i = rank.floor() as usize;
+            let j = (i + 1).min(values.len() - 1);
+            values[i] + (values[j] - values[i]) * (rank - i as f32)
+        })
+        .collect()
+}
+
+/// Returns the value of the finite samples of a channel at the given
+/// percentile (in `[0, 100]`).
+pub fn percentile<P: Pixel>(buffer: &PixelBuffer<P>, channel: usize, p: f32) -> f32 {
+    percentiles(buffer, channel, &[p])[0]
+}
+
+/// Spacing of histogram bins.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BinScale {
+    /// Bins of equal width.
+    Linear,
+
+    /// Bins of equal width in log space, suited to HDR values spanning
+    /// several orders of magnitude. The range must be strictly positive.
+    Log,
+}
+
+/// Histogram of finite values over a fixed range.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    /// Lower and upper bounds of the histogram.
+    pub range: (f32, f32),
+    pub scale: BinScale,
+    /// Number of values in each bin.
+    pub bins: Vec<u64>,
+    /// Number of values below the range (including non-positive values for
+    /// log bins).
+    pub underflow: u64,
+    /// Number of values above the range.
+    pub overflow: u64,
+}
+
+impl Histogram {
+    /// Creates an empty histogram.
+    pub fn new(n_bins: usize, range: (f32, f32), scale: BinScale) -> Self {
+        assert!(n_bins > 0, "histogram must have at least one bin");
+        assert!(range.0 < range.1, "histogram range must not be empty");
+        if scale == BinScale::Log {
+            assert!(range.0 > 0.0, "log histogram range must be positive");
+        }
+        Histogram {
+            range,
+            scale,
+            bins: vec![0; n_bins],
+            underflow: 0,
+            overflow: 0,
+        }
+    }
+
+    /// Creates a histogram of the finite values of a sequence. If `range` is
+    /// `None`, it spans the finite values (the positive ones for log bins).
+    pub fn from_values<I>(
+        values: I,
+        n_bins: usize,
+        scale: BinScale,
+        range: Option<(f32, f32)>,
+    ) -> Self
+    where
+        I: IntoIterator<Item = f32>,
+        I::IntoIter: Clone,
+    {
+        let values = values.into_iter();
+        let range = range.unwrap_or_else(|| {
+            let (lo, hi) = values
+                .clone()
+                .filter(|v| v.is_finite() && (scale == BinScale::Linear || *v > 0.0))
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), v| {
+                    (lo.min(v), hi.max(v))
+                });
+            match (lo.is_finite(), lo < hi) {
+                (true, true) => (lo, hi),
+                (true, false) if scale == BinScale::Log => (lo * 0.5, lo * 2.0),
+                (true, false) => (lo - 0.5, lo + 0.5),
+                (false, _) if scale == BinScale::Log => (1.0, 2.0),
+                (false, _) => (0.0, 1.0),
+            }
+        });
+        let mut histogram = Histogram::new(n_bins, range, scale);
+        values.for_each(|v| histogram.add(v));
+        histogram
+    }
+
+    fn warp(&self, v: f32) -> f32 {
+        match self.scale {
+            BinScale::Linear => v,
+            BinScale::Log => v.ln(),
+        }
+    }
+
+    fn unwarp(&self, v: f32) -> f32 {
+        match self.scale {
+            BinScale::Linear => v,
+            BinScale::Log => v.exp(),
+        }
+    }
+
+    /// Adds a value; non-finite values are ignored.
+    pub fn add(&mut self, v: f32) {
+        if !v.is_finite() {
+            return;
+        }
+        if v < self.range.0 || (self.scale == BinScale::Log && v <= 0.0) {
+            self.underflow += 1;
+        } else if v > self.range.1 {
+            self.overflow += 1;
+        } else {
+            let (lo, hi) = (self.warp(self.range.0), self.warp(self.range.1));
+            let t = (self.warp(v) - lo) / (hi - lo);
+            let i = ((t * self.bins.len() as f32) as usize).min(self.bins.len() - 1);
+            self.bins[i] += 1;
+        }
+    }
+
+    /// Returns the `n_bins + 1` bin edges.
+    pub fn bin_edges(&self) -> Vec<f32> {
+        let (lo, hi) = (self.warp(self.range.0), self.warp(self.range.1));
+        let n = self.bins.len();
+        (0..=n)
+            .map(|i| self.unwarp(lo + (hi - lo) * i as f32 / n as f32))
+            .collect()
+    }
+
+    /// Total number of values in the bins, excluding under- and overflows.
+    pub fn total(&self) -> u64 {
+        self.bins.iter().sum()
+    }
+}
+
+/// Computes the histogram of one channel of the buffer.
+pub fn histogram<P: Pixel>(
+    buffer: &PixelBuffer<P>,
+    channel: usize,
+    n_bins: usize,
+    scale: BinScale,
+    range: Option<(f32, f32)>,
+) -> Histogram {
+    Histogram::from_values(channel_values(buffer, channel), n_bins, scale, range)
+}
+
+/// Luminance of a pixel: Rec. 709 weights for pixels with at least three
+/// (RGB) channels, the first channel otherwise.
+pub fn pixel_luminance<P: Pixel>(pixel: &P) -> f32 {
+    let c = pixel.channels();
+    if c.len() >= 3 {
+        0.2126 * c[0].to_f32() + 0.7152 * c[1].to_f32() + 0.0722 * c[2].to_f32()
+    } else {
+        c[0].to_f32()
+    }
+}
+
+/// Computes the luminance of every pixel; see [`pixel_luminance`].
+pub fn luminance<P: Pixel>(buffer: &PixelBuffer<P>) -> PixelBuffer<Vec1<f32>> {
+    buffer.map(|p| Vec1::from([pixel_luminance(p)]))
+}
+
+/// Computes the histogram of the luminance of the buffer.
+pub fn luminance_histogram<P: Pixel>(
+    buffer: &PixelBuffer<P>,
+    n_bins: usize,
+    scale: BinScale,
+    range: Option<(f32, f32)>,
+) -> Histogram {
+    histogram(&luminance(buffer), 0, n_bins, scale, range)
+}
+
+/// Returns the coordinates of the pixels with at least one NaN or infinite
+/// sample.
+pub fn non_finite_pixels<P: Pixel>(buffer: &PixelBuffer<P>) -> Vec<(usize, usize)> {
+    buffer
+        .pixels()
+        .filter(|(_, p)| p.channels().iter().any(|s| !s.to_f32().is_finite()))
+        .map(|(xy, _)| xy)
+        .collect()
+}
+
+/// Returns the coordinates of the pixels whose luminance exceeds the given
+/// percentile of the luminance by more than `factor`; useful to locate
+/// fireflies.
+pub fn outlier_pixels<P: Pixel>(
+    buffer: &PixelBuffer<P>,
+    p: f32,
+    factor: f32,
+) -> Vec<(usize, usize)> {
+    let luminance = luminance(buffer);
+    let threshold = percentile(&luminance, 0, p) * factor;
+    luminance
+        .pixels()
+        .filter(|(_, l)| l[0].is_finite() && l[0] > threshold)
+        .map(|(xy, _)| xy)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Vec3;
+
+    #[test]
+    fn stats_skip_non_finite() {
+        let buffer = PixelBuffer::<Vec1<f32>>::from_samples(
+            3,
+            2,
+            vec![1.0, 2.0, f32::NAN, 3.0, f32::INFINITY, f32::NEG_INFINITY],
+        );
+        let stats = channel_stats(&buffer)[0];
+        assert_eq!((stats.min, stats.max, stats.count), (1.0, 3.0, 3));
+        assert_eq!((stats.nan_count, stats.inf_count), (1, 2));
+        assert!((stats.mean - 2.0).abs() < 1e-12);
+        assert!((stats.variance - 2.0 / 3.0).abs() < 1e-12);
+        assert_eq!(non_finite_pixels(&buffer), vec![(2, 0), (1, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn sums_skip_non_finite() {
+        let buffer = PixelBuffer::<Vec3<f32>>::from_samples(
+            2,
+            1,
+            vec![1e16, 1.0, f32::NAN, -1e16, 2.0, 3.0],
+        );
+        assert_eq!(channel_sums(&buffer), vec![0.0, 3.0, 3.0]);
+        // 1 + 1e16 + 1 - 1e16 loses the ones in double precision.
+        let buffer = PixelBuffer::<Vec1<f32>>::from_samples(2, 2, vec![1.0, 1e16, 1.0, -1e16]);
+        assert_eq!(channel_sums(&buffer), vec![2.0]);
+    }
+
+    #[test]
+    fn percentiles_interpolate() {
+        let buffer = PixelBuffer::<Vec1<u8>>::from_samples(5, 1, vec![4, 0, 3, 1, 2]);
+        assert_eq!(
+            percentiles(&buffer, 0, &[0.0, 50.0, 100.0]),
+            vec![0.0, 2.0, 4.0]
+        );
+        assert_eq!(percentile(&buffer, 0, 12.5), 0.5);
+    }
+
+    #[test]
+    fn histograms() {
+        let values = [0.5f32, 1.0, 10.0, 100.0, 1000.0, -1.0, f32::NAN];
+        let log = Histogram::from_values(values, 3, BinScale::Log, Some((1.0, 1000.0)));
+        assert_eq!(log.bins, vec![1, 1, 2]);
+        assert_eq!((log.underflow, log.overflow), (2, 0));
+        let edges = log.bin_edges();
+        assert!((edges[1] - 10.0).abs() < 1e-3 && (edges[2] - 100.0).abs() < 1e-2);
+
+        let linear = Histogram::from_values(values, 4, BinScale::Linear, None);
+        assert_eq!(linear.range, (-1.0, 1000.0));
+        assert_eq!(linear.total(), 6);
+    }
+
+    #[test]
+    fn firefly_detection() {
+        let mut image = PixelBuffer::<Vec3<f32>>::new(8, 8);
+        image.fill(Vec3::splat(0.5));
+        *image.pixel_at_mut(3, 4).unwrap() = Vec3::splat(500.0);
+        assert_eq!(outlier_pixels(&image, 95.0, 10.0), vec![(3, 4)]);
+        let histogram = luminance_histogram(&image, 4, BinScale::Log, None);
+        assert_eq!(histogram.bins, vec![63, 0, 0, 1]);
+    }
+}
@@ -0,0 +1,491 @@
+//! Filtered texture lookups from pixel buffers.
+//!
+//! Texture coordinates `(u, v)` are continuous, with `(0, 0)` the top-left
+//! corner of the top-left pixel and `(1, 1)` the bottom-right corner of the
+//! bottom-right pixel, so pixel centres lie at `((x + 0.5) / w, (y + 0.5) /
+//! h)`. Lookups return `f32` samples regardless of the sample type of the
+//! buffer; values are not normalised.
+
+use crate::core::image::{Pixel, PixelBuffer, Sample};
+use glam::Vec2;
+
+/// How texture coordinates outside of `[0, 1]` are handled.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WrapMode {
+    /// Tiles the texture.
+    Repeat,
+
+    /// Repeats the edge texels.
+    Clamp,
+
+    /// Tiles the texture, flipping every other copy.
+    Mirror,
+}
+
+impl WrapMode {
+    /// Maps a texel index to the range `[0, n)`.
+    pub fn wrap(&self, i: i64, n: i64) -> i64 {
+        match self {
+            WrapMode::Repeat => i.rem_euclid(n),
+            WrapMode::Clamp => i.clamp(0, n - 1),
+            WrapMode::Mirror => {
+                let i = i.rem_euclid(2 * n);
+                if i < n {
+                    i
+                } else {
+                    2 * n - 1 - i
+                }
+            }
+        }
+    }
+}
+
+/// Reconstruction filter used for lookups at a single resolution.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FilterMode {
+    /// Nearest texel.
+    Nearest,
+
+    /// Linear interpolation of the 2x2 closest texels.
+    Bilinear,
+
+    /// Catmull-Rom interpolation of the 4x4 closest texels. May overshoot
+    /// near sharp edges.
+    Bicubic,
+}
+
+/// Pixel type returned by texture lookups.
+pub type Texel<P> = <P as Pixel>::WithSubpixel<f32>;
+
+/// Adds `w * src` to `dst`, channel by channel.
+#[inline]
+fn accumulate<Q: Pixel<Subpixel = f32>>(dst: &mut Q, src: &Q, w: f32) {
+    for (d, s) in dst.channels_mut().iter_mut().zip(src.channels()) {
+        *d += w * s;
+    }
+}
+
+#[inline]
+fn scaled<Q: Pixel<Subpixel = f32>>(mut p: Q, w: f32) -> Q {
+    p.channels_mut().iter_mut().for_each(|c| *c *= w);
+    p
+}
+
+#[inline]
+fn lerp<Q: Pixel<Subpixel = f32>>(t: f32, a: &Q, b: &Q) -> Q {
+    let mut out = scaled(*a, 1.0 - t);
+    accumulate(&mut out, b, t);
+    out
+}
+
+/// Returns the texel at integer coordinates, wrapped into the buffer.
+fn texel<P: Pixel>(buffer: &PixelBuffer<P>, x: i64, y: i64, wrap: WrapMode) -> Texel<P> {
+    let x = wrap.wrap(x, buffer.width() as i64) as u32;
+    let y = wrap.wrap(y, buffer.height() as i64) as u32;
+    let mut out = Texel::<P>::default();
+    let pixel = buffer.pixel_at(x, y).unwrap();
+    for (d, s) in out.channels_mut().iter_mut().zip(pixel.channels()) {
+        *d = s.to_f32();
+    }
+    out
+}
+
+/// Same as [`texel`] for buffers which already hold `f32` samples.
+fn texel_f32<Q: Pixel<Subpixel = f32>>(
+    buffer: &PixelBuffer<Q>,
+    x: i64,
+    y: i64,
+    wrap: WrapMode,
+) -> Q {
+    let x = wrap.wrap(x, buffer.width() as i64) as u32;
+    let y = wrap.wrap(y, buffer.height() as i64) as u32;
+    *buffer.pixel_at(x, y).unwrap()
+}
+
+/// Catmull-Rom weights of the four texels around a fractional offset `t`.
+fn catmull_rom_weights(t: f32) -> [f32; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [
+        -0.5 * t3 + t2 - 0.5 * t,
+        1.5 * t3 - 2.5 * t2 + 1.0,
+        -1.5 * t3 + 2.0 * t2 + 0.5 * t,
+        0.5 * t3 - 0.5 * t2,
+    ]
+}
+
+/// Reconstructs the value at continuous coordinates of an image of
+/// `width` x `height` texels, reading texels through `fetch`.
+fn reconstruct<Q: Pixel<Subpixel = f32>, F: Fn(i64, i64) -> Q>(
+    (width, height): (u32, u32),
+    uv: Vec2,
+    filter: FilterMode,
+    fetch: F,
+) -> Q {
+    let x = uv.x * width as f32;
+    let y = uv.y * height as f32;
+    match filter {
+        FilterMode::Nearest => fetch(x.floor() as i64, y.floor() as i64),
+        FilterMode::Bilinear => {
+            let (x, y) = (x - 0.5, y - 0.5);
+            let (x0, y0) = (x.floor(), y.floor());
+            let (dx, dy) = (x - x0, y - y0);
+            let (x0, y0) = (x0 as i64, y0 as i64);
+            let top = lerp(dx, &fetch(x0, y0), &fetch(x0 + 1, y0));
+            let bottom = lerp(dx, &fetch(x0, y0 + 1), &fetch(x0 + 1, y0 + 1));
+            lerp(dy, &top, &bottom)
+        }
+        FilterMode::Bicubic => {
+            let (x, y) = (x - 0.5, y - 0.5);
+            let (x0, y0) = (x.floor(), y.floor());
+            let wx = catmull_rom_weights(x - x0);
+            let wy = catmull_rom_weights(y - y0);
+            let (x0, y0) = (x0 as i64, y0 as i64);
+            let mut out = Q::default();
+            for (j, wy) in wy.iter().enumerate() {
+                for (i, wx) in wx.iter().enumerate() {
+                    let t = fetch(x0 + i as i64 - 1, y0 + j as i64 - 1);
+                    accumulate(&mut out, &t, wx * wy);
+                }
+            }
+            out
+        }
+    }
+}
+
+/// Reads a buffer at continuous texture coordinates.
+pub fn sample<P: Pixel>(
+    buffer: &PixelBuffer<P>,
+    uv: Vec2,
+    filter: FilterMode,
+    wrap: WrapMode,
+) -> Texel<P> {
+    assert!(
+        buffer.width() > 0 && buffer.height() > 0,
+        "cannot sample an empty buffer"
+    );
+    reconstruct(buffer.dimensions(), uv, filter, |x, y| {
+        texel(buffer, x, y, wrap)
+    })
+}
+
+/// Same as [`sample`] for buffers which already hold `f32` samples.
+fn sample_f32<Q: Pixel<Subpixel = f32>>(
+    buffer: &PixelBuffer<Q>,
+    uv: Vec2,
+    filter: FilterMode,
+    wrap: WrapMode,
+) -> Q {
+    reconstruct(buffer.dimensions(), uv, filter, |x, y| {
+        texel_f32(buffer, x, y, wrap)
+    })
+}
+
+/// Texels of a row of `n` texels, with their weights, averaged into each
+/// texel of a row of `m` texels covering the same extent.
+fn box_taps(n: u32, m: u32) -> Vec<Vec<(i64, f32)>> {
+    let scale = n as f64 / m as f64;
+    (0..m)
+        .map(|x| {
+            let (lo, hi) = (x as f64 * scale, (x + 1) as f64 * scale);
+            (lo.floor() as i64..hi.ceil() as i64)
+                .map(|i| {
+                    let overlap = hi.min(i as f64 + 1.0) - lo.max(i as f64);
+                    (i, (overlap / scale) as f32)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Image pyramid of successively halved resolutions supporting filtered
+/// lookups over a footprint.
+///
+/// Level 0 is the full resolution image; each following level halves the
+/// dimensions (rounding down, at least one texel) by averaging the texels
+/// each of its texels covers, until a 1x1 level is reached. Along odd
+/// dimensions, texels straddling two coarser ones are split between them,
+/// so that every level keeps the mean of the image.
+#[derive(Clone)]
+pub struct MipMap<P: Pixel> {
+    levels: Vec<PixelBuffer<Texel<P>>>,
+    wrap: WrapMode,
+    filter: FilterMode,
+    max_anisotropy: f32,
+}
+
+impl<P: Pixel> MipMap<P> {
+    /// Builds the pyramid of a buffer. Lookups at a single level use bilinear
+    /// filtering and EWA lookups limit the anisotropy to 8 by default.
+    pub fn new(buffer: &PixelBuffer<P>, wrap: WrapMode) -> Self {
+        assert!(
+            buffer.width() > 0 && buffer.height() > 0,
+            "cannot build a mip map from an empty buffer"
+        );
+        let mut levels = vec![buffer.convert::<f32>()];
+        loop {
+            let prev = levels.last().unwrap();
+            let (w, h) = prev.dimensions();
+            if w == 1 && h == 1 {
+                break;
+            }
+            let (nw, nh) = ((w / 2).max(1), (h / 2).max(1));
+            let (taps_x, taps_y) = (box_taps(w, nw), box_taps(h, nh));
+            let mut next = PixelBuffer::<Texel<P>>::new(nw, nh);
+            for ((x, y), pixel) in next.pixels_mut() {
+                for &(ty, wy) in &taps_y[y as usize] {
+                    for &(tx, wx) in &taps_x[x as usize] {
+                        let t = texel_f32(prev, tx, ty, WrapMode::Clamp);
+                        accumulate(pixel, &t, wx * wy);
+                    }
+                }
+            }
+            levels.push(next);
+        }
+        MipMap {
+            levels,
+            wrap,
+            filter: FilterMode::Bilinear,
+            max_anisotropy: 8.0,
+        }
+    }
+
+    /// Sets the filter used by lookups at a single level.
+    pub fn with_filter(mut self, filter: FilterMode) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Sets the maximum ratio between the axes of the EWA filter footprint;
+    /// more eccentric footprints are widened along their minor axis.
+    pub fn with_max_anisotropy(mut self, max_anisotropy: f32) -> Self {
+        self.max_anisotropy = max_anisotropy;
+        self
+    }
+
+    pub fn n_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn level(&self, level: usize) -> &PixelBuffer<Texel<P>> {
+        &self.levels[level]
+    }
+
+    pub fn wrap_mode(&self) -> WrapMode {
+        self.wrap
+    }
+
+    /// Looks up the full resolution level.
+    pub fn sample(&self, uv: Vec2) -> Texel<P> {
+        self.sample_level(0, uv)
+    }
+
+    /// Looks up the given level, clamped to the coarsest one.
+    pub fn sample_level(&self, level: usize, uv: Vec2) -> Texel<P> {
+        let level = level.min(self.levels.len() - 1);
+        sample_f32(&self.levels[level], uv, self.filter, self.wrap)
+    }
+
+    /// Isotropic lookup over a square footprint of `width` in texture
+    /// coordinates, interpolating between the two closest levels.
+    pub fn trilinear(&self, uv: Vec2, width: f32) -> Texel<P> {
+        let level = self.levels.len() as f32 - 1.0 + width.max(1e-8).log2();
+        if level <= 0.0 {
+            return self.sample_level(0, uv);
+        }
+        if level >= (self.levels.len() - 1) as f32 {
+            return self.sample_level(self.levels.len() - 1, uv);
+        }
+        let i = level.floor();
+        lerp(
+            level - i,
+            &self.sample_level(i as usize, uv),
+            &self.sample_level(i as usize + 1, uv),
+        )
+    }
+
+    /// Trilinear lookup over the footprint given by the screen-space
+    /// derivatives of the texture coordinates, using the larger axis.
+    pub fn trilinear_with_derivatives(&self, uv: Vec2, duvdx: Vec2, duvdy: Vec2) -> Texel<P> {
+        let width = duvdx.abs().max_element().max(duvdy.abs().max_element()) * 2.0;
+        self.trilinear(uv, width)
+    }
+
+    /// Anisotropic lookup with an elliptically weighted average (Heckbert's
+    /// EWA) over the ellipse spanned by the derivatives of the texture
+    /// coordinates.
+    pub fn ewa(&self, uv: Vec2, duvdx: Vec2, duvdy: Vec2) -> Texel<P> {
+        let (mut major, mut minor) = if duvdx.length_squared() < duvdy.length_squared() {
+            (duvdy, duvdx)
+        } else {
+            (duvdx, duvdy)
+        };
+        let major_length = major.length();
+        let mut minor_length = minor.length();
+        if minor_length * self.max_anisotropy < major_length && minor_length > 0.0 {
+            let scale = major_length / (minor_length * self.max_anisotropy);
+            minor *= scale;
+            minor_length *= scale;
+        }
+        if minor_length == 0.0 {
+            return sample_f32(&self.levels[0], uv, FilterMode::Bilinear, self.wrap);
+        }
+        if major_length == 0.0 {
+            major = minor;
+        }
+        let level = (self.levels.len() as f32 - 1.0 + minor_length.log2()).max(0.0);
+        let i = level.floor();
+        lerp(
+            level - i,
+            &self.ewa_level(i as usize, uv, major, minor),
+            &self.ewa_level(i as usize + 1, uv, major, minor),
+        )
+    }
+
+    fn ewa_level(&self, level: usize, uv: Vec2, axis0: Vec2, axis1: Vec2) -> Texel<P> {
+        if level >= self.levels.len() {
+            return texel_f32(self.levels.last().unwrap(), 0, 0, self.wrap);
+        }
+        let buffer = &self.levels[level];
+        let size = Vec2::new(buffer.width() as f32, buffer.height() as f32);
+        let st = uv * size - 0.5;
+        let (d0, d1) = (axis0 * size, axis1 * size);
+
+        // Implicit ellipse A s^2 + B s t + C t^2 = 1, slightly enlarged so it
+        // always covers at least one texel.
+        let mut a = d0.y * d0.y + d1.y * d1.y + 1.0;
+        let mut b = -2.0 * (d0.x * d0.y + d1.x * d1.y);
+        let mut c = d0.x * d0.x + d1.x * d1.x + 1.0;
+        let inv_f = 1.0 / (a * c - b * b * 0.25);
+        a *= inv_f;
+        b *= inv_f;
+        c *= inv_f;
+
+        // Bounding box of the ellipse in texel space.
+        let det = -b * b + 4.0 * a * c;
+        let inv_det = 1.0 / det;
+        let u_sqrt = (det * c).sqrt();
+        let v_sqrt = (a * det).sqrt();
+        let s0 = (st.x - 2.0 * inv_det * u_sqrt).ceil() as i64;
+        let s1 = (st.x + 2.0 * inv_det * u_sqrt).floor() as i64;
+        let t0 = (st.y - 2.0 * inv_det * v_sqrt).ceil() as i64;
+        let t1 = (st.y + 2.0 * inv_det * v_sqrt).floor() as i64;
+
+        // Truncated Gaussian falloff.
+        const ALPHA: f32 = 2.0;
+        let mut sum = Texel::<P>::default();
+        let mut weight_sum = 0.0;
+        for it in t0..=t1 {
+            let tt = it as f32 - st.y;
+            for is in s0..=s1 {
+                let ss = is as f32 - st.x;
+                let r2 = a * ss * ss + b * ss * tt + c * tt * tt;
+                if r2 < 1.0 {
+                    let w = (-ALPHA * r2).exp() - (-ALPHA).exp();
+                    accumulate(&mut sum, &texel_f32(buffer, is, it, self.wrap), w);
+                    weight_sum += w;
+                }
+            }
+        }
+        if weight_sum > 0.0 {
+            scaled(sum, 1.0 / weight_sum)
+        } else {
+            sample_f32(buffer, uv, FilterMode::Bilinear, self.wrap)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Vec1, Vec3};
+
+    fn checker(size: u32) -> PixelBuffer<Vec1<u8>> {
+        let samples = (0..size * size)
+            .map(|i| {
+                if (i % size + i / size).is_multiple_of(2) {
+                    0
+                } else {
+                    255
+                }
+            })
+            .collect();
+        PixelBuffer::from_samples(size, size, samples)
+    }
+
+    #[test]
+    fn wrap_modes() {
+        assert_eq!(WrapMode::Repeat.wrap(-1, 4), 3);
+        assert_eq!(WrapMode::Clamp.wrap(-1, 4), 0);
+        assert_eq!(WrapMode::Mirror.wrap(-1, 4), 0);
+        assert_eq!(WrapMode::Mirror.wrap(4, 4), 3);
+        assert_eq!(WrapMode::Mirror.wrap(8, 4), 0);
+    }
+
+    #[test]
+    fn filters_reproduce_texel_centres() {
+        let image = checker(4);
+        let uv = Vec2::new(1.5 / 4.0, 2.5 / 4.0);
+        for filter in [
+            FilterMode::Nearest,
+            FilterMode::Bilinear,
+            FilterMode::Bicubic,
+        ] {
+            let t = sample(&image, uv, filter, WrapMode::Repeat);
+            assert!((t[0] - 255.0).abs() < 1e-3, "{:?}: {}", filter, t[0]);
+        }
+        let mid = sample(
+            &image,
+            Vec2::new(0.25, 0.125),
+            FilterMode::Bilinear,
+            WrapMode::Repeat,
+        );
+        assert!((mid[0] - 127.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn mip_levels_average() {
+        let mipmap = MipMap::new(&checker(8), WrapMode::Repeat);
+        assert_eq!(mipmap.n_levels(), 4);
+        assert_eq!(mipmap.level(3).dimensions(), (1, 1));
+        assert!(mipmap.level(1).samples().iter().all(|&s| s == 127.5));
+        let wide = mipmap.trilinear(Vec2::new(0.3, 0.6), 1.0);
+        assert!((wide[0] - 127.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn mip_levels_keep_the_mean() {
+        for (w, h) in [(3, 3), (5, 1), (7, 4)] {
+            let samples = (0..w * h).map(|i| (i * i % 7) as f32).collect::<Vec<_>>();
+            let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+            let mipmap = MipMap::new(
+                &PixelBuffer::<Vec1<f32>>::from_samples(w, h, samples),
+                WrapMode::Clamp,
+            );
+            for level in 1..mipmap.n_levels() {
+                let level = mipmap.level(level);
+                let (lw, lh) = level.dimensions();
+                let level_mean = level.samples().iter().sum::<f32>() / (lw * lh) as f32;
+                assert!(
+                    (level_mean - mean).abs() < 1e-5,
+                    "{w}x{h}: {level_mean} {mean}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn ewa_matches_constant_texture() {
+        let mut image = PixelBuffer::<Vec3<f32>>::new(16, 8);
+        image.fill(Vec3::from([0.25, 0.5, 0.75]));
+        let mipmap = MipMap::new(&image, WrapMode::Clamp);
+        let t = mipmap.ewa(
+            Vec2::new(0.4, 0.7),
+            Vec2::new(0.2, 0.01),
+            Vec2::new(0.0, 0.02),
+        );
+        for (c, expected) in t.iter().zip([0.25, 0.5, 0.75]) {
+            assert!((c - expected).abs() < 1e-5);
+        }
+    }
+}
@@ -1,19 +1,19 @@
-use std::ops::{Deref, DerefMut};
+use std::ops::{Add, AddAssign, Deref, DerefMut, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
 
 pub mod error;
 pub mod image;
 pub mod rounding;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Vec1<T>([T; 1]);
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Vec2<T>([T; 2]);
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Vec3<T>([T; 3]);
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Vec4<T>([T; 4]);
 
 macro_rules! impl_deref {
@@ -44,3 +44,177 @@ macro_rules! impl_deref {
 impl_deref! {
     Vec1<T> 1; Vec2<T> 2; Vec3<T> 3; Vec4<T> 4;
 }
+
+macro_rules! impl_vec_ops {
+    ($($name:ident<T> $n:expr;)*) => {
+        $(
+            impl<T> From<[T; $n]> for $name<T> {
+                fn from(arr: [T; $n]) -> Self {
+                    $name(arr)
+                }
+            }
+
+            impl<T: Copy> $name<T> {
+                /// Creates a vector with all components set to `val`.
+                pub fn splat(val: T) -> Self {
+                    $name([val; $n])
+                }
+
+                /// Applies `f` to each component.
+                pub fn map<U, F: FnMut(T) -> U>(self, f: F) -> $name<U> {
+                    $name(self.0.map(f))
+                }
+
+                /// Combines two vectors component-wise with `f`.
+                pub fn zip_with<U: Copy, V, F: FnMut(T, U) -> V>(self, other: $name<U>, mut f: F) -> $name<V> {
+                    $name(std::array::from_fn(|i| f(self.0[i], other.0[i])))
+                }
+            }
+
+            impl<T: Copy + Add<Output = T> + Mul<Output = T>> $name<T> {
+                /// Dot product of two vectors.
+                pub fn dot(self, other: Self) -> T {
+                    let prod = self.zip_with(other, |a, b| a * b);
+                    prod.0.into_iter().reduce(|a, b| a + b).unwrap()
+                }
+            }
+
+            impl<T: Copy + PartialOrd> $name<T> {
+                /// Component-wise minimum.
+                pub fn min(self, other: Self) -> Self {
+                    self.zip_with(other, |a, b| if b < a { b } else { a })
+                }
+
+                /// Component-wise maximum.
+                pub fn max(self, other: Self) -> Self {
+                    self.zip_with(other, |a, b| if b > a { b } else { a })
+                }
+
+                /// Clamps each component to the range `[lo, hi]`.
+                pub fn clamp(self, lo: T, hi: T) -> Self {
+                    self.map(|a| if a < lo { lo } else if a > hi { hi } else { a })
+                }
+            }
+
+            impl<T: Copy + Add<Output = T>> Add for $name<T> {
+                type Output = Self;
+
+                fn add(self, rhs: Self) -> Self::Output {
+                    self.zip_with(rhs, |a, b| a + b)
+                }
+            }
+
+            impl<T: Copy + Sub<Output = T>> Sub for $name<T> {
+                type Output = Self;
+
+                fn sub(self, rhs: Self) -> Self::Output {
+                    self.zip_with(rhs, |a, b| a - b)
+                }
+            }
+
+            impl<T: Copy + Mul<Output = T>> Mul for $name<T> {
+                type Output = Self;
+
+                fn mul(self, rhs: Self) -> Self::Output {
+                    self.zip_with(rhs, |a, b| a * b)
+                }
+            }
+
+            impl<T: Copy + Div<Output = T>> Div for $name<T> {
+                type Output = Self;
+
+                fn div(self, rhs: Self) -> Self::Output {
+                    self.zip_with(rhs, |a, b| a / b)
+                }
+            }
+
+            impl<T: Copy + Mul<Output = T>> Mul<T> for $name<T> {
+                type Output = Self;
+
+                fn mul(self, rhs: T) -> Self::Output {
+                    self.map(|a| a * rhs)
+                }
+            }
+
+            impl<T: Copy + Div<Output = T>> Div<T> for $name<T> {
+                type Output = Self;
+
+                fn div(self, rhs: T) -> Self::Output {
+                    self.map(|a| a / rhs)
+                }
+            }
+
+            impl<T: Copy + Add<Output = T>> AddAssign for $name<T> {
+                fn add_assign(&mut self, rhs: Self) {
+                    *self = *self + rhs;
+                }
+            }
+
+            impl<T: Copy + Sub<Output = T>> SubAssign for $name<T> {
+                fn sub_assign(&mut self, rhs: Self) {
+                    *self = *self - rhs;
+                }
+            }
+
+            impl<T: Copy + Mul<Output = T>> MulAssign<T> for $name<T> {
+                fn mul_assign(&mut self, rhs: T) {
+                    *self = *self * rhs;
+                }
+            }
+
+            impl<T: Copy + Div<Output = T>> DivAssign<T> for $name<T> {
+                fn div_assign(&mut self, rhs: T) {
+                    *self = *self / rhs;
+                }
+            }
+
+            impl $name<f32> {
+                /// Linearly interpolates between `self` (t = 0) and `other` (t = 1).
+                pub fn lerp(self, other: Self, t: f32) -> Self {
+                    self * (1.0 - t) + other * t
+                }
+            }
+        )*
+    };
+}
+
+impl_vec_ops! {
+    Vec1<T> 1; Vec2<T> 2; Vec3<T> 3; Vec4<T> 4;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Vec3, Vec4};
+    use quickcheck::quickcheck;
+
+    quickcheck! {
+        fn add_sub_roundtrip(a: (i16, i16, i16), b: (i16, i16, i16)) -> bool {
+            let a = Vec3::from([a.0 as i32, a.1 as i32, a.2 as i32]);
+            let b = Vec3::from([b.0 as i32, b.1 as i32, b.2 as i32]);
+            (a + b) - b == a
+        }
+
+        fn dot_matches_manual(a: (i16, i16, i16, i16), b: (i16, i16, i16, i16)) -> bool {
+            let va = Vec4::from([a.0 as i64, a.1 as i64, a.2 as i64, a.3 as i64]);
+            let vb = Vec4::from([b.0 as i64, b.1 as i64, b.2 as i64, b.3 as i64]);
+            va.dot(vb) == va[0] * vb[0] + va[1] * vb[1] + va[2] * vb[2] + va[3] * vb[3]
+        }
+    }
+
+    #[test]
+    fn lerp_endpoints() {
+        let a = Vec3::from([0.0f32, 1.0, 2.0]);
+        let b = Vec3::from([4.0f32, 3.0, 2.0]);
+        assert_eq!(a.lerp(b, 0.0), a);
+        assert_eq!(a.lerp(b, 1.0), b);
+        assert_eq!(a.lerp(b, 0.5), Vec3::from([2.0, 2.0, 2.0]));
+    }
+
+    #[test]
+    fn clamp_min_max() {
+        let a = Vec3::from([-1.0f32, 0.5, 2.0]);
+        assert_eq!(a.clamp(0.0, 1.0), Vec3::from([0.0, 0.5, 1.0]));
+        assert_eq!(a.min(Vec3::splat(0.0)), Vec3::from([-1.0, 0.0, 0.0]));
+        assert_eq!(a.max(Vec3::splat(0.0)), Vec3::from([0.0, 0.5, 2.0]));
+    }
+}
@@ -1,3 +1,9 @@
+use std::{
+    cmp::Ordering,
+    iter::FromIterator,
+    ops::{Add, AddAssign, Div, Mul, Neg, Sub},
+};
+
 /// Machine epsilon for floating point types.
 ///
 /// In rust/c++, this is the magnitude of one ulp (unit in the last place)
@@ -16,10 +22,75 @@ impl MachineEpsilon for f64 {
     const MACH_EPS: Self = f64::EPSILON * 0.5;
 }
 
-pub trait Floating: MachineEpsilon {}
+pub trait Floating:
+    MachineEpsilon
+    + Copy
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const INFINITY: Self;
+
+    /// The next representable number above.
+    fn next_up(self) -> Self;
+
+    /// The next representable number below.
+    fn next_down(self) -> Self;
+
+    fn sqrt(self) -> Self;
+
+    /// `self * a + b` with a single rounding.
+    fn mul_add(self, a: Self, b: Self) -> Self;
+}
+
+impl Floating for f32 {
+    const ZERO: Self = 0.0;
+    const ONE: Self = 1.0;
+    const INFINITY: Self = f32::INFINITY;
+
+    fn next_up(self) -> Self {
+        next_float_up(self)
+    }
+
+    fn next_down(self) -> Self {
+        next_float_down(self)
+    }
+
+    fn sqrt(self) -> Self {
+        f32::sqrt(self)
+    }
+
+    fn mul_add(self, a: Self, b: Self) -> Self {
+        f32::mul_add(self, a, b)
+    }
+}
 
-impl f32 for Floating {}
-impl f64 for Floating {}
+impl Floating for f64 {
+    const ZERO: Self = 0.0;
+    const ONE: Self = 1.0;
+    const INFINITY: Self = f64::INFINITY;
+
+    fn next_up(self) -> Self {
+        next_double_up(self)
+    }
+
+    fn next_down(self) -> Self {
+        next_double_down(self)
+    }
+
+    fn sqrt(self) -> Self {
+        f64::sqrt(self)
+    }
+
+    fn mul_add(self, a: Self, b: Self) -> Self {
+        f64::mul_add(self, a, b)
+    }
+}
 
 /// Returns the next representable floating point number.
 ///
@@ -42,6 +113,55 @@ pub const fn next_float_up(val: f32) -> f32 {
     }
 }
 
+/// Returns the previous representable floating point number, the
+/// counterpart of [`next_float_up`].
+pub const fn next_float_down(val: f32) -> f32 {
+    if val.is_infinite() && val.is_sign_negative() {
+        return val;
+    }
+
+    let f = if val == 0.0 { -0.0 } else { val };
+
+    let bits = f.to_bits();
+    if f > 0.0 {
+        f32::from_bits(bits - 1)
+    } else {
+        f32::from_bits(bits + 1)
+    }
+}
+
+/// Double precision counterpart of [`next_float_up`].
+pub const fn next_double_up(val: f64) -> f64 {
+    if val.is_infinite() && val.is_sign_positive() {
+        return val;
+    }
+
+    let f = if val == -0.0 { 0.0 } else { val };
+
+    let bits = f.to_bits();
+    if f >= 0.0 {
+        f64::from_bits(bits + 1)
+    } else {
+        f64::from_bits(bits - 1)
+    }
+}
+
+/// Double precision counterpart of [`next_float_down`].
+pub const fn next_double_down(val: f64) -> f64 {
+    if val.is_infinite() && val.is_sign_negative() {
+        return val;
+    }
+
+    let f = if val == 0.0 { -0.0 } else { val };
+
+    let bits = f.to_bits();
+    if f > 0.0 {
+        f64::from_bits(bits - 1)
+    } else {
+        f64::from_bits(bits + 1)
+    }
+}
+
 /// Computes the magnitude of the conservative bounding of the relative error.
 /// (1 \pm \epsilon_m) ^ n.
 ///
@@ -51,22 +171,652 @@ pub const fn next_float_up(val: f32) -> f32 {
 /// More details in  Higham, N. J. Accuracy and Stability of Numerical
 /// Algorithms (2nd ed.). Philadelphia: Society for Industrial and Applied
 /// Mathematics, (2002).
-pub const fn mre<F: Floating>(n: u32) -> F {
-    let t = n as f32 * F::MACH_EPS;
+pub const fn mre(n: u32) -> f32 {
+    let t = n as f32 * f32::MACH_EPS;
     t / (1.0 - t)
 }
 
+/// Double precision counterpart of [`mre`].
+pub const fn mre_f64(n: u32) -> f64 {
+    let t = n as f64 * f64::MACH_EPS;
+    t / (1.0 - t)
+}
+
+/// Rounds up an error bound computed with at most a few operations on
+/// non-negative terms, so that it bounds the exact value of its expression.
+fn round_error_up(err: f32) -> f32 {
+    next_float_up(err * (1.0 + mre(4)))
+}
+
+/// Floating point value carrying a bound on its absolute error, i.e. on its
+/// distance to the result of the same computation in exact arithmetic.
+///
+/// Values created with [`ErrorFloat::new`] are taken as exact, and every
+/// operation adds its own rounding error to the error propagated from its
+/// operands.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ErrorFloat {
+    v: f32,
+    err: f32,
+}
+
+impl From<f32> for ErrorFloat {
+    fn from(v: f32) -> Self {
+        Self::new(v)
+    }
+}
+
+impl ErrorFloat {
+    /// Creates an exact value.
+    pub const fn new(v: f32) -> Self {
+        Self { v, err: 0.0 }
+    }
+
+    /// Creates a value whose absolute error is at most `err`.
+    pub fn with_error(v: f32, err: f32) -> Self {
+        Self { v, err: err.abs() }
+    }
+
+    pub fn value(self) -> f32 {
+        self.v
+    }
+
+    /// Bound on the absolute error.
+    pub fn error(self) -> f32 {
+        self.err
+    }
+
+    /// Bound on the relative error; infinite for zero values with errors.
+    pub fn relative_error(self) -> f32 {
+        if self.err == 0.0 {
+            0.0
+        } else {
+            self.err / self.v.abs()
+        }
+    }
+
+    /// Lower bound of the exact value.
+    pub fn lower_bound(self) -> f32 {
+        next_float_down(self.v - self.err)
+    }
+
+    /// Upper bound of the exact value.
+    pub fn upper_bound(self) -> f32 {
+        next_float_up(self.v + self.err)
+    }
+
+    pub fn abs(self) -> Self {
+        Self {
+            v: self.v.abs(),
+            err: self.err,
+        }
+    }
+
+    /// Square root, the value being clamped to zero if it is negative.
+    pub fn sqrt(self) -> Self {
+        let v = self.v.max(0.0).sqrt();
+        // |√a - √b| is at most |a - b| / √b, and at most √|a - b|.
+        let propagated = if v > 0.0 {
+            (self.err / v).min(self.err.sqrt())
+        } else {
+            self.err.sqrt()
+        };
+        Self {
+            v,
+            err: round_error_up(propagated + mre(1) * v),
+        }
+    }
+}
+
+impl Neg for ErrorFloat {
+    type Output = ErrorFloat;
+
+    fn neg(self) -> ErrorFloat {
+        Self {
+            v: -self.v,
+            err: self.err,
+        }
+    }
+}
+
+impl Add for ErrorFloat {
+    type Output = ErrorFloat;
+
+    fn add(self, rhs: ErrorFloat) -> ErrorFloat {
+        let v = self.v + rhs.v;
+        Self {
+            v,
+            err: round_error_up(self.err + rhs.err + mre(1) * v.abs()),
+        }
+    }
+}
+
+impl Sub for ErrorFloat {
+    type Output = ErrorFloat;
+
+    fn sub(self, rhs: ErrorFloat) -> ErrorFloat {
+        self + -rhs
+    }
+}
+
+impl Mul for ErrorFloat {
+    type Output = ErrorFloat;
+
+    fn mul(self, rhs: ErrorFloat) -> ErrorFloat {
+        let v = self.v * rhs.v;
+        let propagated = self.v.abs() * rhs.err + rhs.v.abs() * self.err + self.err * rhs.err;
+        Self {
+            v,
+            err: round_error_up(propagated + mre(1) * v.abs()),
+        }
+    }
+}
+
+impl Div for ErrorFloat {
+    type Output = ErrorFloat;
+
+    /// Division, whose error is infinite if the divisor may be zero.
+    fn div(self, rhs: ErrorFloat) -> ErrorFloat {
+        let v = self.v / rhs.v;
+        let margin = rhs.v.abs() - rhs.err;
+        if margin <= 0.0 {
+            return Self {
+                v,
+                err: f32::INFINITY,
+            };
+        }
+        // a / b - (a + δa) / (b + δb) = (a δb / b - δa) / (b + δb)
+        let propagated = (self.err + v.abs() * rhs.err) / next_float_down(margin);
+        Self {
+            v,
+            err: round_error_up(propagated + mre(1) * v.abs()),
+        }
+    }
+}
+
+/// Closed interval of floating point numbers containing the exact value of
+/// a computation.
+///
+/// Operations round their bounds outwards, one representable number beyond
+/// the rounded result, so that the interval contains the result of the same
+/// operations on any values of the operands. NaN bounds are not supported.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Interval<F: Floating> {
+    low: F,
+    high: F,
+}
+
+impl<F: Floating> From<F> for Interval<F> {
+    fn from(v: F) -> Self {
+        Self::exact(v)
+    }
+}
+
+impl<F: Floating> Interval<F> {
+    /// Creates the interval between `a` and `b`, in any order.
+    pub fn new(a: F, b: F) -> Self {
+        if a <= b {
+            Self { low: a, high: b }
+        } else {
+            Self { low: b, high: a }
+        }
+    }
+
+    /// Creates the interval holding only `v`.
+    pub fn exact(v: F) -> Self {
+        Self { low: v, high: v }
+    }
+
+    /// Creates the interval of the values within `err` of `v`.
+    pub fn with_error(v: F, err: F) -> Self {
+        let err = if err < F::ZERO { -err } else { err };
+        Self {
+            low: (v - err).next_down(),
+            high: (v + err).next_up(),
+        }
+    }
+
+    pub fn low(&self) -> F {
+        self.low
+    }
+
+    pub fn high(&self) -> F {
+        self.high
+    }
+
+    pub fn midpoint(&self) -> F {
+        let half = F::ONE / (F::ONE + F::ONE);
+        self.low * half + self.high * half
+    }
+
+    pub fn width(&self) -> F {
+        self.high - self.low
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.low == self.high
+    }
+
+    pub fn contains(&self, v: F) -> bool {
+        self.low <= v && v <= self.high
+    }
+
+    /// Orders the intervals if all of their values compare the same way;
+    /// `None` if the order of the exact values is uncertain.
+    pub fn compare(&self, other: &Self) -> Option<Ordering> {
+        if self.high < other.low {
+            Some(Ordering::Less)
+        } else if self.low > other.high {
+            Some(Ordering::Greater)
+        } else if self.is_exact() && other.is_exact() {
+            Some(Ordering::Equal)
+        } else {
+            None
+        }
+    }
+
+    /// Square, tighter than the product of the interval with itself when it
+    /// straddles zero.
+    pub fn square(&self) -> Self {
+        let (a, b) = (self.low * self.low, self.high * self.high);
+        let (min, max) = if a <= b { (a, b) } else { (b, a) };
+        if self.contains(F::ZERO) {
+            Self {
+                low: F::ZERO,
+                high: max.next_up(),
+            }
+        } else {
+            Self {
+                low: min.next_down(),
+                high: max.next_up(),
+            }
+        }
+    }
+
+    /// Square root of the non-negative part of the interval.
+    pub fn sqrt(&self) -> Self {
+        let clamp = |v: F| if v < F::ZERO { F::ZERO } else { v };
+        let low = clamp(self.low).sqrt();
+        Self {
+            low: if low > F::ZERO { low.next_down() } else { low },
+            high: clamp(self.high).sqrt().next_up(),
+        }
+    }
+
+    /// Interval bounding the values of `f` at the corners of the operands,
+    /// for operations monotonic in each of them.
+    fn from_corners(values: [F; 4]) -> Self {
+        let (mut low, mut high) = (values[0], values[0]);
+        for &v in &values[1..] {
+            if v < low {
+                low = v;
+            }
+            if v > high {
+                high = v;
+            }
+        }
+        Self {
+            low: low.next_down(),
+            high: high.next_up(),
+        }
+    }
+}
+
+impl<F: Floating> Neg for Interval<F> {
+    type Output = Interval<F>;
+
+    fn neg(self) -> Interval<F> {
+        Self {
+            low: -self.high,
+            high: -self.low,
+        }
+    }
+}
+
+impl<F: Floating> Add for Interval<F> {
+    type Output = Interval<F>;
+
+    fn add(self, rhs: Interval<F>) -> Interval<F> {
+        Self {
+            low: (self.low + rhs.low).next_down(),
+            high: (self.high + rhs.high).next_up(),
+        }
+    }
+}
+
+impl<F: Floating> Sub for Interval<F> {
+    type Output = Interval<F>;
+
+    fn sub(self, rhs: Interval<F>) -> Interval<F> {
+        Self {
+            low: (self.low - rhs.high).next_down(),
+            high: (self.high - rhs.low).next_up(),
+        }
+    }
+}
+
+impl<F: Floating> Mul for Interval<F> {
+    type Output = Interval<F>;
+
+    fn mul(self, rhs: Interval<F>) -> Interval<F> {
+        Self::from_corners([
+            self.low * rhs.low,
+            self.low * rhs.high,
+            self.high * rhs.low,
+            self.high * rhs.high,
+        ])
+    }
+}
+
+impl<F: Floating> Div for Interval<F> {
+    type Output = Interval<F>;
+
+    /// Division, unbounded if the divisor contains zero.
+    fn div(self, rhs: Interval<F>) -> Interval<F> {
+        if rhs.contains(F::ZERO) {
+            return Self {
+                low: -F::INFINITY,
+                high: F::INFINITY,
+            };
+        }
+        Self::from_corners([
+            self.low / rhs.low,
+            self.low / rhs.high,
+            self.high / rhs.low,
+            self.high / rhs.high,
+        ])
+    }
+}
+
+/// Error-free transformation of a sum: returns the rounded sum `s` and its
+/// rounding error `e`, with `a + b = s + e` exactly (Knuth's TwoSum).
+pub fn two_sum<F: Floating>(a: F, b: F) -> (F, F) {
+    let s = a + b;
+    let b_virtual = s - a;
+    let a_virtual = s - b_virtual;
+    (s, (a - a_virtual) + (b - b_virtual))
+}
+
+/// Error-free transformation of a product: returns the rounded product `p`
+/// and its rounding error `e`, with `a * b = p + e` exactly, barring
+/// underflow. The error is recovered with a fused multiply-add.
+pub fn two_prod<F: Floating>(a: F, b: F) -> (F, F) {
+    let p = a * b;
+    (p, a.mul_add(b, -p))
+}
+
+/// Computes `a * b - c * d` within 1.5 ulps, avoiding the catastrophic
+/// cancellation of the naive expression (Kahan's algorithm).
+pub fn difference_of_products<F: Floating>(a: F, b: F, c: F, d: F) -> F {
+    let cd = c * d;
+    let err = (-c).mul_add(d, cd);
+    a.mul_add(b, -cd) + err
+}
+
+/// Sums `values` with Kahan's compensated summation, whose error does not
+/// grow with the number of values as long as they don't cancel out.
+pub fn kahan_sum<F: Floating, I: IntoIterator<Item = F>>(values: I) -> F {
+    let (mut sum, mut c) = (F::ZERO, F::ZERO);
+    for v in values {
+        let y = v - c;
+        let t = sum + y;
+        c = (t - sum) - y;
+        sum = t;
+    }
+    sum
+}
+
+/// Sums `values` with Neumaier's compensated summation, see
+/// [`CompensatedSum`].
+pub fn neumaier_sum<F: Floating, I: IntoIterator<Item = F>>(values: I) -> F {
+    values.into_iter().collect::<CompensatedSum<F>>().value()
+}
+
+/// Running sum keeping track of its rounding error (Neumaier's variant of
+/// Kahan summation), accurate to a few ulps however many values are added,
+/// including values larger than the sum.
+///
+/// The compensation is itself summed without compensation, so the error
+/// grows again as the number of values approaches 1 / ε: millions of `f32`
+/// values are best summed in a `CompensatedSum<f64>`.
+///
+/// Sums can be merged with `+`, so that partial sums of tiles or threads
+/// combine without losing their compensation.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct CompensatedSum<F: Floating> {
+    sum: F,
+    /// Accumulated rounding errors, still to be added to `sum`.
+    c: F,
+}
+
+impl<F: Floating> CompensatedSum<F> {
+    pub fn new() -> Self {
+        Self {
+            sum: F::ZERO,
+            c: F::ZERO,
+        }
+    }
+
+    pub fn add(&mut self, v: F) {
+        let (sum, err) = two_sum(self.sum, v);
+        self.sum = sum;
+        self.c = self.c + err;
+    }
+
+    /// The compensated value of the sum.
+    pub fn value(&self) -> F {
+        self.sum + self.c
+    }
+}
+
+impl<F: Floating> From<F> for CompensatedSum<F> {
+    fn from(v: F) -> Self {
+        Self { sum: v, c: F::ZERO }
+    }
+}
+
+impl<F: Floating> AddAssign<F> for CompensatedSum<F> {
+    fn add_assign(&mut self, v: F) {
+        self.add(v);
+    }
+}
+
+impl<F: Floating> AddAssign for CompensatedSum<F> {
+    fn add_assign(&mut self, rhs: Self) {
+        self.add(rhs.sum);
+        self.c = self.c + rhs.c;
+    }
+}
+
+impl<F: Floating> Add for CompensatedSum<F> {
+    type Output = CompensatedSum<F>;
+
+    fn add(mut self, rhs: CompensatedSum<F>) -> CompensatedSum<F> {
+        self += rhs;
+        self
+    }
+}
+
+impl<F: Floating> Extend<F> for CompensatedSum<F> {
+    fn extend<I: IntoIterator<Item = F>>(&mut self, values: I) {
+        values.into_iter().for_each(|v| self.add(v));
+    }
+}
+
+impl<F: Floating> FromIterator<F> for CompensatedSum<F> {
+    fn from_iter<I: IntoIterator<Item = F>>(values: I) -> Self {
+        let mut sum = Self::new();
+        sum.extend(values);
+        sum
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::next_float_up;
-    use quickcheck::quickcheck;
+    use super::*;
+    use quickcheck::{quickcheck, TestResult};
+
+    quickcheck! {
+        fn next_float(bits: u32) -> TestResult {
+            let val = f32::from_bits(bits);
+            if !val.is_finite() {
+                return TestResult::discard();
+            }
+            // Both zeros step to the smallest positive subnormal.
+            let expected = if val == 0.0 {
+                1
+            } else if val > 0.0 {
+                bits + 1
+            } else {
+                bits - 1
+            };
+            TestResult::from_bool(next_float_up(val).to_bits() == expected)
+        }
+    }
+
+    quickcheck! {
+        fn next_float_down_is_below(val: f32) -> TestResult {
+            if !val.is_finite() {
+                return TestResult::discard();
+            }
+            let down = next_float_down(val);
+            TestResult::from_bool(down < val && next_float_up(down) == if val == 0.0 { 0.0 } else { val })
+        }
+
+        fn error_bounds_hold(a: f32, b: f32, c: f32) -> TestResult {
+            if ![a, b, c].iter().all(|x| x.is_finite() && x.abs() < 1e6) {
+                return TestResult::discard();
+            }
+            // (a b + c) / (a - c) and √(a² + b²), compared to the same
+            // computations in double precision.
+            let (x, y, z) = (ErrorFloat::new(a), ErrorFloat::new(b), ErrorFloat::new(c));
+            let (a, b, c) = (a as f64, b as f64, c as f64);
+            let within = |e: ErrorFloat, exact: f64| {
+                !e.error().is_finite()
+                    || (e.lower_bound() as f64 <= exact && exact <= e.upper_bound() as f64)
+            };
+            let quotient = (x * y + z) / (x - z);
+            let norm = (x * x + y * y).sqrt();
+            TestResult::from_bool(
+                within(quotient, (a * b + c) / (a - c)) && within(norm, (a * a + b * b).sqrt()),
+            )
+        }
+    }
 
     quickcheck! {
-        fn next_float(val: u32) -> bool {
-            let val_u32 = val % (u32::MAX - 1);
-            let next_u32 = val + 1;
-            let next_f32 = next_float_up(val_u32 as f32);
-            next_f32.to_bits() == next_u32
+        fn error_free_transformations_are_exact(a: f32, b: f32) -> TestResult {
+            if !(a.is_finite() && b.is_finite() && a.abs() < 1e18 && b.abs() < 1e18) {
+                return TestResult::discard();
+            }
+            // Sums and products of two f32 are exact in f64, away from
+            // underflow.
+            let (s, e) = two_sum(a, b);
+            let (p, f) = two_prod(a, b);
+            let (a, b) = (a as f64, b as f64);
+            let prod_exact = (a * b).abs() < 1e-30 || p as f64 + f as f64 == a * b;
+            TestResult::from_bool(s as f64 + e as f64 == a + b && prod_exact)
+        }
+
+        fn next_double_is_adjacent(val: f64) -> TestResult {
+            if !val.is_finite() || val == 0.0 {
+                return TestResult::discard();
+            }
+            let (up, down) = (next_double_up(val), next_double_down(val));
+            TestResult::from_bool(
+                down < val && val < up && next_double_down(up) == val && next_double_up(down) == val,
+            )
+        }
+
+        fn intervals_contain_exact_values(a: f32, b: f32, c: f32) -> TestResult {
+            if ![a, b, c].iter().all(|x| x.is_finite() && x.abs() < 1e6) {
+                return TestResult::discard();
+            }
+            // The same expressions in double precision, exact up to a
+            // relative error far below that of single precision.
+            let (x, y, z) = (Interval::from(a), Interval::from(b), Interval::with_error(c, 0.5));
+            let (a, b, c) = (a as f64, b as f64, c as f64);
+            let within = |i: Interval<f32>, exact: f64| {
+                exact.is_nan() || (i.low() as f64 <= exact && exact <= i.high() as f64)
+            };
+            let mut ok = true;
+            for c in [c - 0.5, c, c + 0.5] {
+                ok &= within((x * y - z) / (x + z), (a * b - c) / (a + c));
+                ok &= within((x.square() + z.square()).sqrt(), (a * a + c * c).sqrt());
+                ok &= within(-x * z, -a * c);
+            }
+            TestResult::from_bool(ok)
         }
     }
+
+    #[test]
+    fn compensated_sums() {
+        // Increments lost below the rounding of a large f32 sum.
+        let values = || std::iter::once(1e8f32).chain(std::iter::repeat_n(1.0, 1_000_000));
+        assert_eq!(values().sum::<f32>(), 1e8);
+        assert_eq!(kahan_sum(values()), 1.01e8);
+        assert_eq!(neumaier_sum(values()), 1.01e8);
+        // Kahan summation fails when a value outweighs the running sum.
+        let values = [1.0f64, 1e100, 1.0, -1e100];
+        assert_eq!(kahan_sum(values), 0.0);
+        assert_eq!(neumaier_sum(values), 2.0);
+
+        // Partial sums merge without losing their compensation.
+        let mut a: CompensatedSum<f32> = std::iter::repeat_n(0.1, 100_000).collect();
+        let b = CompensatedSum::from(1e6) + std::iter::repeat_n(0.1, 100_000).collect();
+        a += b;
+        let exact = 1e6 + 2e5 * 0.1f32 as f64;
+        assert!((a.value() as f64 - exact).abs() <= 0.0625);
+    }
+
+    #[test]
+    fn difference_of_products_avoids_cancellation() {
+        // (1 + 2⁻¹²)² rounds to 1 + 2⁻¹¹, losing the 2⁻²⁴ that remains.
+        let a = 1.0 + 2f32.powi(-12);
+        let c = 1.0 + 2f32.powi(-11);
+        assert_eq!(a * a - c, 0.0);
+        assert_eq!(difference_of_products(a, a, c, 1.0), 2f32.powi(-24));
+        assert_eq!(difference_of_products(c, 1.0, a, a), -2f32.powi(-24));
+        assert_eq!(two_prod(a, a), (c, 2f32.powi(-24)));
+    }
+
+    #[test]
+    fn interval_arithmetic() {
+        let third = Interval::from(1.0f32) / Interval::from(3.0);
+        assert!(third.contains(1.0 / 3.0) && !third.is_exact());
+        assert!(third.width() <= 4.0 * f32::EPSILON);
+        // 3 · (1 / 3) is 1 up to rounding, which the interval cannot decide.
+        let one = third * Interval::from(3.0);
+        assert!(one.contains(1.0));
+        assert_eq!(one.compare(&Interval::from(1.0)), None);
+        assert_eq!(one.compare(&Interval::from(1.1)), Some(Ordering::Less));
+        assert_eq!(
+            Interval::from(2.0).compare(&Interval::from(2.0)),
+            Some(Ordering::Equal)
+        );
+
+        let straddling = Interval::new(1.0f64, -2.0);
+        assert_eq!(straddling.low(), -2.0);
+        assert_eq!(straddling.square().low(), 0.0);
+        assert!(straddling.square().high() >= 4.0);
+        assert_eq!((straddling * straddling).low(), next_double_down(-2.0));
+        assert_eq!((Interval::from(1.0) / straddling).high(), f64::INFINITY);
+        assert_eq!(Interval::new(-4.0, 4.0).sqrt().low(), 0.0);
+        assert!(Interval::with_error(2.0, 0.5).contains(2.5));
+        assert_eq!(Interval::new(1.0, 3.0).midpoint(), 2.0);
+    }
+
+    #[test]
+    fn error_grows_with_cancellation() {
+        let third = ErrorFloat::new(1.0) / ErrorFloat::new(3.0);
+        assert!(third.relative_error() <= 1.01 * mre(1));
+        // 1 / 3 · 3 - 1 cancels, leaving an error larger than the value.
+        let cancelled = third * ErrorFloat::new(3.0) - ErrorFloat::new(1.0);
+        assert!(cancelled.error() >= cancelled.value().abs());
+        assert!(cancelled.lower_bound() <= 0.0 && cancelled.upper_bound() >= 0.0);
+        assert_eq!(
+            (ErrorFloat::new(1.0) / (third - third)).error(),
+            f32::INFINITY
+        );
+        assert_eq!(mre_f64(1), f64::MACH_EPS / (1.0 - f64::MACH_EPS));
+    }
 }
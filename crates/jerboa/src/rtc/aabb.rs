@@ -0,0 +1,167 @@
+//! Axis-aligned bounding boxes.
+
+use crate::rtc::ray::Ray;
+use glam::Vec3;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+impl Aabb {
+    /// Box containing nothing, the identity of [`Aabb::union`].
+    pub const EMPTY: Aabb = Aabb {
+        min: Vec3::splat(f32::INFINITY),
+        max: Vec3::splat(f32::NEG_INFINITY),
+    };
+
+    /// Box containing the whole space.
+    pub const INFINITE: Aabb = Aabb {
+        min: Vec3::splat(f32::NEG_INFINITY),
+        max: Vec3::splat(f32::INFINITY),
+    };
+
+    /// Creates the box spanned by two opposite corners.
+    pub fn new(a: Vec3, b: Vec3) -> Self {
+        Self {
+            min: a.min(b),
+            max: a.max(b),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.cmpgt(self.max).any()
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.min.is_finite() && self.max.is_finite()
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    pub fn union_point(&self, p: Vec3) -> Aabb {
+        Aabb {
+            min: self.min.min(p),
+            max: self.max.max(p),
+        }
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn diagonal(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let d = self.diagonal();
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    /// Centre and radius of a sphere enclosing the box.
+    pub fn bounding_sphere(&self) -> (Vec3, f32) {
+        if self.is_empty() {
+            return (Vec3::ZERO, 0.0);
+        }
+        (self.centroid(), 0.5 * self.diagonal().length())
+    }
+
+    /// Index of the axis along which the box is the longest.
+    pub fn maximum_extent(&self) -> usize {
+        let d = self.diagonal();
+        if d.x > d.y && d.x > d.z {
+            0
+        } else if d.y > d.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Position of `p` relative to the corners, `0` at `min` and `1` at `max`.
+    pub fn offset(&self, p: Vec3) -> Vec3 {
+        let d = self.diagonal();
+        let o = p - self.min;
+        Vec3::new(
+            if d.x > 0.0 { o.x / d.x } else { 0.0 },
+            if d.y > 0.0 { o.y / d.y } else { 0.0 },
+            if d.z > 0.0 { o.z / d.z } else { 0.0 },
+        )
+    }
+
+    /// Parametric range `[t0, t1]` of the ray inside the box, clipped to
+    /// `[0, t_max]`, if the ray hits it.
+    pub fn intersect_p(&self, ray: &Ray, t_max: f32) -> Option<(f32, f32)> {
+        let t_near = (self.min - ray.o) * ray.d_rcp;
+        let t_far = (self.max - ray.o) * ray.d_rcp;
+        // Rays parallel to a slab get infinite distances to its planes, of
+        // the same sign if they start outside of it.
+        let t0 = t_near.min(t_far).max_element().max(0.0);
+        let t1 = t_near.max(t_far).min_element().min(t_max);
+        (t0 <= t1).then_some((t0, t1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unions_and_measures() {
+        let a = Aabb::new(Vec3::ONE, Vec3::ZERO);
+        assert_eq!(a.min, Vec3::ZERO);
+        assert!(Aabb::EMPTY.is_empty() && !a.is_empty());
+        assert_eq!(Aabb::EMPTY.union(&a), a);
+        let b = a.union_point(Vec3::new(3.0, 0.5, 0.5));
+        assert_eq!(b.maximum_extent(), 0);
+        assert_eq!(b.surface_area(), 2.0 * (3.0 + 1.0 + 3.0));
+        assert_eq!(b.offset(Vec3::new(1.5, 1.0, 0.0)), Vec3::new(0.5, 1.0, 0.0));
+        assert!(!Aabb::INFINITE.is_finite());
+        assert_eq!(a.bounding_sphere(), (Vec3::splat(0.5), 0.75f32.sqrt()));
+    }
+
+    #[test]
+    fn ray_slabs() {
+        let b = Aabb::new(Vec3::splat(-1.0), Vec3::ONE);
+        let (t0, t1) = b
+            .intersect_p(&Ray::new(Vec3::new(0.0, 0.0, 5.0), -Vec3::Z), f32::INFINITY)
+            .unwrap();
+        assert_eq!((t0, t1), (4.0, 6.0));
+        assert!(b
+            .intersect_p(&Ray::new(Vec3::new(0.0, 0.0, 5.0), -Vec3::Z), 3.0)
+            .is_none());
+        assert!(b
+            .intersect_p(&Ray::new(Vec3::new(0.0, 2.0, 5.0), -Vec3::Z), f32::INFINITY)
+            .is_none());
+        // Inside the box, and parallel to the slabs of two axes.
+        assert_eq!(
+            b.intersect_p(&Ray::new(Vec3::ZERO, Vec3::X), f32::INFINITY),
+            Some((0.0, 1.0))
+        );
+        assert!(b
+            .intersect_p(&Ray::new(Vec3::new(-2.0, 0.5, 0.0), Vec3::X), f32::INFINITY)
+            .is_some());
+        assert!(b
+            .intersect_p(&Ray::new(Vec3::new(-2.0, 1.5, 0.0), Vec3::X), f32::INFINITY)
+            .is_none());
+        assert!(Aabb::INFINITE
+            .intersect_p(&Ray::new(Vec3::ZERO, Vec3::Y), 1.0)
+            .is_some());
+    }
+}
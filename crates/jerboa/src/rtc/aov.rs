@@ -0,0 +1,455 @@
+//! Arbitrary output variables: auxiliary images written by integrators
+//! alongside the beauty, used for denoising and compositing.
+
+use crate::core::{
+    image::{
+        codec::exr::{write_exr_to_stream, ExrLayer, ExrSamples},
+        error::ImageError,
+        PixelBuffer, PixelBufferRgb32f,
+    },
+    Vec1, Vec2 as Uv, Vec3 as Rgb,
+};
+use glam::{Vec2, Vec3};
+use std::{fs::File, io::BufWriter, path::Path};
+
+/// Identifier written for pixels whose samples hit no surface.
+pub const INVALID_ID: u32 = u32::MAX;
+
+/// Auxiliary quantity recorded per pixel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AovKind {
+    /// Distance along the camera ray to the first hit.
+    Depth,
+
+    /// World space position of the first hit.
+    Position,
+
+    /// Shading normal at the first hit.
+    Normal,
+
+    /// Reflectance of the material at the first hit.
+    Albedo,
+
+    /// Index of the primitive at the first hit.
+    PrimitiveId,
+
+    /// Index of the material at the first hit.
+    MaterialId,
+
+    /// Surface coordinates of the first hit.
+    Uv,
+
+    /// Number of samples taken in the pixel.
+    SampleCount,
+
+    /// Sample variance of the radiance estimates in the pixel.
+    Variance,
+}
+
+impl AovKind {
+    pub const ALL: [AovKind; 9] = [
+        AovKind::Depth,
+        AovKind::Position,
+        AovKind::Normal,
+        AovKind::Albedo,
+        AovKind::PrimitiveId,
+        AovKind::MaterialId,
+        AovKind::Uv,
+        AovKind::SampleCount,
+        AovKind::Variance,
+    ];
+
+    /// Name of the buffer, used as the layer name in files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AovKind::Depth => "depth",
+            AovKind::Position => "position",
+            AovKind::Normal => "normal",
+            AovKind::Albedo => "albedo",
+            AovKind::PrimitiveId => "primitive_id",
+            AovKind::MaterialId => "material_id",
+            AovKind::Uv => "uv",
+            AovKind::SampleCount => "sample_count",
+            AovKind::Variance => "variance",
+        }
+    }
+
+    /// Names of the channels of the buffer.
+    pub fn channels(&self) -> &'static [&'static str] {
+        match self {
+            AovKind::Depth => &["Z"],
+            AovKind::Position | AovKind::Normal => &["X", "Y", "Z"],
+            AovKind::Albedo | AovKind::Variance => &["R", "G", "B"],
+            AovKind::PrimitiveId | AovKind::MaterialId => &["id"],
+            AovKind::Uv => &["U", "V"],
+            AovKind::SampleCount => &["count"],
+        }
+    }
+}
+
+/// Auxiliary quantities of one camera sample, filled in by the integrator at
+/// the first surface hit.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AovSample {
+    /// Whether the camera ray hit a surface; the other fields are ignored
+    /// otherwise.
+    pub hit: bool,
+    pub depth: f32,
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub albedo: Vec3,
+    pub uv: Vec2,
+    pub primitive_id: u32,
+    pub material_id: u32,
+}
+
+impl Default for AovSample {
+    fn default() -> Self {
+        Self {
+            hit: false,
+            depth: f32::INFINITY,
+            position: Vec3::ZERO,
+            normal: Vec3::ZERO,
+            albedo: Vec3::ZERO,
+            uv: Vec2::ZERO,
+            primitive_id: INVALID_ID,
+            material_id: INVALID_ID,
+        }
+    }
+}
+
+/// Running sums of a pixel.
+#[derive(Debug, Copy, Clone)]
+struct AovPixel {
+    n_samples: u32,
+    n_hits: u32,
+    depth: f32,
+    position: Vec3,
+    normal: Vec3,
+    albedo: Vec3,
+    uv: Vec2,
+    primitive_id: u32,
+    material_id: u32,
+
+    /// Mean and sum of squared deviations of the radiance (Welford).
+    mean: Vec3,
+    m2: Vec3,
+}
+
+impl Default for AovPixel {
+    fn default() -> Self {
+        Self {
+            n_samples: 0,
+            n_hits: 0,
+            depth: 0.0,
+            position: Vec3::ZERO,
+            normal: Vec3::ZERO,
+            albedo: Vec3::ZERO,
+            uv: Vec2::ZERO,
+            primitive_id: INVALID_ID,
+            material_id: INVALID_ID,
+            mean: Vec3::ZERO,
+            m2: Vec3::ZERO,
+        }
+    }
+}
+
+/// Resolved auxiliary image.
+#[derive(Debug, Clone)]
+pub enum AovBuffer {
+    Scalar(PixelBuffer<Vec1<f32>>),
+    Uv(PixelBuffer<Uv<f32>>),
+    Rgb(PixelBuffer<Rgb<f32>>),
+    Uint(PixelBuffer<Vec1<u32>>),
+}
+
+impl AovBuffer {
+    fn exr_samples(&self) -> ExrSamples<'_> {
+        match self {
+            AovBuffer::Scalar(b) => ExrSamples::Float(b.samples()),
+            AovBuffer::Uv(b) => ExrSamples::Float(b.samples()),
+            AovBuffer::Rgb(b) => ExrSamples::Float(b.samples()),
+            AovBuffer::Uint(b) => ExrSamples::Uint(b.samples()),
+        }
+    }
+}
+
+/// Accumulates the auxiliary quantities of the samples of each pixel.
+///
+/// Geometric quantities are averaged over the samples hitting a surface;
+/// pixels without hits get an infinite depth, zero vectors and
+/// [`INVALID_ID`]. Identifiers cannot be averaged and are those of the first
+/// sample hitting a surface.
+#[derive(Debug, Clone)]
+pub struct Aovs {
+    width: u32,
+    height: u32,
+    kinds: Vec<AovKind>,
+    pixels: Vec<AovPixel>,
+}
+
+impl Aovs {
+    /// Creates empty buffers for the given kinds of outputs.
+    pub fn new(width: u32, height: u32, kinds: &[AovKind]) -> Self {
+        let mut unique = Vec::with_capacity(kinds.len());
+        for kind in kinds {
+            if !unique.contains(kind) {
+                unique.push(*kind);
+            }
+        }
+        Self {
+            width,
+            height,
+            kinds: unique,
+            pixels: vec![AovPixel::default(); (width * height) as usize],
+        }
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Kinds of outputs produced, in the order they were requested.
+    pub fn kinds(&self) -> &[AovKind] {
+        &self.kinds
+    }
+
+    /// Records a camera sample of pixel `(x, y)` with its radiance estimate.
+    pub fn record(&mut self, x: u32, y: u32, radiance: Vec3, sample: &AovSample) {
+        let p = &mut self.pixels[(y * self.width + x) as usize];
+        p.n_samples += 1;
+        let delta = radiance - p.mean;
+        p.mean += delta / p.n_samples as f32;
+        p.m2 += delta * (radiance - p.mean);
+
+        if sample.hit {
+            p.n_hits += 1;
+            p.depth += sample.depth;
+            p.position += sample.position;
+            p.normal += sample.normal;
+            p.albedo += sample.albedo;
+            p.uv += sample.uv;
+            if p.n_hits == 1 {
+                p.primitive_id = sample.primitive_id;
+                p.material_id = sample.material_id;
+            }
+        }
+    }
+
+    /// Number of samples recorded for pixel `(x, y)`.
+    pub fn sample_count(&self, x: u32, y: u32) -> u32 {
+        self.pixels[(y * self.width + x) as usize].n_samples
+    }
+
+    /// Mean of the radiance estimates recorded for pixel `(x, y)`.
+    pub fn mean(&self, x: u32, y: u32) -> Vec3 {
+        self.pixels[(y * self.width + x) as usize].mean
+    }
+
+    /// Sample variance of the radiance estimates recorded for pixel `(x, y)`.
+    pub fn variance(&self, x: u32, y: u32) -> Vec3 {
+        let p = &self.pixels[(y * self.width + x) as usize];
+        if p.n_samples < 2 {
+            Vec3::ZERO
+        } else {
+            p.m2 / (p.n_samples - 1) as f32
+        }
+    }
+
+    /// Adds the samples recorded in `other`, of the same dimensions.
+    pub fn merge(&mut self, other: &Aovs) {
+        assert_eq!(
+            self.dimensions(),
+            other.dimensions(),
+            "aov dimensions mismatch"
+        );
+        for (a, b) in self.pixels.iter_mut().zip(&other.pixels) {
+            if b.n_samples == 0 {
+                continue;
+            }
+            // Chan et al. parallel combination of the running moments.
+            let n = a.n_samples + b.n_samples;
+            let delta = b.mean - a.mean;
+            a.m2 += b.m2 + delta * delta * (a.n_samples as f32 * b.n_samples as f32 / n as f32);
+            a.mean += delta * (b.n_samples as f32 / n as f32);
+            a.n_samples = n;
+            if a.n_hits == 0 {
+                a.primitive_id = b.primitive_id;
+                a.material_id = b.material_id;
+            }
+            a.n_hits += b.n_hits;
+            a.depth += b.depth;
+            a.position += b.position;
+            a.normal += b.normal;
+            a.albedo += b.albedo;
+            a.uv += b.uv;
+        }
+    }
+
+    /// Resolves an output, whether or not it was requested.
+    pub fn buffer(&self, kind: AovKind) -> AovBuffer {
+        let (w, h) = (self.width, self.height);
+        let average = |p: &AovPixel, v: Vec3| {
+            if p.n_hits == 0 {
+                Vec3::ZERO
+            } else {
+                v / p.n_hits as f32
+            }
+        };
+        let rgb = |f: &dyn Fn(&AovPixel) -> Vec3| {
+            let mut b = PixelBuffer::<Rgb<f32>>::new(w, h);
+            for ((_, dst), p) in b.pixels_mut().zip(&self.pixels) {
+                *dst = Rgb::from(f(p).to_array());
+            }
+            AovBuffer::Rgb(b)
+        };
+        let uint = |f: &dyn Fn(&AovPixel) -> u32| {
+            let mut b = PixelBuffer::<Vec1<u32>>::new(w, h);
+            for ((_, dst), p) in b.pixels_mut().zip(&self.pixels) {
+                *dst = Vec1::from([f(p)]);
+            }
+            AovBuffer::Uint(b)
+        };
+        match kind {
+            AovKind::Depth => {
+                let mut b = PixelBuffer::<Vec1<f32>>::new(w, h);
+                for ((_, dst), p) in b.pixels_mut().zip(&self.pixels) {
+                    let depth = if p.n_hits == 0 {
+                        f32::INFINITY
+                    } else {
+                        p.depth / p.n_hits as f32
+                    };
+                    *dst = Vec1::from([depth]);
+                }
+                AovBuffer::Scalar(b)
+            }
+            AovKind::Position => rgb(&|p| average(p, p.position)),
+            AovKind::Normal => rgb(&|p| average(p, p.normal).normalize_or_zero()),
+            AovKind::Albedo => rgb(&|p| average(p, p.albedo)),
+            AovKind::PrimitiveId => uint(&|p| p.primitive_id),
+            AovKind::MaterialId => uint(&|p| p.material_id),
+            AovKind::Uv => {
+                let mut b = PixelBuffer::<Uv<f32>>::new(w, h);
+                for ((_, dst), p) in b.pixels_mut().zip(&self.pixels) {
+                    let uv = average(p, p.uv.extend(0.0));
+                    *dst = Uv::from([uv.x, uv.y]);
+                }
+                AovBuffer::Uv(b)
+            }
+            AovKind::SampleCount => uint(&|p| p.n_samples),
+            AovKind::Variance => {
+                let mut b = PixelBuffer::<Rgb<f32>>::new(w, h);
+                for ((x, y), dst) in b.pixels_mut() {
+                    *dst = Rgb::from(self.variance(x as u32, y as u32).to_array());
+                }
+                AovBuffer::Rgb(b)
+            }
+        }
+    }
+
+    /// Resolves the requested outputs, with their names.
+    pub fn buffers(&self) -> Vec<(&'static str, AovBuffer)> {
+        self.kinds
+            .iter()
+            .map(|kind| (kind.name(), self.buffer(*kind)))
+            .collect()
+    }
+
+    /// Writes the beauty and the requested outputs as the layers of an EXR
+    /// file. The beauty is stored in the unprefixed `R`, `G` and `B`
+    /// channels, the outputs in channels prefixed with their name.
+    pub fn write_as_exr<P: AsRef<Path>>(
+        &self,
+        path: P,
+        beauty: &PixelBufferRgb32f,
+    ) -> Result<(), ImageError> {
+        assert_eq!(
+            self.dimensions(),
+            beauty.dimensions(),
+            "beauty dimensions mismatch"
+        );
+        let path = path.as_ref().with_extension("exr");
+        let mut writer = BufWriter::new(File::create(path)?);
+        let buffers = self.buffers();
+        let mut layers = vec![ExrLayer {
+            name: "",
+            channels: &["R", "G", "B"],
+            samples: ExrSamples::Float(beauty.samples()),
+        }];
+        for (kind, (name, buffer)) in self.kinds.iter().zip(&buffers) {
+            layers.push(ExrLayer {
+                name,
+                channels: kind.channels(),
+                samples: buffer.exr_samples(),
+            });
+        }
+        write_exr_to_stream(&mut writer, self.width, self.height, &layers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(depth: f32, id: u32) -> AovSample {
+        AovSample {
+            hit: true,
+            depth,
+            normal: Vec3::Y,
+            primitive_id: id,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn averages_hits_and_keeps_first_id() {
+        let mut aovs = Aovs::new(
+            2,
+            1,
+            &[AovKind::Depth, AovKind::PrimitiveId, AovKind::Depth],
+        );
+        assert_eq!(aovs.kinds().len(), 2);
+        aovs.record(0, 0, Vec3::ONE, &hit(2.0, 3));
+        aovs.record(0, 0, Vec3::ONE, &AovSample::default());
+        aovs.record(0, 0, Vec3::ONE, &hit(4.0, 5));
+        match aovs.buffer(AovKind::Depth) {
+            AovBuffer::Scalar(b) => {
+                assert_eq!(b.pixel_at(0, 0).unwrap()[0], 3.0);
+                assert_eq!(b.pixel_at(1, 0).unwrap()[0], f32::INFINITY);
+            }
+            _ => panic!("depth is a scalar buffer"),
+        }
+        match aovs.buffer(AovKind::PrimitiveId) {
+            AovBuffer::Uint(b) => {
+                assert_eq!(b.pixel_at(0, 0).unwrap()[0], 3);
+                assert_eq!(b.pixel_at(1, 0).unwrap()[0], INVALID_ID);
+            }
+            _ => panic!("ids are integer buffers"),
+        }
+        match aovs.buffer(AovKind::SampleCount) {
+            AovBuffer::Uint(b) => assert_eq!(b.pixel_at(0, 0).unwrap()[0], 3),
+            _ => panic!("sample counts are integer buffers"),
+        }
+    }
+
+    #[test]
+    fn merged_variance_matches_sequential() {
+        let values = [1.0, 4.0, 2.0, 8.0, 3.0];
+        let mut all = Aovs::new(1, 1, &[AovKind::Variance]);
+        let mut first = Aovs::new(1, 1, &[AovKind::Variance]);
+        let mut second = Aovs::new(1, 1, &[AovKind::Variance]);
+        for (i, v) in values.iter().enumerate() {
+            let sample = AovSample::default();
+            all.record(0, 0, Vec3::splat(*v), &sample);
+            let part = if i < 2 { &mut first } else { &mut second };
+            part.record(0, 0, Vec3::splat(*v), &sample);
+        }
+        first.merge(&second);
+        let variance = |aovs: &Aovs| match aovs.buffer(AovKind::Variance) {
+            AovBuffer::Rgb(b) => b.pixel_at(0, 0).unwrap()[0],
+            _ => unreachable!(),
+        };
+        assert!((variance(&all) - 7.3).abs() < 1e-5);
+        assert!((variance(&first) - variance(&all)).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,333 @@
+//! Scattering functions describing how surfaces reflect and transmit light.
+//!
+//! Directions are in world space and point away from the surface; `n` is the
+//! surface normal, on either side of the surface.
+
+use crate::rtc::sampling::{coordinate_system, cosine_hemisphere_pdf, cosine_sample_hemisphere};
+use glam::{Vec2, Vec3};
+use std::f32::consts::FRAC_1_PI;
+
+/// Quantity carried by a path being sampled. Refraction scales radiance but
+/// not importance, making the BSDF of dielectrics non-symmetric.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TransportMode {
+    /// Paths traced from the camera.
+    Radiance,
+
+    /// Paths traced from the lights.
+    Importance,
+}
+
+/// Incident direction sampled from a BSDF.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BsdfSample {
+    pub wi: Vec3,
+
+    /// Value of the BSDF for the pair of directions.
+    pub f: Vec3,
+
+    /// Density of `wi` with respect to solid angle. For specular
+    /// scattering, it is the probability of choosing the lobe and `f` is
+    /// divided by the cosine of `wi`, so that the estimator keeps its usual
+    /// form `f |cos θ| / pdf`.
+    pub pdf: f32,
+
+    pub specular: bool,
+}
+
+pub trait Bsdf: Send + Sync {
+    /// Value of the BSDF for the pair of directions; zero for specular
+    /// BSDFs.
+    fn f(&self, wo: Vec3, wi: Vec3, n: Vec3) -> Vec3;
+
+    /// Samples an incident direction for the outgoing direction `wo`.
+    fn sample_f(&self, wo: Vec3, n: Vec3, u: Vec2, mode: TransportMode) -> Option<BsdfSample>;
+
+    /// Density with respect to solid angle of [`Bsdf::sample_f`] returning
+    /// `wi`.
+    fn pdf(&self, wo: Vec3, wi: Vec3, n: Vec3) -> f32;
+
+    /// Whether the BSDF only scatters in discrete directions.
+    fn is_specular(&self) -> bool {
+        false
+    }
+
+    /// Directions in which a specular BSDF scatters light coming from `wo`,
+    /// with their weights `f |cos θ|`.
+    fn specular_directions(&self, _wo: Vec3, _n: Vec3) -> Vec<(Vec3, Vec3)> {
+        Vec::new()
+    }
+
+    /// Overall colour of the surface, as recorded in the albedo AOV.
+    fn albedo(&self) -> Vec3;
+}
+
+/// Mirrors `wo` about the normal `n`.
+pub fn reflect(wo: Vec3, n: Vec3) -> Vec3 {
+    -wo + 2.0 * wo.dot(n) * n
+}
+
+/// Refracts `wo` through a surface of normal `n` on the side of `wo`, where
+/// `eta` is the ratio of the index of refraction of the other side to the
+/// one of `wo`. Returns `None` on total internal reflection.
+pub fn refract(wo: Vec3, n: Vec3, eta: f32) -> Option<Vec3> {
+    let cos_i = wo.dot(n);
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-wo / eta + (cos_i / eta - cos_t) * n)
+}
+
+/// Fresnel reflectance of unpolarised light at the interface between two
+/// dielectrics, for a cosine `cos_i` of the incident angle measured on the
+/// side of the medium of index 1, the other having index `eta`.
+pub fn fresnel_dielectric(cos_i: f32, eta: f32) -> f32 {
+    let (cos_i, eta) = if cos_i < 0.0 {
+        (-cos_i, 1.0 / eta)
+    } else {
+        (cos_i, eta)
+    };
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let r_parl = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perp = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    0.5 * (r_parl * r_parl + r_perp * r_perp)
+}
+
+/// Lambertian reflection.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Diffuse {
+    pub albedo: Vec3,
+}
+
+impl Diffuse {
+    pub fn new(albedo: Vec3) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Bsdf for Diffuse {
+    fn f(&self, wo: Vec3, wi: Vec3, n: Vec3) -> Vec3 {
+        if wo.dot(n) * wi.dot(n) > 0.0 {
+            self.albedo * FRAC_1_PI
+        } else {
+            Vec3::ZERO
+        }
+    }
+
+    fn sample_f(&self, wo: Vec3, n: Vec3, u: Vec2, _mode: TransportMode) -> Option<BsdfSample> {
+        let n = if wo.dot(n) < 0.0 { -n } else { n };
+        let (s, t) = coordinate_system(n);
+        let local = cosine_sample_hemisphere(u);
+        let wi = s * local.x + t * local.y + n * local.z;
+        let pdf = cosine_hemisphere_pdf(local.z);
+        if pdf == 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            wi,
+            f: self.albedo * FRAC_1_PI,
+            pdf,
+            specular: false,
+        })
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3, n: Vec3) -> f32 {
+        if wo.dot(n) * wi.dot(n) > 0.0 {
+            cosine_hemisphere_pdf(wi.dot(n).abs())
+        } else {
+            0.0
+        }
+    }
+
+    fn albedo(&self) -> Vec3 {
+        self.albedo
+    }
+}
+
+/// Perfect specular reflection.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Mirror {
+    pub reflectance: Vec3,
+}
+
+impl Mirror {
+    pub fn new(reflectance: Vec3) -> Self {
+        Self { reflectance }
+    }
+}
+
+impl Bsdf for Mirror {
+    fn f(&self, _wo: Vec3, _wi: Vec3, _n: Vec3) -> Vec3 {
+        Vec3::ZERO
+    }
+
+    fn sample_f(&self, wo: Vec3, n: Vec3, _u: Vec2, _mode: TransportMode) -> Option<BsdfSample> {
+        let wi = reflect(wo, n);
+        Some(BsdfSample {
+            wi,
+            f: self.reflectance / wi.dot(n).abs(),
+            pdf: 1.0,
+            specular: true,
+        })
+    }
+
+    fn pdf(&self, _wo: Vec3, _wi: Vec3, _n: Vec3) -> f32 {
+        0.0
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    fn specular_directions(&self, wo: Vec3, n: Vec3) -> Vec<(Vec3, Vec3)> {
+        vec![(reflect(wo, n), self.reflectance)]
+    }
+
+    fn albedo(&self) -> Vec3 {
+        self.reflectance
+    }
+}
+
+/// Smooth interface between the outside, of index 1, and a dielectric of
+/// index `eta` on the side the normal points away from, e.g. glass or water.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Dielectric {
+    pub eta: f32,
+}
+
+impl Dielectric {
+    pub fn new(eta: f32) -> Self {
+        Self { eta }
+    }
+
+    /// Reflected and refracted directions with their weights. The radiance
+    /// of refracted light is scaled by the squared ratio of the indices, its
+    /// importance is not.
+    fn scattered(
+        &self,
+        wo: Vec3,
+        n: Vec3,
+        mode: TransportMode,
+    ) -> (Vec3, f32, Option<(Vec3, f32)>) {
+        let cos_o = wo.dot(n);
+        let fresnel = fresnel_dielectric(cos_o, self.eta);
+        let (n_o, eta) = if cos_o >= 0.0 {
+            (n, self.eta)
+        } else {
+            (-n, 1.0 / self.eta)
+        };
+        let reflected = reflect(wo, n);
+        let refracted = refract(wo, n_o, eta)
+            .filter(|_| fresnel < 1.0)
+            .map(|wi| match mode {
+                TransportMode::Radiance => (wi, (1.0 - fresnel) / (eta * eta)),
+                TransportMode::Importance => (wi, 1.0 - fresnel),
+            });
+        (reflected, fresnel, refracted)
+    }
+}
+
+impl Bsdf for Dielectric {
+    fn f(&self, _wo: Vec3, _wi: Vec3, _n: Vec3) -> Vec3 {
+        Vec3::ZERO
+    }
+
+    fn sample_f(&self, wo: Vec3, n: Vec3, u: Vec2, mode: TransportMode) -> Option<BsdfSample> {
+        let (reflected, fresnel, refracted) = self.scattered(wo, n, mode);
+        let (wi, weight, pdf) = match refracted {
+            Some((wi, weight)) if u.x >= fresnel => (wi, weight, 1.0 - fresnel),
+            _ => (reflected, fresnel, fresnel),
+        };
+        Some(BsdfSample {
+            wi,
+            f: Vec3::splat(weight / wi.dot(n).abs()),
+            pdf,
+            specular: true,
+        })
+    }
+
+    fn pdf(&self, _wo: Vec3, _wi: Vec3, _n: Vec3) -> f32 {
+        0.0
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    fn specular_directions(&self, wo: Vec3, n: Vec3) -> Vec<(Vec3, Vec3)> {
+        let (reflected, fresnel, refracted) = self.scattered(wo, n, TransportMode::Radiance);
+        let mut directions = vec![(reflected, Vec3::splat(fresnel))];
+        directions.extend(refracted.map(|(wi, weight)| (wi, Vec3::splat(weight))));
+        directions
+    }
+
+    fn albedo(&self) -> Vec3 {
+        Vec3::ONE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fresnel_of_glass() {
+        assert!((fresnel_dielectric(1.0, 1.5) - 0.04).abs() < 1e-6);
+        assert!((fresnel_dielectric(-1.0, 1.5) - 0.04).abs() < 1e-6);
+        assert_eq!(fresnel_dielectric(0.0, 1.5), 1.0);
+        // Total internal reflection leaving glass beyond the critical angle.
+        assert_eq!(fresnel_dielectric(-0.5, 1.5), 1.0);
+    }
+
+    #[test]
+    fn refraction_follows_snell() {
+        let n = Vec3::Z;
+        let wo = Vec3::new(0.6, 0.0, 0.8);
+        let wi = refract(wo, n, 1.5).unwrap();
+        assert!((wi.length() - 1.0).abs() < 1e-5);
+        assert!((wi.x.abs() - 0.6 / 1.5).abs() < 1e-5);
+        assert!(wi.z < 0.0 && wi.x < 0.0);
+        assert!(refract(Vec3::new(0.8, 0.0, 0.6), n, 1.0 / 1.5).is_none());
+    }
+
+    #[test]
+    fn dielectric_conserves_energy() {
+        let glass = Dielectric::new(1.5);
+        for wo in [Vec3::Z, Vec3::new(0.6, 0.0, 0.8), Vec3::new(0.0, 0.8, -0.6)] {
+            let total: f32 = glass
+                .specular_directions(wo, Vec3::Z)
+                .iter()
+                .map(|(wi, w)| {
+                    // Undo the radiance scaling of refracted light.
+                    if wi.z * wo.z < 0.0 {
+                        let eta = if wo.z > 0.0 { 1.5f32 } else { 1.0 / 1.5 };
+                        w.x * eta * eta
+                    } else {
+                        w.x
+                    }
+                })
+                .sum();
+            assert!((total - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn diffuse_sampling_matches_pdf() {
+        let diffuse = Diffuse::new(Vec3::splat(0.5));
+        let n = Vec3::new(0.0, 1.0, 0.0);
+        let wo = Vec3::new(0.3, -0.9, 0.1).normalize();
+        let sample = diffuse
+            .sample_f(wo, n, Vec2::new(0.3, 0.7), TransportMode::Radiance)
+            .unwrap();
+        assert!(sample.wi.dot(n) < 0.0);
+        assert!((diffuse.pdf(wo, sample.wi, n) - sample.pdf).abs() < 1e-5);
+        assert_eq!(diffuse.f(wo, sample.wi, n), sample.f);
+        assert_eq!(diffuse.f(wo, -sample.wi, n), Vec3::ZERO);
+    }
+}
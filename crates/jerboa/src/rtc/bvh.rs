@@ -0,0 +1,351 @@
+//! Bounding volume hierarchy over the shapes of a scene.
+//!
+//! The hierarchy is built top-down with the surface area heuristic evaluated
+//! over a fixed number of buckets, and stored as a flat array in depth-first
+//! order: the first child of an interior node immediately follows it.
+//! Unbounded shapes, e.g. planes, are kept aside and tested by every ray.
+
+use crate::rtc::{aabb::Aabb, ray::Ray, IntersectRecord, Shape};
+
+/// Number of buckets over which split candidates are evaluated.
+const N_BUCKETS: usize = 12;
+
+/// Maximum number of primitives in a leaf.
+const MAX_LEAF_SIZE: usize = 4;
+
+/// Cost of traversing an interior node relative to intersecting a primitive.
+const TRAVERSAL_COST: f32 = 0.125;
+
+/// Work done to trace a ray, e.g. to visualise the cost of traversal.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct TraversalStats {
+    pub nodes_visited: u32,
+    pub primitives_tested: u32,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct BvhNode {
+    bounds: Aabb,
+
+    /// First primitive of a leaf, or index of the second child of an
+    /// interior node.
+    offset: u32,
+
+    /// Number of primitives of a leaf, zero for interior nodes.
+    count: u16,
+
+    /// Axis along which the children of an interior node are split.
+    axis: u8,
+}
+
+/// Primitive being sorted into the hierarchy.
+#[derive(Debug, Copy, Clone)]
+struct BuildPrimitive {
+    index: usize,
+    bounds: Aabb,
+    centroid: glam::Vec3,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+
+    /// Shape indices, grouped by leaf.
+    indices: Vec<usize>,
+
+    /// Shapes with infinite bounds.
+    unbounded: Vec<usize>,
+}
+
+impl Bvh {
+    pub fn new(shapes: &[Box<dyn Shape>]) -> Self {
+        let mut primitives = Vec::with_capacity(shapes.len());
+        let mut unbounded = Vec::new();
+        for (index, shape) in shapes.iter().enumerate() {
+            let bounds = shape.bounds();
+            if bounds.is_finite() {
+                primitives.push(BuildPrimitive {
+                    index,
+                    bounds,
+                    centroid: bounds.centroid(),
+                });
+            } else if !bounds.is_empty() {
+                unbounded.push(index);
+            }
+        }
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(2 * primitives.len()),
+            indices: Vec::with_capacity(primitives.len()),
+            unbounded,
+        };
+        if !primitives.is_empty() {
+            bvh.build(&mut primitives);
+        }
+        bvh
+    }
+
+    /// Bounds of the bounded shapes.
+    pub fn bounds(&self) -> Aabb {
+        self.nodes.first().map_or(Aabb::EMPTY, |node| node.bounds)
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Appends the subtree of the primitives, returning the index of its
+    /// root.
+    fn build(&mut self, primitives: &mut [BuildPrimitive]) -> usize {
+        let bounds = primitives
+            .iter()
+            .fold(Aabb::EMPTY, |b, p| b.union(&p.bounds));
+        let node = self.nodes.len();
+        self.nodes.push(BvhNode {
+            bounds,
+            offset: 0,
+            count: 0,
+            axis: 0,
+        });
+
+        let split = if primitives.len() == 1 {
+            None
+        } else {
+            self.find_split(primitives, &bounds)
+        };
+        match split {
+            Some((axis, mid)) => {
+                self.build(&mut primitives[..mid]);
+                let second = self.build(&mut primitives[mid..]);
+                self.nodes[node].offset = second as u32;
+                self.nodes[node].axis = axis as u8;
+            }
+            None => {
+                self.nodes[node].offset = self.indices.len() as u32;
+                self.nodes[node].count = primitives.len() as u16;
+                self.indices.extend(primitives.iter().map(|p| p.index));
+            }
+        }
+        node
+    }
+
+    /// Partitions the primitives along the best split, returning the axis
+    /// and the size of the first part, or `None` if they should form a leaf.
+    fn find_split(
+        &self,
+        primitives: &mut [BuildPrimitive],
+        bounds: &Aabb,
+    ) -> Option<(usize, usize)> {
+        let centroid_bounds = primitives
+            .iter()
+            .fold(Aabb::EMPTY, |b, p| b.union_point(p.centroid));
+        let axis = centroid_bounds.maximum_extent();
+        if centroid_bounds.max[axis] == centroid_bounds.min[axis] {
+            // Coincident centroids: no split separates them.
+            return if primitives.len() <= MAX_LEAF_SIZE {
+                None
+            } else {
+                Some((axis, primitives.len() / 2))
+            };
+        }
+
+        let bucket_of = |p: &BuildPrimitive| {
+            let b = (N_BUCKETS as f32 * centroid_bounds.offset(p.centroid)[axis]) as usize;
+            b.min(N_BUCKETS - 1)
+        };
+        let mut buckets = [(0usize, Aabb::EMPTY); N_BUCKETS];
+        for p in primitives.iter() {
+            let bucket = &mut buckets[bucket_of(p)];
+            bucket.0 += 1;
+            bucket.1 = bucket.1.union(&p.bounds);
+        }
+
+        // Cost of splitting after each bucket, relative to the area of the
+        // node.
+        let (mut best_cost, mut best_split) = (f32::INFINITY, 0);
+        for split in 0..N_BUCKETS - 1 {
+            let (below, above) = buckets.split_at(split + 1);
+            let side = |buckets: &[(usize, Aabb)]| {
+                buckets
+                    .iter()
+                    .fold((0, Aabb::EMPTY), |(n, b), (m, c)| (n + m, b.union(c)))
+            };
+            let ((n0, b0), (n1, b1)) = (side(below), side(above));
+            let cost = TRAVERSAL_COST
+                + (n0 as f32 * b0.surface_area() + n1 as f32 * b1.surface_area())
+                    / bounds.surface_area();
+            if cost < best_cost {
+                best_cost = cost;
+                best_split = split;
+            }
+        }
+
+        let leaf_cost = primitives.len() as f32;
+        if primitives.len() <= MAX_LEAF_SIZE && best_cost >= leaf_cost {
+            return None;
+        }
+        let mut mid = 0;
+        for i in 0..primitives.len() {
+            if bucket_of(&primitives[i]) <= best_split {
+                primitives.swap(i, mid);
+                mid += 1;
+            }
+        }
+        if mid == 0 || mid == primitives.len() {
+            mid = primitives.len() / 2;
+        }
+        Some((axis, mid))
+    }
+
+    /// Closest hit among the shapes, which must be the ones the hierarchy was
+    /// built from.
+    pub fn intersect(
+        &self,
+        shapes: &[Box<dyn Shape>],
+        ray: &Ray,
+        stats: &mut TraversalStats,
+    ) -> Option<(usize, IntersectRecord)> {
+        let mut closest: Option<(usize, IntersectRecord)> = None;
+        let test = |i: usize,
+                    closest: &mut Option<(usize, IntersectRecord)>,
+                    stats: &mut TraversalStats| {
+            stats.primitives_tested += 1;
+            if let Some(record) = shapes[i].intersect(ray) {
+                if !matches!(closest, Some((_, c)) if c.t <= record.t) {
+                    *closest = Some((i, record));
+                }
+            }
+        };
+        for &i in &self.unbounded {
+            test(i, &mut closest, stats);
+        }
+        self.traverse(ray, stats, |i, stats| {
+            test(i, &mut closest, stats);
+            closest.as_ref().map_or(f32::INFINITY, |(_, c)| c.t)
+        });
+        closest
+    }
+
+    /// Whether a shape lies along the ray closer than `t_max`.
+    pub fn occluded(
+        &self,
+        shapes: &[Box<dyn Shape>],
+        ray: &Ray,
+        t_max: f32,
+        stats: &mut TraversalStats,
+    ) -> bool {
+        let blocks = |i: usize, stats: &mut TraversalStats| {
+            stats.primitives_tested += 1;
+            matches!(shapes[i].intersect(ray), Some(record) if record.t < t_max)
+        };
+        if self.unbounded.iter().any(|&i| blocks(i, stats)) {
+            return true;
+        }
+        let mut occluded = false;
+        self.traverse(ray, stats, |i, stats| {
+            if blocks(i, stats) {
+                occluded = true;
+                f32::NEG_INFINITY
+            } else {
+                t_max
+            }
+        });
+        occluded
+    }
+
+    /// Visits the leaves the ray reaches, nearest child first, calling
+    /// `visit` on their primitives. `visit` returns the distance beyond
+    /// which nodes can be skipped.
+    fn traverse<F: FnMut(usize, &mut TraversalStats) -> f32>(
+        &self,
+        ray: &Ray,
+        stats: &mut TraversalStats,
+        mut visit: F,
+    ) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let dir_is_neg = [ray.d.x < 0.0, ray.d.y < 0.0, ray.d.z < 0.0];
+        let mut t_max = f32::INFINITY;
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0usize);
+        while let Some(i) = stack.pop() {
+            let node = &self.nodes[i];
+            stats.nodes_visited += 1;
+            if node.bounds.intersect_p(ray, t_max).is_none() {
+                continue;
+            }
+            if node.count > 0 {
+                let first = node.offset as usize;
+                for &index in &self.indices[first..first + node.count as usize] {
+                    t_max = t_max.min(visit(index, stats));
+                    if t_max < 0.0 {
+                        return;
+                    }
+                }
+            } else if dir_is_neg[node.axis as usize] {
+                stack.push(i + 1);
+                stack.push(node.offset as usize);
+            } else {
+                stack.push(node.offset as usize);
+                stack.push(i + 1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::{
+        sampler::Pcg32,
+        shapes::{Plane, Sphere},
+    };
+    use glam::Vec3;
+
+    fn random_spheres(n: usize) -> Vec<Box<dyn Shape>> {
+        let mut rng = Pcg32::new(3, 0);
+        let mut next = || rng.next_f32();
+        (0..n)
+            .map(|_| {
+                let c = Vec3::new(next(), next(), next()) * 20.0 - 10.0;
+                Box::new(Sphere::new(c, 0.2 + next())) as Box<dyn Shape>
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut shapes = random_spheres(200);
+        shapes.push(Box::new(Plane::new(Vec3::new(0.0, -12.0, 0.0), Vec3::Y)));
+        let bvh = Bvh::new(&shapes);
+        assert!(bvh.bounds().is_finite());
+        let mut rng = Pcg32::new(5, 1);
+        let mut total = TraversalStats::default();
+        for _ in 0..500 {
+            let o = Vec3::new(rng.next_f32(), rng.next_f32(), rng.next_f32()) * 30.0 - 15.0;
+            let d = Vec3::new(rng.next_f32(), rng.next_f32(), rng.next_f32()) - 0.5;
+            let ray = Ray::new(o, d.normalize());
+            let expected = shapes
+                .iter()
+                .enumerate()
+                .filter_map(|(i, s)| s.intersect(&ray).map(|r| (i, r.t)))
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            let hit = bvh.intersect(&shapes, &ray, &mut total);
+            assert_eq!(hit.map(|(i, r)| (i, r.t)), expected);
+            let blocked = bvh.occluded(&shapes, &ray, 5.0, &mut total);
+            assert_eq!(blocked, matches!(expected, Some((_, t)) if t < 5.0));
+        }
+        // Far fewer tests than the 201 shapes per query of a linear search.
+        assert!(total.primitives_tested < 1000 * 40);
+    }
+
+    #[test]
+    fn empty_hierarchy() {
+        let bvh = Bvh::new(&[]);
+        let ray = Ray::new(Vec3::ZERO, Vec3::X);
+        assert!(bvh
+            .intersect(&[], &ray, &mut TraversalStats::default())
+            .is_none());
+        assert!(bvh.bounds().is_empty());
+    }
+}
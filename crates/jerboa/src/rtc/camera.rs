@@ -0,0 +1,246 @@
+//! Cameras generating primary rays.
+
+use crate::rtc::ray::Ray;
+use glam::{Vec2, Vec3};
+
+/// Direction from a point towards the camera, for light paths connecting to
+/// it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CameraSample {
+    /// Direction towards the camera, normalised.
+    pub wi: Vec3,
+
+    /// Importance arriving along `wi`.
+    pub we: f32,
+
+    /// Density of `wi` with respect to solid angle.
+    pub pdf: f32,
+
+    /// Point sampled on the camera.
+    pub p: Vec3,
+
+    /// Raster position seen along `wi`.
+    pub p_film: Vec2,
+}
+
+pub trait Camera: Send + Sync {
+    /// Width and height of the film, in pixels.
+    fn resolution(&self) -> (u32, u32);
+
+    /// Generates the ray through the continuous raster position `p_film`,
+    /// using `u_lens` to sample the aperture of cameras which have one and
+    /// `u_time` to sample the time while the shutter is open.
+    fn generate_ray(&self, p_film: Vec2, u_lens: Vec2, u_time: f32) -> Ray;
+
+    /// Times at which the shutter opens and closes, over which rays of
+    /// light paths are to be sampled too.
+    fn shutter(&self) -> (f32, f32);
+
+    /// Importance emitted along `ray`, leaving the camera, with the raster
+    /// position it goes through. The importance is normalised over the
+    /// whole film, so that splatting light paths and scaling by the inverse
+    /// of the number of samples per pixel estimates pixel values.
+    fn we(&self, ray: &Ray) -> Option<(f32, Vec2)>;
+
+    /// Densities of the origin, with respect to area, and direction, with
+    /// respect to solid angle, of a ray generated by the camera.
+    fn pdf_we(&self, ray: &Ray) -> (f32, f32);
+
+    /// Samples a point on the camera seen from `p`.
+    fn sample_wi(&self, p: Vec3, u: Vec2) -> Option<CameraSample>;
+}
+
+/// Pinhole camera with a perspective projection.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PerspectiveCamera {
+    position: Vec3,
+
+    /// Orthonormal camera frame: right, up and viewing direction.
+    right: Vec3,
+    up: Vec3,
+    forward: Vec3,
+
+    resolution: (u32, u32),
+
+    /// Half extent of the image plane at unit distance.
+    half_extent: Vec2,
+
+    /// Times at which the shutter opens and closes.
+    shutter: (f32, f32),
+}
+
+impl PerspectiveCamera {
+    /// Creates a camera at `position` looking at `target`, with the vertical
+    /// field of view `fov_y` in degrees.
+    pub fn look_at(
+        position: Vec3,
+        target: Vec3,
+        up: Vec3,
+        fov_y: f32,
+        resolution: (u32, u32),
+    ) -> Self {
+        let forward = (target - position).normalize();
+        let right = forward.cross(up).normalize();
+        let up = right.cross(forward);
+        let half_height = (fov_y.to_radians() * 0.5).tan();
+        let aspect = resolution.0 as f32 / resolution.1 as f32;
+        Self {
+            position,
+            right,
+            up,
+            forward,
+            resolution,
+            half_extent: Vec2::new(half_height * aspect, half_height),
+            shutter: (0.0, 0.0),
+        }
+    }
+
+    /// Keeps the shutter open from `open` to `close`, blurring what moves
+    /// in between. The shutter is instantaneous at time zero by default.
+    pub fn with_shutter(mut self, open: f32, close: f32) -> Self {
+        self.shutter = (open, close);
+        self
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.forward
+    }
+
+    /// Raster position of the direction `d` leaving the camera, with the
+    /// cosine of its angle to the viewing direction.
+    fn raster(&self, d: Vec3) -> Option<(Vec2, f32)> {
+        let cos_theta = d.dot(self.forward);
+        if cos_theta <= 0.0 {
+            return None;
+        }
+        let q = d / cos_theta;
+        let ndc = Vec2::new(
+            q.dot(self.right) / self.half_extent.x,
+            q.dot(self.up) / self.half_extent.y,
+        );
+        let (w, h) = (self.resolution.0 as f32, self.resolution.1 as f32);
+        let p_film = Vec2::new(0.5 * (ndc.x + 1.0) * w, 0.5 * (1.0 - ndc.y) * h);
+        if p_film.x < 0.0 || p_film.y < 0.0 || p_film.x >= w || p_film.y >= h {
+            return None;
+        }
+        Some((p_film, cos_theta))
+    }
+
+    /// Area of the image plane at unit distance.
+    fn film_area(&self) -> f32 {
+        4.0 * self.half_extent.x * self.half_extent.y
+    }
+}
+
+impl Camera for PerspectiveCamera {
+    fn resolution(&self) -> (u32, u32) {
+        self.resolution
+    }
+
+    fn generate_ray(&self, p_film: Vec2, _u_lens: Vec2, u_time: f32) -> Ray {
+        // Normalised device coordinates in [-1, 1], y pointing up.
+        let ndc = Vec2::new(
+            2.0 * p_film.x / self.resolution.0 as f32 - 1.0,
+            1.0 - 2.0 * p_film.y / self.resolution.1 as f32,
+        );
+        let d = self.forward
+            + self.right * (ndc.x * self.half_extent.x)
+            + self.up * (ndc.y * self.half_extent.y);
+        let (open, close) = self.shutter;
+        Ray::new(self.position, d.normalize()).with_time(open + (close - open) * u_time)
+    }
+
+    fn shutter(&self) -> (f32, f32) {
+        self.shutter
+    }
+
+    fn we(&self, ray: &Ray) -> Option<(f32, Vec2)> {
+        let (p_film, cos_theta) = self.raster(ray.d.normalize())?;
+        let cos2 = cos_theta * cos_theta;
+        Some((1.0 / (self.film_area() * cos2 * cos2), p_film))
+    }
+
+    fn pdf_we(&self, ray: &Ray) -> (f32, f32) {
+        match self.raster(ray.d.normalize()) {
+            // The pinhole is a point: its density is a delta, left as 1.
+            Some((_, cos_theta)) => (1.0, 1.0 / (self.film_area() * cos_theta.powi(3))),
+            None => (0.0, 0.0),
+        }
+    }
+
+    fn sample_wi(&self, p: Vec3, _u: Vec2) -> Option<CameraSample> {
+        let to_camera = self.position - p;
+        let distance_squared = to_camera.length_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let wi = to_camera / distance_squared.sqrt();
+        let (we, p_film) = self.we(&Ray::new(self.position, -wi))?;
+        Some(CameraSample {
+            wi,
+            we,
+            pdf: distance_squared / wi.dot(self.forward).abs(),
+            p: self.position,
+            p_film,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rays_span_the_field_of_view() {
+        let camera = PerspectiveCamera::look_at(Vec3::ZERO, -Vec3::Z, Vec3::Y, 90.0, (200, 100));
+        let centre = camera.generate_ray(Vec2::new(100.0, 50.0), Vec2::ZERO, 0.0);
+        assert!(centre.d.abs_diff_eq(-Vec3::Z, 1e-6));
+        let top = camera.generate_ray(Vec2::new(100.0, 0.0), Vec2::ZERO, 0.0);
+        assert!(top
+            .d
+            .abs_diff_eq(Vec3::new(0.0, 1.0, -1.0).normalize(), 1e-6));
+        let right = camera.generate_ray(Vec2::new(200.0, 50.0), Vec2::ZERO, 0.0);
+        assert!(right
+            .d
+            .abs_diff_eq(Vec3::new(2.0, 0.0, -1.0).normalize(), 1e-6));
+    }
+
+    #[test]
+    fn shutter() {
+        let camera = PerspectiveCamera::look_at(Vec3::ZERO, -Vec3::Z, Vec3::Y, 60.0, (4, 4));
+        assert_eq!(camera.generate_ray(Vec2::ONE, Vec2::ZERO, 0.7).time, 0.0);
+        let camera = camera.with_shutter(1.0, 3.0);
+        assert_eq!(camera.shutter(), (1.0, 3.0));
+        assert_eq!(camera.generate_ray(Vec2::ONE, Vec2::ZERO, 0.0).time, 1.0);
+        assert_eq!(camera.generate_ray(Vec2::ONE, Vec2::ZERO, 0.5).time, 2.0);
+    }
+
+    #[test]
+    fn importance_matches_rays() {
+        let camera = PerspectiveCamera::look_at(Vec3::ONE, Vec3::ZERO, Vec3::Y, 50.0, (64, 48));
+        for p_film in [
+            Vec2::new(3.2, 40.5),
+            Vec2::new(32.0, 24.0),
+            Vec2::new(60.1, 0.7),
+        ] {
+            let ray = camera.generate_ray(p_film, Vec2::ZERO, 0.0);
+            let (we, raster) = camera.we(&ray).unwrap();
+            assert!(raster.abs_diff_eq(p_film, 1e-3));
+            // The importance integrates to one over the film:
+            // We cos / pdf_dir is constant.
+            let (_, pdf_dir) = camera.pdf_we(&ray);
+            let cos_theta = ray.d.dot(camera.forward());
+            assert!((we * cos_theta / pdf_dir - 1.0).abs() < 1e-4);
+
+            let p = ray.o + ray.d * 3.0;
+            let sample = camera.sample_wi(p, Vec2::ZERO).unwrap();
+            assert!(sample.wi.abs_diff_eq(-ray.d, 1e-5));
+            assert!(sample.p_film.abs_diff_eq(p_film, 1e-3));
+        }
+        assert!(camera.we(&Ray::new(Vec3::ONE, Vec3::ONE)).is_none());
+    }
+}
@@ -0,0 +1,339 @@
+//! Film accumulating radiance samples into an image.
+//!
+//! Positions on the film are continuous raster coordinates: pixel `(x, y)`
+//! covers `[x, x + 1) x [y, y + 1)` and its centre is at `(x + 0.5, y +
+//! 0.5)`.
+
+use crate::{
+    core::image::PixelBufferRgb32f,
+    rtc::filters::{BoxFilter, Filter},
+};
+use glam::{Vec2, Vec3};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// `f32` supporting atomic additions, stored as its bits.
+#[derive(Debug, Default)]
+struct AtomicF32(AtomicU32);
+
+impl AtomicF32 {
+    fn load(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    fn add(&self, v: f32) {
+        let mut old = self.0.load(Ordering::Relaxed);
+        loop {
+            let new = (f32::from_bits(old) + v).to_bits();
+            match self
+                .0
+                .compare_exchange_weak(old, new, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(_) => break,
+                Err(current) => old = current,
+            }
+        }
+    }
+}
+
+/// Sums of a pixel, in double precision so that they don't drift over
+/// millions of samples.
+#[derive(Debug, Default, Copy, Clone)]
+struct FilmPixel {
+    /// Sum of the filter weighted radiance samples, per channel.
+    sum: [f64; 3],
+
+    /// Sum of the filter weights.
+    weight: f64,
+}
+
+impl FilmPixel {
+    fn add(&mut self, l: Vec3, w: f32) {
+        for (sum, l) in self.sum.iter_mut().zip(l.to_array()) {
+            *sum += (l * w) as f64;
+        }
+        self.weight += w as f64;
+    }
+
+    fn merge(&mut self, other: &FilmPixel) {
+        for (sum, other) in self.sum.iter_mut().zip(other.sum) {
+            *sum += other;
+        }
+        self.weight += other.weight;
+    }
+}
+
+/// Contributions of the samples of a block of pixels, recorded without
+/// synchronisation and then merged into the film with [`Film::merge_tile`].
+#[derive(Debug, Clone)]
+pub struct FilmTile {
+    /// First pixel covered by the tile, inclusive.
+    min: (u32, u32),
+
+    /// Last pixel covered by the tile, exclusive.
+    max: (u32, u32),
+
+    filter_radius: Vec2,
+    pixels: Vec<FilmPixel>,
+}
+
+impl FilmTile {
+    /// Records a radiance sample at `p_film`, weighted by the filter and by
+    /// `weight`. Samples only affect the pixels of the tile.
+    pub fn add_sample<F: Filter + ?Sized>(
+        &mut self,
+        filter: &F,
+        p_film: Vec2,
+        l: Vec3,
+        weight: f32,
+    ) {
+        let footprint = (self.min, self.max, self.filter_radius);
+        add_filtered_sample(&mut self.pixels, footprint, filter, p_film, l, weight);
+    }
+}
+
+/// Adds a radiance sample at `p_film` to the pixels whose centre lies within
+/// the filter support, among the pixels `[min, max)` stored row by row in
+/// `pixels`.
+fn add_filtered_sample<F: Filter + ?Sized>(
+    pixels: &mut [FilmPixel],
+    (min, max, radius): ((u32, u32), (u32, u32), Vec2),
+    filter: &F,
+    p_film: Vec2,
+    l: Vec3,
+    weight: f32,
+) {
+    let p = p_film - Vec2::splat(0.5);
+    let lo = (p - radius).ceil();
+    let hi = (p + radius).floor();
+    let x0 = (lo.x.max(0.0) as u32).max(min.0);
+    let y0 = (lo.y.max(0.0) as u32).max(min.1);
+    let x1 = ((hi.x + 1.0).max(0.0) as u32).min(max.0);
+    let y1 = ((hi.y + 1.0).max(0.0) as u32).min(max.1);
+    let width = max.0 - min.0;
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let w = filter.eval(Vec2::new(x as f32, y as f32) - p) * weight;
+            if w == 0.0 {
+                continue;
+            }
+            let i = ((y - min.1) * width + (x - min.0)) as usize;
+            pixels[i].add(l, w);
+        }
+    }
+}
+
+/// Image plane accumulating filtered radiance samples.
+///
+/// Camera samples are filtered into weighted sums, normalised when the film
+/// is resolved. Splats from light tracing strategies, whose position on the
+/// film is not chosen by the sampler, are summed separately without
+/// normalisation and scaled when resolving.
+pub struct Film {
+    width: u32,
+    height: u32,
+    filter: Box<dyn Filter>,
+    pixels: Vec<FilmPixel>,
+    splats: Vec<[AtomicF32; 3]>,
+}
+
+impl Film {
+    /// Creates a film using a box filter covering a single pixel.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            filter: Box::new(BoxFilter::default()),
+            pixels: vec![FilmPixel::default(); (width * height) as usize],
+            splats: (0..width * height).map(|_| Default::default()).collect(),
+        }
+    }
+
+    /// Sets the reconstruction filter.
+    pub fn with_filter(mut self, filter: impl Filter + 'static) -> Self {
+        self.filter = Box::new(filter);
+        self
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn filter(&self) -> &dyn Filter {
+        self.filter.as_ref()
+    }
+
+    /// Creates an empty tile receiving the samples taken in the pixels
+    /// `[x0, x1) x [y0, y1)`. The tile extends beyond these pixels by the
+    /// filter radius, so that tiles of neighbouring pixels overlap.
+    pub fn tile(&self, (x0, y0): (u32, u32), (x1, y1): (u32, u32)) -> FilmTile {
+        let r = self.filter.radius();
+        let (rx, ry) = (r.x.ceil() as u32, r.y.ceil() as u32);
+        let min = (x0.saturating_sub(rx), y0.saturating_sub(ry));
+        let max = (
+            (x1 + rx).min(self.width).max(min.0),
+            (y1 + ry).min(self.height).max(min.1),
+        );
+        let n = ((max.0 - min.0) * (max.1 - min.1)) as usize;
+        FilmTile {
+            min,
+            max,
+            filter_radius: r,
+            pixels: vec![FilmPixel::default(); n],
+        }
+    }
+
+    /// Adds the contributions recorded in a tile to the film.
+    pub fn merge_tile(&mut self, tile: FilmTile) {
+        let tile_width = (tile.max.0 - tile.min.0) as usize;
+        for y in tile.min.1..tile.max.1 {
+            let src = (y - tile.min.1) as usize * tile_width;
+            let dst = (y * self.width + tile.min.0) as usize;
+            for (d, s) in self.pixels[dst..dst + tile_width]
+                .iter_mut()
+                .zip(&tile.pixels[src..src + tile_width])
+            {
+                d.merge(s);
+            }
+        }
+    }
+
+    /// Records a radiance sample at `p_film`, weighted by the filter and by
+    /// `weight`.
+    pub fn add_sample(&mut self, p_film: Vec2, l: Vec3, weight: f32) {
+        let footprint = ((0, 0), (self.width, self.height), self.filter.radius());
+        add_filtered_sample(
+            &mut self.pixels,
+            footprint,
+            self.filter.as_ref(),
+            p_film,
+            l,
+            weight,
+        );
+    }
+
+    /// Adds `v` to the pixel containing `p_film`, ignoring the filter. May be
+    /// called concurrently.
+    pub fn add_splat(&self, p_film: Vec2, v: Vec3) {
+        if !v.is_finite() || p_film.x < 0.0 || p_film.y < 0.0 {
+            return;
+        }
+        let (x, y) = (p_film.x as u32, p_film.y as u32);
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let splat = &self.splats[(y * self.width + x) as usize];
+        splat.iter().zip(v.to_array()).for_each(|(s, v)| s.add(v));
+    }
+
+    /// Produces the image, adding the splats scaled by `splat_scale`
+    /// (typically the inverse of the number of samples per pixel).
+    pub fn resolve(&self, splat_scale: f32) -> PixelBufferRgb32f {
+        let mut image = PixelBufferRgb32f::new(self.width, self.height);
+        for (((_, pixel), p), s) in image.pixels_mut().zip(&self.pixels).zip(&self.splats) {
+            let mut rgb = if p.weight != 0.0 {
+                Vec3::from(p.sum.map(|s| (s / p.weight) as f32))
+            } else {
+                Vec3::ZERO
+            };
+            rgb += Vec3::new(s[0].load(), s[1].load(), s[2].load()) * splat_scale;
+            *pixel = crate::core::Vec3::from(rgb.to_array());
+        }
+        image
+    }
+
+    /// Discards all samples and splats.
+    pub fn clear(&mut self) {
+        self.pixels.fill(FilmPixel::default());
+        self.splats = (0..self.width * self.height)
+            .map(|_| Default::default())
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::filters::GaussianFilter;
+
+    #[test]
+    fn box_filter_averages_samples() {
+        let mut film = Film::new(4, 2);
+        film.add_sample(Vec2::new(1.2, 0.3), Vec3::ONE, 1.0);
+        film.add_sample(Vec2::new(1.8, 0.9), Vec3::splat(3.0), 1.0);
+        film.add_sample(Vec2::new(3.5, 1.5), Vec3::X, 2.0);
+        let image = film.resolve(1.0);
+        assert_eq!(image.pixel_at(1, 0).unwrap()[0], 2.0);
+        assert_eq!(image.pixel_at(3, 1).unwrap()[0], 1.0);
+        assert_eq!(image.pixel_at(0, 0).unwrap()[0], 0.0);
+    }
+
+    #[test]
+    fn wide_filters_spread_samples() {
+        let mut film = Film::new(5, 5).with_filter(GaussianFilter::default());
+        film.add_sample(Vec2::new(2.5, 2.5), Vec3::ONE, 1.0);
+        let image = film.resolve(1.0);
+        // Normalisation gives every pixel reached by the sample its value.
+        assert_eq!(image.pixel_at(1, 2).unwrap()[1], 1.0);
+        assert_eq!(image.pixel_at(0, 0).unwrap()[1], 0.0);
+    }
+
+    #[test]
+    fn tiles_match_direct_samples() {
+        let samples = [
+            (Vec2::new(0.2, 0.7), Vec3::new(1.0, 2.0, 3.0)),
+            (Vec2::new(3.9, 2.1), Vec3::splat(0.5)),
+            (Vec2::new(4.1, 2.2), Vec3::splat(2.0)),
+        ];
+        let mut direct = Film::new(8, 4).with_filter(GaussianFilter::default());
+        let mut tiled = Film::new(8, 4).with_filter(GaussianFilter::default());
+        let mut left = tiled.tile((0, 0), (4, 4));
+        let mut right = tiled.tile((4, 0), (8, 4));
+        for (p, l) in samples {
+            direct.add_sample(p, l, 1.0);
+            let tile = if p.x < 4.0 { &mut left } else { &mut right };
+            tile.add_sample(tiled.filter(), p, l, 1.0);
+        }
+        tiled.merge_tile(left);
+        tiled.merge_tile(right);
+        let (a, b) = (direct.resolve(1.0), tiled.resolve(1.0));
+        for ((_, a), (_, b)) in a.pixels().zip(b.pixels()) {
+            for c in 0..3 {
+                assert!((a[c] - b[c]).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn sums_do_not_drift() {
+        let mut film = Film::new(1, 1);
+        let mut tile = film.tile((0, 0), (1, 1));
+        for _ in 0..2_000_000 {
+            tile.add_sample(film.filter(), Vec2::splat(0.5), Vec3::splat(0.1), 1.0);
+        }
+        film.merge_tile(tile);
+        let v = film.resolve(1.0).pixel_at(0, 0).unwrap()[0];
+        assert!((v - 0.1).abs() < 1e-7, "{v}");
+    }
+
+    #[test]
+    fn splats_are_scaled() {
+        let film = Film::new(2, 2);
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| film.add_splat(Vec2::new(1.5, 0.5), Vec3::ONE));
+            }
+        });
+        film.add_splat(Vec2::new(-1.0, 0.5), Vec3::ONE);
+        let image = film.resolve(0.5);
+        assert_eq!(image.pixel_at(1, 0).unwrap()[2], 2.0);
+        assert_eq!(image.pixel_at(0, 0).unwrap()[2], 0.0);
+    }
+}
@@ -0,0 +1,227 @@
+//! Pixel reconstruction filters.
+//!
+//! Filters are evaluated at offsets from a pixel centre, in pixels, and are
+//! zero outside of `[-radius.x, radius.x] x [-radius.y, radius.y]`. They need
+//! not be normalised, the film divides by the sum of the weights.
+
+use glam::Vec2;
+use std::f32::consts::TAU;
+
+pub trait Filter: Send + Sync {
+    /// Half extent of the support of the filter.
+    fn radius(&self) -> Vec2;
+
+    /// Weight of a sample at offset `p` from a pixel centre.
+    fn eval(&self, p: Vec2) -> f32;
+}
+
+/// Constant weight over the support.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoxFilter {
+    pub radius: Vec2,
+}
+
+impl Default for BoxFilter {
+    fn default() -> Self {
+        Self {
+            radius: Vec2::splat(0.5),
+        }
+    }
+}
+
+impl Filter for BoxFilter {
+    fn radius(&self) -> Vec2 {
+        self.radius
+    }
+
+    fn eval(&self, p: Vec2) -> f32 {
+        if p.abs().cmple(self.radius).all() {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Separable tent, decreasing linearly to zero at the radius.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TentFilter {
+    pub radius: Vec2,
+}
+
+impl Default for TentFilter {
+    fn default() -> Self {
+        Self {
+            radius: Vec2::splat(1.0),
+        }
+    }
+}
+
+impl Filter for TentFilter {
+    fn radius(&self) -> Vec2 {
+        self.radius
+    }
+
+    fn eval(&self, p: Vec2) -> f32 {
+        let w = (self.radius - p.abs()).max(Vec2::ZERO);
+        w.x * w.y
+    }
+}
+
+/// Separable Gaussian, shifted down so that it reaches zero at the radius.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GaussianFilter {
+    pub radius: Vec2,
+    pub sigma: f32,
+}
+
+impl Default for GaussianFilter {
+    fn default() -> Self {
+        Self {
+            radius: Vec2::splat(1.5),
+            sigma: 0.5,
+        }
+    }
+}
+
+impl GaussianFilter {
+    fn gaussian(&self, x: f32, radius: f32) -> f32 {
+        let g = |x: f32| (-x * x / (2.0 * self.sigma * self.sigma)).exp();
+        (g(x) - g(radius)).max(0.0)
+    }
+}
+
+impl Filter for GaussianFilter {
+    fn radius(&self) -> Vec2 {
+        self.radius
+    }
+
+    fn eval(&self, p: Vec2) -> f32 {
+        self.gaussian(p.x, self.radius.x) * self.gaussian(p.y, self.radius.y)
+    }
+}
+
+/// Separable Mitchell-Netravali cubic with parameters `b` and `c`; the
+/// recommended `b = c = 1/3` trades off ringing and blurring.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MitchellFilter {
+    pub radius: Vec2,
+    pub b: f32,
+    pub c: f32,
+}
+
+impl Default for MitchellFilter {
+    fn default() -> Self {
+        Self {
+            radius: Vec2::splat(2.0),
+            b: 1.0 / 3.0,
+            c: 1.0 / 3.0,
+        }
+    }
+}
+
+impl MitchellFilter {
+    /// Cubic over `[-2, 2]`.
+    fn mitchell_1d(&self, x: f32) -> f32 {
+        let (b, c) = (self.b, self.c);
+        let x = x.abs();
+        let v = if x <= 1.0 {
+            (12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                + (6.0 - 2.0 * b)
+        } else if x <= 2.0 {
+            (-b - 6.0 * c) * x * x * x
+                + (6.0 * b + 30.0 * c) * x * x
+                + (-12.0 * b - 48.0 * c) * x
+                + (8.0 * b + 24.0 * c)
+        } else {
+            0.0
+        };
+        v / 6.0
+    }
+}
+
+impl Filter for MitchellFilter {
+    fn radius(&self) -> Vec2 {
+        self.radius
+    }
+
+    fn eval(&self, p: Vec2) -> f32 {
+        let q = 2.0 * p / self.radius;
+        self.mitchell_1d(q.x) * self.mitchell_1d(q.y)
+    }
+}
+
+/// Separable four-term Blackman-Harris window, close to a Gaussian but
+/// smoothly reaching zero at the radius.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BlackmanHarrisFilter {
+    pub radius: Vec2,
+}
+
+impl Default for BlackmanHarrisFilter {
+    fn default() -> Self {
+        Self {
+            radius: Vec2::splat(1.5),
+        }
+    }
+}
+
+impl BlackmanHarrisFilter {
+    fn window(x: f32, radius: f32) -> f32 {
+        if x.abs() > radius {
+            return 0.0;
+        }
+        // Position in the window, with the centre at 0.5. Only the upper
+        // half is evaluated to keep the window exactly symmetric.
+        let t = 0.5 * (x.abs() / radius + 1.0);
+        0.35875 - 0.48829 * (TAU * t).cos() + 0.14128 * (2.0 * TAU * t).cos()
+            - 0.01168 * (3.0 * TAU * t).cos()
+    }
+}
+
+impl Filter for BlackmanHarrisFilter {
+    fn radius(&self) -> Vec2 {
+        self.radius
+    }
+
+    fn eval(&self, p: Vec2) -> f32 {
+        Self::window(p.x, self.radius.x) * Self::window(p.y, self.radius.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filters() -> Vec<Box<dyn Filter>> {
+        vec![
+            Box::new(BoxFilter::default()),
+            Box::new(TentFilter::default()),
+            Box::new(GaussianFilter::default()),
+            Box::new(MitchellFilter::default()),
+            Box::new(BlackmanHarrisFilter::default()),
+        ]
+    }
+
+    #[test]
+    fn filters_peak_at_centre_and_vanish_outside() {
+        for filter in filters() {
+            let r = filter.radius();
+            let centre = filter.eval(Vec2::ZERO);
+            assert!(centre > 0.0);
+            assert!(filter.eval(Vec2::new(0.3 * r.x, 0.2 * r.y)) <= centre);
+            assert!(filter.eval(Vec2::new(r.x * 1.01, 0.0)).abs() < 1e-6);
+            assert!(filter.eval(Vec2::new(0.0, -r.y * 1.01)).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn filters_are_symmetric() {
+        let p = Vec2::new(0.4, 0.25);
+        for filter in filters() {
+            assert_eq!(filter.eval(p), filter.eval(-p));
+            assert_eq!(filter.eval(p), filter.eval(Vec2::new(-p.x, p.y)));
+        }
+    }
+}
@@ -0,0 +1,767 @@
+//! Bidirectional path tracing (Veach 1997, chapter 10).
+//!
+//! For every camera ray, a camera subpath and a light subpath are traced and
+//! all pairs of their vertices are connected. Each connection strategy `(s,
+//! t)`, with `s` light and `t` camera vertices, is weighted with the power
+//! heuristic against all the other strategies able to produce the same path.
+//! Strategies with a single camera vertex (light tracing) reach arbitrary
+//! pixels and are splatted to a film owned by the integrator.
+
+use crate::{
+    core::image::PixelBufferRgb32f,
+    rtc::{
+        aabb::Aabb,
+        aov::AovSample,
+        bsdfs::{Bsdf, TransportMode},
+        camera::Camera,
+        film::Film,
+        integrators::{record_hit, spawn_ray, Integrator, SHADOW_EPSILON},
+        ray::{offset_ray_origin, Ray},
+        sampler::Sampler,
+        scene::Scene,
+    },
+};
+use glam::Vec3;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum VertexKind {
+    Camera,
+
+    /// Point sampled on the light of the given index.
+    Light(usize),
+
+    /// Camera path escaping the scene towards the lights at infinity.
+    InfiniteLight,
+
+    Surface,
+}
+
+#[derive(Copy, Clone)]
+struct Vertex<'a> {
+    kind: VertexKind,
+    p: Vec3,
+
+    /// Bound on the error of `p`, for surface points.
+    p_error: Vec3,
+
+    /// Surface normal; zero for points, e.g. the pinhole or point lights.
+    n: Vec3,
+
+    /// Direction towards the previous vertex of the subpath.
+    wo: Vec3,
+
+    bsdf: Option<&'a dyn Bsdf>,
+
+    /// Throughput of the subpath up to this vertex, divided by its density.
+    beta: Vec3,
+
+    /// Whether the vertex scatters specularly, so that it cannot be
+    /// connected.
+    delta: bool,
+
+    /// Density with respect to area of sampling this vertex along its
+    /// subpath, and along the reverse direction.
+    pdf_fwd: f32,
+    pdf_rev: f32,
+}
+
+impl<'a> Vertex<'a> {
+    fn new(kind: VertexKind, p: Vec3, n: Vec3, beta: Vec3) -> Self {
+        Self {
+            kind,
+            p,
+            p_error: Vec3::ZERO,
+            n,
+            wo: Vec3::ZERO,
+            bsdf: None,
+            beta,
+            delta: false,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+        }
+    }
+
+    fn is_on_surface(&self) -> bool {
+        self.n != Vec3::ZERO
+    }
+
+    fn is_light(&self) -> bool {
+        matches!(self.kind, VertexKind::Light(_) | VertexKind::InfiniteLight)
+    }
+
+    fn is_infinite_light(&self, scene: &Scene) -> bool {
+        match self.kind {
+            VertexKind::InfiniteLight => true,
+            VertexKind::Light(i) => scene.lights()[i].is_infinite(),
+            _ => false,
+        }
+    }
+
+    fn is_delta_light(&self, scene: &Scene) -> bool {
+        matches!(self.kind, VertexKind::Light(i) if scene.lights()[i].is_delta())
+    }
+
+    fn is_connectible(&self) -> bool {
+        match self.kind {
+            VertexKind::Surface => self.bsdf.is_some_and(|bsdf| !bsdf.is_specular()),
+            _ => true,
+        }
+    }
+
+    /// BSDF of a surface vertex for the direction towards `next`.
+    fn f(&self, next: &Vertex) -> Vec3 {
+        match self.bsdf {
+            Some(bsdf) => bsdf.f(self.wo, (next.p - self.p).normalize(), self.n),
+            None => Vec3::ZERO,
+        }
+    }
+}
+
+/// Shared state of the functions of a single path sample.
+struct Context<'a, C: Camera> {
+    scene: &'a Scene,
+    camera: &'a C,
+    scene_bounds: Aabb,
+    scene_radius: f32,
+
+    /// Time of the camera ray, shared by all the rays of both subpaths.
+    time: f32,
+}
+
+impl<'a, C: Camera> Context<'a, C> {
+    /// Probability of choosing each light.
+    fn light_choice_pdf(&self) -> f32 {
+        1.0 / self.scene.lights().len() as f32
+    }
+
+    /// Converts a density with respect to solid angle at `from` into a
+    /// density with respect to area at `to`.
+    fn convert_density(&self, pdf: f32, from: &Vertex, to: &Vertex) -> f32 {
+        if to.is_infinite_light(self.scene) {
+            return pdf;
+        }
+        let w = to.p - from.p;
+        let distance_squared = w.length_squared();
+        if distance_squared == 0.0 {
+            return 0.0;
+        }
+        let inv_distance_squared = 1.0 / distance_squared;
+        let mut pdf = pdf * inv_distance_squared;
+        if to.is_on_surface() {
+            pdf *= to.n.dot(w * inv_distance_squared.sqrt()).abs();
+        }
+        pdf
+    }
+
+    /// Density with respect to area of sampling `next` from `v`, reached
+    /// from `prev`.
+    fn pdf(&self, v: &Vertex, prev: Option<&Vertex>, next: &Vertex) -> f32 {
+        if v.is_light() {
+            return self.pdf_light(v, next);
+        }
+        let wn = next.p - v.p;
+        if wn.length_squared() == 0.0 {
+            return 0.0;
+        }
+        let wn = wn.normalize();
+        let pdf = match v.kind {
+            VertexKind::Camera => self.camera.pdf_we(&Ray::new(v.p, wn)).1,
+            _ => match (v.bsdf, prev) {
+                (Some(bsdf), Some(prev)) => bsdf.pdf((prev.p - v.p).normalize(), wn, v.n),
+                _ => 0.0,
+            },
+        };
+        self.convert_density(pdf, v, next)
+    }
+
+    /// Density with respect to area of the light vertex `v` emitting
+    /// towards `next`.
+    fn pdf_light(&self, v: &Vertex, next: &Vertex) -> f32 {
+        let w = next.p - v.p;
+        let distance_squared = w.length_squared();
+        if distance_squared == 0.0 {
+            return 0.0;
+        }
+        let w = w / distance_squared.sqrt();
+        let mut pdf = if v.is_infinite_light(self.scene) {
+            // Density of the origin on the disk facing the scene.
+            1.0 / (std::f32::consts::PI * self.scene_radius * self.scene_radius)
+        } else {
+            let light = match v.kind {
+                VertexKind::Light(i) => &self.scene.lights()[i],
+                _ => return 0.0,
+            };
+            light.pdf_le(&Ray::new(v.p, w), &self.scene_bounds).1 / distance_squared
+        };
+        if next.is_on_surface() {
+            pdf *= next.n.dot(w).abs();
+        }
+        pdf
+    }
+
+    /// Density of sampling the light vertex `v` as the origin of a light
+    /// subpath, seen from `next`.
+    fn pdf_light_origin(&self, v: &Vertex, next: &Vertex) -> f32 {
+        let w = (next.p - v.p).normalize();
+        let lights = self.scene.lights();
+        if v.is_infinite_light(self.scene) {
+            // Density of the direction from all the lights at infinity.
+            return lights
+                .iter()
+                .filter(|light| light.is_infinite())
+                .map(|light| light.pdf_li(next.p, -w))
+                .sum::<f32>()
+                * self.light_choice_pdf();
+        }
+        match v.kind {
+            VertexKind::Light(i) => {
+                lights[i].pdf_le(&Ray::new(v.p, w), &self.scene_bounds).0 * self.light_choice_pdf()
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Radiance emitted by the light vertex `v` towards `next`.
+    fn le(&self, v: &Vertex, next: &Vertex) -> Vec3 {
+        match v.kind {
+            VertexKind::InfiniteLight => {
+                let ray = Ray::new(next.p, (v.p - next.p).normalize());
+                self.scene
+                    .lights()
+                    .iter()
+                    .filter(|light| light.is_infinite())
+                    .fold(Vec3::ZERO, |l, light| l + light.le(&ray))
+            }
+            _ => Vec3::ZERO,
+        }
+    }
+
+    /// Geometric term between two vertices, including their visibility.
+    fn g(&self, a: &Vertex, b: &Vertex) -> f32 {
+        let d = b.p - a.p;
+        let distance_squared = d.length_squared();
+        if distance_squared == 0.0 {
+            return 0.0;
+        }
+        let distance = distance_squared.sqrt();
+        let w = d / distance;
+        let mut g = 1.0 / distance_squared;
+        if a.is_on_surface() {
+            g *= a.n.dot(w).abs();
+        }
+        if b.is_on_surface() {
+            g *= b.n.dot(w).abs();
+        }
+        if g == 0.0 || !self.unoccluded(a, w, distance) {
+            return 0.0;
+        }
+        g
+    }
+
+    /// Whether the segment leaving the vertex `a` in the unit direction `w`
+    /// is free of occluders up to `distance`, which is infinite for infinite
+    /// lights.
+    fn unoccluded(&self, a: &Vertex, w: Vec3, distance: f32) -> bool {
+        let o = if a.is_on_surface() {
+            offset_ray_origin(a.p, a.p_error, a.n, w)
+        } else {
+            a.p
+        };
+        let ray = Ray::new(o, w).with_time(self.time);
+        !self.scene.occluded(&ray, distance * (1.0 - SHADOW_EPSILON))
+    }
+
+    /// Extends `path` along `ray` by sampling the BSDFs, until `max_depth`
+    /// vertices are added or the path leaves the scene.
+    #[allow(clippy::too_many_arguments)]
+    fn random_walk(
+        &self,
+        path: &mut Vec<Vertex<'a>>,
+        mut ray: Ray,
+        sampler: &mut dyn Sampler,
+        mut beta: Vec3,
+        pdf: f32,
+        max_depth: u32,
+        mode: TransportMode,
+        aov: Option<&mut AovSample>,
+    ) {
+        let mut aov = aov;
+        let mut pdf_fwd = pdf;
+        for _ in 0..max_depth {
+            let prev = path.len() - 1;
+            let (primitive, record) = match self.scene.intersect(&ray) {
+                Some(hit) => hit,
+                None => {
+                    if mode == TransportMode::Radiance {
+                        let mut v =
+                            Vertex::new(VertexKind::InfiniteLight, ray.o + ray.d, Vec3::ZERO, beta);
+                        v.pdf_fwd = pdf_fwd;
+                        path.push(v);
+                    }
+                    return;
+                }
+            };
+            if let Some(aov) = aov.take() {
+                record_hit(aov, self.scene, primitive, &record);
+            }
+            let mut v = Vertex::new(VertexKind::Surface, record.p, record.n, beta);
+            v.p_error = record.p_error;
+            v.wo = -ray.d;
+            v.bsdf = self.scene.material(primitive);
+            v.pdf_fwd = self.convert_density(pdf_fwd, &path[prev], &v);
+            path.push(v);
+            let current = path.len() - 1;
+
+            let bsdf = match v.bsdf {
+                Some(bsdf) => bsdf,
+                None => return,
+            };
+            let sample = match bsdf.sample_f(v.wo, v.n, sampler.get_2d(), mode) {
+                Some(sample) if sample.pdf > 0.0 && sample.f != Vec3::ZERO => sample,
+                _ => return,
+            };
+            beta *= sample.f * sample.wi.dot(v.n).abs() / sample.pdf;
+            pdf_fwd = sample.pdf;
+            let mut pdf_rev = bsdf.pdf(sample.wi, v.wo, v.n);
+            if sample.specular {
+                path[current].delta = true;
+                pdf_fwd = 0.0;
+                pdf_rev = 0.0;
+            }
+            path[prev].pdf_rev = self.convert_density(pdf_rev, &path[current], &path[prev]);
+            ray = spawn_ray(&record, sample.wi);
+        }
+    }
+
+    /// Multiple importance sampling weight of the strategy `(s, t)`, whose
+    /// connection vertex on the light or camera side may have been sampled
+    /// anew as `sampled`.
+    fn mis_weight(
+        &self,
+        light_path: &[Vertex],
+        camera_path: &[Vertex],
+        sampled: Option<Vertex>,
+        s: usize,
+        t: usize,
+    ) -> f32 {
+        if s + t == 2 {
+            return 1.0;
+        }
+        let mut light_path = light_path[..s].to_vec();
+        let mut camera_path = camera_path[..t].to_vec();
+        if let Some(sampled) = sampled {
+            if s == 1 {
+                light_path[0] = sampled;
+            } else if t == 1 {
+                camera_path[0] = sampled;
+            }
+        }
+
+        // Densities of the vertices around the connection, in the reverse
+        // direction, as if the path had been sampled by another strategy.
+        camera_path[t - 1].delta = false;
+        if s > 0 {
+            light_path[s - 1].delta = false;
+        }
+        let pt = camera_path[t - 1];
+        let pt_minus = (t > 1).then(|| camera_path[t - 2]);
+        let qs = (s > 0).then(|| light_path[s - 1]);
+        let qs_minus = (s > 1).then(|| light_path[s - 2]);
+        camera_path[t - 1].pdf_rev = match &qs {
+            Some(qs) => self.pdf(qs, qs_minus.as_ref(), &pt),
+            None => pt_minus.map_or(0.0, |pm| self.pdf_light_origin(&pt, &pm)),
+        };
+        if let Some(pt_minus) = pt_minus {
+            camera_path[t - 2].pdf_rev = match &qs {
+                Some(qs) => self.pdf(&pt, Some(qs), &pt_minus),
+                None => self.pdf_light(&pt, &pt_minus),
+            };
+        }
+        if let Some(qs) = qs {
+            light_path[s - 1].pdf_rev = self.pdf(&pt, pt_minus.as_ref(), &qs);
+            if let Some(qs_minus) = qs_minus {
+                light_path[s - 2].pdf_rev = self.pdf(&qs, Some(&pt), &qs_minus);
+            }
+        }
+
+        // Ratios of the densities of the other strategies to this one.
+        let remap = |pdf: f32| if pdf != 0.0 { pdf } else { 1.0 };
+        let mut sum_ri = 0.0;
+        let mut ri = 1.0;
+        for i in (1..t).rev() {
+            ri *= remap(camera_path[i].pdf_rev) / remap(camera_path[i].pdf_fwd);
+            if !camera_path[i].delta && !camera_path[i - 1].delta {
+                sum_ri += ri * ri;
+            }
+        }
+        let mut ri = 1.0;
+        for i in (0..s).rev() {
+            ri *= remap(light_path[i].pdf_rev) / remap(light_path[i].pdf_fwd);
+            let delta_light_vertex = if i > 0 {
+                light_path[i - 1].delta
+            } else {
+                light_path[0].is_delta_light(self.scene)
+            };
+            if !light_path[i].delta && !delta_light_vertex {
+                sum_ri += ri * ri;
+            }
+        }
+        1.0 / (1.0 + sum_ri)
+    }
+}
+
+/// Bidirectional path tracer.
+///
+/// Camera rays must be generated by the camera given to the integrator. The
+/// light tracing contributions are accumulated separately, see
+/// [`BdptIntegrator::light_image`].
+pub struct BdptIntegrator<C: Camera> {
+    camera: C,
+
+    /// Maximum number of bounces of the paths.
+    max_depth: u32,
+
+    light_film: Film,
+
+    /// Number of light subpaths traced, to normalise the splats.
+    n_light_paths: AtomicU64,
+}
+
+impl<C: Camera> BdptIntegrator<C> {
+    pub fn new(camera: C, max_depth: u32) -> Self {
+        let (width, height) = camera.resolution();
+        Self {
+            camera,
+            max_depth,
+            light_film: Film::new(width, height),
+            n_light_paths: AtomicU64::new(0),
+        }
+    }
+
+    pub fn camera(&self) -> &C {
+        &self.camera
+    }
+
+    /// Image of the light tracing contributions, normalised by the average
+    /// number of light paths traced per pixel. It completes the image
+    /// estimated from the radiance returned by the integrator.
+    pub fn light_image(&self) -> PixelBufferRgb32f {
+        let (width, height) = self.camera.resolution();
+        let n_paths = self.n_light_paths.load(Ordering::Relaxed);
+        let scale = if n_paths == 0 {
+            0.0
+        } else {
+            (width * height) as f32 / n_paths as f32
+        };
+        self.light_film.resolve(scale)
+    }
+
+    /// Adds the light tracing contributions to `image`, the mean radiance
+    /// estimated per pixel.
+    pub fn combine(&self, image: &PixelBufferRgb32f) -> PixelBufferRgb32f {
+        let light_image = self.light_image();
+        let mut combined = image.clone();
+        for ((_, pixel), (_, light)) in combined.pixels_mut().zip(light_image.pixels()) {
+            for c in 0..3 {
+                pixel[c] += light[c];
+            }
+        }
+        combined
+    }
+
+    /// Discards the light tracing contributions.
+    pub fn clear(&mut self) {
+        self.light_film.clear();
+        *self.n_light_paths.get_mut() = 0;
+    }
+
+    fn light_subpath<'a>(
+        &self,
+        ctx: &Context<'a, C>,
+        sampler: &mut dyn Sampler,
+    ) -> Vec<Vertex<'a>> {
+        let lights = ctx.scene.lights();
+        let mut path = Vec::with_capacity(self.max_depth as usize + 1);
+        if lights.is_empty() {
+            return path;
+        }
+        let choice = sampler.get_1d();
+        let index = ((choice * lights.len() as f32) as usize).min(lights.len() - 1);
+        let light = &lights[index];
+        let choice_pdf = ctx.light_choice_pdf();
+        let (u_pos, u_dir) = (sampler.get_2d(), sampler.get_2d());
+        let emission = match light.sample_le(u_pos, u_dir, &ctx.scene_bounds) {
+            Some(e) if e.pdf_pos > 0.0 && e.pdf_dir > 0.0 && e.le != Vec3::ZERO => e,
+            _ => return path,
+        };
+        let mut v = Vertex::new(
+            VertexKind::Light(index),
+            emission.ray.o,
+            emission.n,
+            emission.le / (emission.pdf_pos * choice_pdf),
+        );
+        v.pdf_fwd = emission.pdf_pos * choice_pdf;
+        path.push(v);
+        let cos = if emission.n == Vec3::ZERO {
+            1.0
+        } else {
+            emission.n.dot(emission.ray.d).abs()
+        };
+        let beta = emission.le * cos / (choice_pdf * emission.pdf_pos * emission.pdf_dir);
+        ctx.random_walk(
+            &mut path,
+            emission.ray.with_time(ctx.time),
+            sampler,
+            beta,
+            emission.pdf_dir,
+            self.max_depth,
+            TransportMode::Importance,
+            None,
+        );
+        if light.is_infinite() && path.len() > 1 {
+            // The first hit is sampled through the disk of the light, not
+            // from a direction at its origin.
+            path[1].pdf_fwd = emission.pdf_pos;
+            if path[1].is_on_surface() {
+                path[1].pdf_fwd *= emission.ray.d.dot(path[1].n).abs();
+            }
+        }
+        path
+    }
+
+    /// Contribution of the strategy `(s, t)`, splatting it if `t` is 1.
+    fn connect(
+        &self,
+        ctx: &Context<C>,
+        light_path: &[Vertex],
+        camera_path: &[Vertex],
+        s: usize,
+        t: usize,
+        sampler: &mut dyn Sampler,
+    ) -> Vec3 {
+        let scene = ctx.scene;
+        if t > 1 && s != 0 && camera_path[t - 1].kind == VertexKind::InfiniteLight {
+            return Vec3::ZERO;
+        }
+        let mut sampled = None;
+        let l = if s == 0 {
+            let pt = &camera_path[t - 1];
+            if pt.is_light() {
+                ctx.le(pt, &camera_path[t - 2]) * pt.beta
+            } else {
+                Vec3::ZERO
+            }
+        } else if t == 1 {
+            let qs = &light_path[s - 1];
+            if !qs.is_connectible() {
+                return Vec3::ZERO;
+            }
+            let sample = match self.camera.sample_wi(qs.p, sampler.get_2d()) {
+                Some(sample) if sample.pdf > 0.0 && sample.we > 0.0 => sample,
+                _ => return Vec3::ZERO,
+            };
+            let v = Vertex::new(
+                VertexKind::Camera,
+                sample.p,
+                Vec3::ZERO,
+                Vec3::splat(sample.we / sample.pdf),
+            );
+            let mut l = qs.beta * v.beta;
+            if qs.kind == VertexKind::Surface {
+                l *= qs.f(&v) * qs.n.dot(sample.wi).abs();
+            }
+            let distance = (sample.p - qs.p).length();
+            if l == Vec3::ZERO || !ctx.unoccluded(qs, sample.wi, distance) {
+                return Vec3::ZERO;
+            }
+            sampled = Some(v);
+            let weight = ctx.mis_weight(light_path, camera_path, sampled, s, t);
+            self.light_film.add_splat(sample.p_film, l * weight);
+            return Vec3::ZERO;
+        } else if s == 1 {
+            let pt = &camera_path[t - 1];
+            let lights = scene.lights();
+            if !pt.is_connectible() || lights.is_empty() {
+                return Vec3::ZERO;
+            }
+            let choice = sampler.get_1d();
+            let index = ((choice * lights.len() as f32) as usize).min(lights.len() - 1);
+            let light = &lights[index];
+            let sample = match light.sample_li(pt.p, sampler.get_2d()) {
+                Some(sample) if sample.pdf > 0.0 && sample.li != Vec3::ZERO => sample,
+                _ => return Vec3::ZERO,
+            };
+            let p = if light.is_infinite() {
+                pt.p + sample.wi
+            } else {
+                pt.p + sample.wi * sample.distance
+            };
+            let mut v = Vertex::new(
+                VertexKind::Light(index),
+                p,
+                Vec3::ZERO,
+                sample.li / (sample.pdf * ctx.light_choice_pdf()),
+            );
+            v.pdf_fwd = ctx.pdf_light_origin(&v, pt);
+            let mut l = pt.beta * pt.f(&v) * v.beta * pt.n.dot(sample.wi).abs();
+            let distance = if light.is_infinite() {
+                f32::INFINITY
+            } else {
+                sample.distance
+            };
+            if l != Vec3::ZERO && !ctx.unoccluded(pt, sample.wi, distance) {
+                l = Vec3::ZERO;
+            }
+            sampled = Some(v);
+            l
+        } else {
+            let qs = &light_path[s - 1];
+            let pt = &camera_path[t - 1];
+            if !qs.is_connectible() || !pt.is_connectible() {
+                return Vec3::ZERO;
+            }
+            let l = qs.beta * qs.f(pt) * pt.f(qs) * pt.beta;
+            if l == Vec3::ZERO {
+                return Vec3::ZERO;
+            }
+            l * ctx.g(qs, pt)
+        };
+        if l == Vec3::ZERO {
+            return Vec3::ZERO;
+        }
+        l * ctx.mis_weight(light_path, camera_path, sampled, s, t)
+    }
+}
+
+impl<C: Camera> Integrator for BdptIntegrator<C> {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler, aov: &mut AovSample) -> Vec3 {
+        let scene_bounds = scene.bvh().bounds();
+        let ctx = Context {
+            scene,
+            camera: &self.camera,
+            scene_bounds,
+            scene_radius: scene_bounds.bounding_sphere().1,
+            time: ray.time,
+        };
+
+        let mut camera_path = Vec::with_capacity(self.max_depth as usize + 2);
+        camera_path.push(Vertex::new(
+            VertexKind::Camera,
+            ray.o,
+            Vec3::ZERO,
+            Vec3::ONE,
+        ));
+        let (_, pdf_dir) = self.camera.pdf_we(ray);
+        ctx.random_walk(
+            &mut camera_path,
+            *ray,
+            sampler,
+            Vec3::ONE,
+            pdf_dir,
+            self.max_depth + 1,
+            TransportMode::Radiance,
+            Some(aov),
+        );
+        let light_path = self.light_subpath(&ctx, sampler);
+        self.n_light_paths.fetch_add(1, Ordering::Relaxed);
+
+        let mut l = Vec3::ZERO;
+        for t in 1..=camera_path.len() {
+            for s in 0..=light_path.len() {
+                let depth = s as i64 + t as i64 - 2;
+                if (s == 1 && t == 1) || depth < 0 || depth > self.max_depth as i64 {
+                    continue;
+                }
+                l += self.connect(&ctx, &light_path, &camera_path, s, t, sampler);
+            }
+        }
+        l
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{image::PixelBuffer, Vec3 as Rgb},
+        rtc::{
+            bsdfs::Diffuse,
+            camera::PerspectiveCamera,
+            lights::{EnvironmentLight, PointLight},
+            progressive::ProgressiveRenderer,
+            shapes::{Plane, Sphere},
+        },
+    };
+    use std::f32::consts::FRAC_1_PI;
+
+    fn render(
+        integrator: &BdptIntegrator<PerspectiveCamera>,
+        scene: &Scene,
+        spp: u32,
+    ) -> PixelBufferRgb32f {
+        let output = ProgressiveRenderer::new()
+            .with_samples_per_pass(spp)
+            .with_sample_range(spp, spp)
+            .with_threads(4)
+            .render(integrator.camera(), scene, integrator);
+        integrator.combine(&output.image)
+    }
+
+    fn mean(image: &PixelBufferRgb32f) -> f32 {
+        let (w, h) = image.dimensions();
+        image.pixels().map(|(_, p)| p[1]).sum::<f32>() / (w * h) as f32
+    }
+
+    #[test]
+    fn diffuse_sphere_in_uniform_environment() {
+        // The sphere fills the field of view, so that every pixel sees it.
+        let mut map = PixelBuffer::new(16, 8);
+        map.fill(Rgb::splat(1.0));
+        let mut scene = Scene::new();
+        scene.add_light(EnvironmentLight::new(map));
+        let diffuse = scene.add_material(Diffuse::new(Vec3::splat(0.5)));
+        scene.add_shape_with_material(Sphere::new(Vec3::ZERO, 1.0), diffuse);
+        let camera =
+            PerspectiveCamera::look_at(Vec3::new(0.0, 0.0, 6.0), Vec3::ZERO, Vec3::Y, 5.0, (8, 8));
+        let integrator = BdptIntegrator::new(camera, 3);
+        let image = render(&integrator, &scene, 64);
+        // A convex diffuse object under a uniform environment reflects its
+        // albedo times the environment radiance.
+        assert!((mean(&image) - 0.5).abs() < 0.03);
+        assert!(mean(&integrator.light_image()) > 0.0);
+    }
+
+    #[test]
+    fn enclosed_camera_sees_no_environment() {
+        // The camera is inside a closed sphere: the environment only reaches
+        // its outer side, and every connection to it is occluded.
+        let mut map = PixelBuffer::new(16, 8);
+        map.fill(Rgb::splat(1.0));
+        let mut scene = Scene::new();
+        scene.add_light(EnvironmentLight::new(map));
+        let diffuse = scene.add_material(Diffuse::new(Vec3::splat(0.5)));
+        scene.add_shape_with_material(Sphere::new(Vec3::ZERO, 10.0), diffuse);
+        let camera =
+            PerspectiveCamera::look_at(Vec3::new(0.0, 0.0, 2.0), Vec3::ZERO, Vec3::Y, 60.0, (8, 8));
+        let integrator = BdptIntegrator::new(camera, 1);
+        let image = render(&integrator, &scene, 16);
+        assert_eq!(mean(&image), 0.0);
+    }
+
+    #[test]
+    fn point_light_over_plane() {
+        let mut scene = Scene::new();
+        let diffuse = scene.add_material(Diffuse::new(Vec3::splat(0.8)));
+        scene.add_shape_with_material(Plane::new(Vec3::ZERO, Vec3::Y), diffuse);
+        scene.add_light(PointLight::new(Vec3::new(0.0, 1.0, 0.0), Vec3::splat(2.0)));
+        // Narrow view of the point below the light: the radiance is the
+        // albedo over pi times the irradiance I / d².
+        let camera =
+            PerspectiveCamera::look_at(Vec3::new(0.0, 2.0, 0.5), Vec3::ZERO, Vec3::Y, 2.0, (4, 4));
+        let integrator = BdptIntegrator::new(camera, 1);
+        let image = render(&integrator, &scene, 256);
+        let expected = 0.8 * FRAC_1_PI * 2.0;
+        assert!((mean(&image) - expected).abs() < 0.05 * expected);
+    }
+}
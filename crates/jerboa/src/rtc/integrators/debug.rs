@@ -0,0 +1,186 @@
+use crate::rtc::{
+    aov::AovSample,
+    bvh::TraversalStats,
+    integrators::{record_hit, spawn_ray, Integrator},
+    ray::Ray,
+    sampler::Sampler,
+    sampling::{coordinate_system, cosine_sample_hemisphere},
+    scene::Scene,
+};
+use glam::Vec3;
+
+/// Quantity displayed by a [`DebugIntegrator`]. Rays missing the scene are
+/// black.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DebugView {
+    /// Normal at the hit, mapped from `[-1, 1]` to `[0, 1]`.
+    Normal,
+
+    /// Barycentric coordinates of the three vertices of the triangle hit,
+    /// derived from the surface coordinates for other shapes.
+    Barycentrics,
+
+    /// Distance to the hit, from white at zero to black at `max_distance`.
+    Distance { max_distance: f32 },
+
+    /// Fractional part of the surface coordinates in red and green.
+    Uv,
+
+    /// Number of BVH nodes visited and primitives tested, as a heat map
+    /// reaching red at `max_cost`. Misses are shown as well.
+    TraversalCost { max_cost: u32 },
+
+    /// Fraction of the cosine weighted hemisphere unoccluded within
+    /// `radius`.
+    AmbientOcclusion { radius: f32, n_samples: u32 },
+}
+
+/// Integrator visualising geometric quantities, to inspect intersections and
+/// acceleration structures.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DebugIntegrator {
+    pub view: DebugView,
+}
+
+impl DebugIntegrator {
+    pub fn new(view: DebugView) -> Self {
+        Self { view }
+    }
+}
+
+/// Maps `x` in `[0, 1]` to a blue, cyan, green, yellow and red ramp.
+fn heat_map(x: f32) -> Vec3 {
+    const RAMP: [Vec3; 5] = [
+        Vec3::new(0.0, 0.0, 1.0),
+        Vec3::new(0.0, 1.0, 1.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Vec3::new(1.0, 1.0, 0.0),
+        Vec3::new(1.0, 0.0, 0.0),
+    ];
+    let x = x.clamp(0.0, 1.0) * (RAMP.len() - 1) as f32;
+    let i = (x as usize).min(RAMP.len() - 2);
+    RAMP[i].lerp(RAMP[i + 1], x - i as f32)
+}
+
+impl Integrator for DebugIntegrator {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler, aov: &mut AovSample) -> Vec3 {
+        let mut stats = TraversalStats::default();
+        let hit = scene.intersect_with_stats(ray, &mut stats);
+        if let DebugView::TraversalCost { max_cost } = self.view {
+            if let Some((primitive, record)) = &hit {
+                record_hit(aov, scene, *primitive, record);
+            }
+            let cost = stats.nodes_visited + stats.primitives_tested;
+            return heat_map(cost as f32 / max_cost as f32);
+        }
+        let (primitive, record) = match hit {
+            Some(hit) => hit,
+            None => return Vec3::ZERO,
+        };
+        record_hit(aov, scene, primitive, &record);
+        match self.view {
+            DebugView::Normal => record.n * 0.5 + Vec3::splat(0.5),
+            DebugView::Barycentrics => {
+                let uv = record.uv;
+                Vec3::new(1.0 - uv.x - uv.y, uv.x, uv.y)
+            }
+            DebugView::Distance { max_distance } => {
+                Vec3::splat((1.0 - record.t / max_distance).max(0.0))
+            }
+            DebugView::Uv => Vec3::new(record.uv.x.fract(), record.uv.y.fract(), 0.0),
+            DebugView::AmbientOcclusion { radius, n_samples } => {
+                // Occlusion is evaluated on the side of the surface facing
+                // the ray.
+                let n = if record.n.dot(ray.d) > 0.0 {
+                    -record.n
+                } else {
+                    record.n
+                };
+                let (s, t) = coordinate_system(n);
+                let unoccluded = (0..n_samples)
+                    .filter(|_| {
+                        let local = cosine_sample_hemisphere(sampler.get_2d());
+                        let wi = s * local.x + t * local.y + n * local.z;
+                        !scene.occluded(&spawn_ray(&record, wi), radius)
+                    })
+                    .count();
+                Vec3::splat(unoccluded as f32 / n_samples.max(1) as f32)
+            }
+            DebugView::TraversalCost { .. } => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::{
+        sampler::IndependentSampler,
+        shapes::{Plane, Sphere, Triangle},
+    };
+
+    fn view(view: DebugView, scene: &Scene, ray: &Ray) -> Vec3 {
+        let mut sampler = IndependentSampler::new(0);
+        DebugIntegrator::new(view).li(ray, scene, &mut sampler, &mut AovSample::default())
+    }
+
+    #[test]
+    fn geometric_views() {
+        let mut scene = Scene::new();
+        scene.add_shape(Plane::new(Vec3::new(0.0, -1.0, 0.0), Vec3::Y));
+        scene.add_shape(Triangle::new(
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(3.0, 0.0, 0.0),
+            Vec3::new(2.0, 1.0, 0.0),
+        ));
+        let down = Ray::new(Vec3::new(0.0, 3.0, 0.0), -Vec3::Y);
+        assert_eq!(
+            view(DebugView::Normal, &scene, &down),
+            Vec3::new(0.5, 1.0, 0.5)
+        );
+        let distance = view(DebugView::Distance { max_distance: 8.0 }, &scene, &down);
+        assert_eq!(distance, Vec3::splat(0.5));
+
+        let at_triangle = Ray::new(Vec3::new(2.25, 0.5, 1.0), -Vec3::Z);
+        let barycentrics = view(DebugView::Barycentrics, &scene, &at_triangle);
+        assert!(barycentrics.abs_diff_eq(Vec3::new(0.25, 0.25, 0.5), 1e-6));
+        let up = Ray::new(Vec3::ZERO, Vec3::Y);
+        assert_eq!(view(DebugView::Normal, &scene, &up), Vec3::ZERO);
+    }
+
+    #[test]
+    fn ambient_occlusion() {
+        let mut scene = Scene::new();
+        scene.add_shape(Plane::new(Vec3::ZERO, Vec3::Y));
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 0.0), -Vec3::Y);
+        let ao = DebugView::AmbientOcclusion {
+            radius: 1.0,
+            n_samples: 64,
+        };
+        assert_eq!(view(ao, &scene, &ray), Vec3::ONE);
+        // A sphere resting on the plane next to the shaded point.
+        scene.add_shape(Sphere::new(Vec3::new(0.6, 0.5, 0.0), 0.5));
+        let ray = Ray::new(
+            Vec3::new(0.0, 1.0, 0.0),
+            (Vec3::new(0.05, 0.0, 0.0) - Vec3::Y).normalize(),
+        );
+        let occlusion = view(ao, &scene, &ray).x;
+        assert!(occlusion > 0.3 && occlusion < 0.95);
+    }
+
+    #[test]
+    fn traversal_cost_grows_with_scene() {
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 30.0), -Vec3::Z);
+        let cost = DebugView::TraversalCost { max_cost: 16 };
+        let mut scene = Scene::new();
+        scene.add_shape(Sphere::new(Vec3::new(0.0, 0.0, -1.0), 0.5));
+        let low = view(cost, &scene, &ray);
+        for i in 0..16 {
+            scene.add_shape(Sphere::new(Vec3::new(0.0, 0.0, i as f32), 0.4));
+        }
+        let high = view(cost, &scene, &ray);
+        assert!(low.z > 0.5 && low.x == 0.0);
+        assert!(high.x > low.x);
+        assert_eq!(heat_map(1.0), Vec3::X);
+    }
+}
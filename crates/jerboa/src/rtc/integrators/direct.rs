@@ -0,0 +1,182 @@
+use crate::rtc::{
+    aov::AovSample,
+    bsdfs::{Bsdf, TransportMode},
+    integrators::{escaped_radiance, record_hit, spawn_ray, Integrator, SHADOW_EPSILON},
+    lights::Light,
+    ray::Ray,
+    sampler::Sampler,
+    sampling::power_heuristic,
+    scene::Scene,
+    IntersectRecord,
+};
+use glam::Vec3;
+
+/// Direct illumination, estimated for every light by combining a light sample
+/// and a BSDF sample with multiple importance sampling. Specular surfaces are
+/// followed as in [`super::WhittedIntegrator`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DirectLightingIntegrator {
+    /// Maximum number of specular bounces.
+    pub max_depth: u32,
+}
+
+impl Default for DirectLightingIntegrator {
+    fn default() -> Self {
+        Self { max_depth: 5 }
+    }
+}
+
+impl DirectLightingIntegrator {
+    pub fn new(max_depth: u32) -> Self {
+        Self { max_depth }
+    }
+
+    fn trace(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler, depth: u32) -> Vec3 {
+        match scene.intersect(ray) {
+            Some((primitive, record)) => self.shade(ray, scene, sampler, primitive, &record, depth),
+            None => escaped_radiance(ray, scene),
+        }
+    }
+
+    fn shade(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        sampler: &mut dyn Sampler,
+        primitive: usize,
+        record: &IntersectRecord,
+        depth: u32,
+    ) -> Vec3 {
+        let bsdf = match scene.material(primitive) {
+            Some(bsdf) => bsdf,
+            None => return Vec3::ZERO,
+        };
+        let wo = -ray.d;
+        if !bsdf.is_specular() {
+            return scene.lights().iter().fold(Vec3::ZERO, |l, light| {
+                l + estimate_direct(light.as_ref(), bsdf, wo, record, scene, sampler)
+            });
+        }
+        let mut l = Vec3::ZERO;
+        if depth < self.max_depth {
+            for (wi, weight) in bsdf.specular_directions(wo, record.n) {
+                if weight != Vec3::ZERO {
+                    l += weight * self.trace(&spawn_ray(record, wi), scene, sampler, depth + 1);
+                }
+            }
+        }
+        l
+    }
+}
+
+/// Radiance reflected towards `wo` from a single light.
+pub(crate) fn estimate_direct(
+    light: &dyn Light,
+    bsdf: &dyn Bsdf,
+    wo: Vec3,
+    record: &IntersectRecord,
+    scene: &Scene,
+    sampler: &mut dyn Sampler,
+) -> Vec3 {
+    let n = record.n;
+    let u_light = sampler.get_2d();
+    let u_bsdf = sampler.get_2d();
+    // Only lights at infinity can be reached by BSDF sampling, the others
+    // are estimated from light samples alone.
+    let use_mis = light.is_infinite() && !light.is_delta();
+    let mut l = Vec3::ZERO;
+
+    if let Some(sample) = light.sample_li(record.p, u_light) {
+        let f = bsdf.f(wo, sample.wi, n) * sample.wi.dot(n).abs();
+        if sample.pdf > 0.0 && sample.li != Vec3::ZERO && f != Vec3::ZERO {
+            let shadow_ray = spawn_ray(record, sample.wi);
+            if !scene.occluded(&shadow_ray, sample.distance * (1.0 - SHADOW_EPSILON)) {
+                let weight = if use_mis {
+                    power_heuristic(1, sample.pdf, 1, bsdf.pdf(wo, sample.wi, n))
+                } else {
+                    1.0
+                };
+                l += f * sample.li * weight / sample.pdf;
+            }
+        }
+    }
+
+    if use_mis {
+        if let Some(sample) = bsdf.sample_f(wo, n, u_bsdf, TransportMode::Radiance) {
+            let f = sample.f * sample.wi.dot(n).abs();
+            let light_pdf = light.pdf_li(record.p, sample.wi);
+            if sample.pdf > 0.0 && light_pdf > 0.0 && f != Vec3::ZERO {
+                let ray = spawn_ray(record, sample.wi);
+                if scene.intersect(&ray).is_none() {
+                    let weight = power_heuristic(1, sample.pdf, 1, light_pdf);
+                    l += f * light.le(&ray) * weight / sample.pdf;
+                }
+            }
+        }
+    }
+    l
+}
+
+impl Integrator for DirectLightingIntegrator {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler, aov: &mut AovSample) -> Vec3 {
+        match scene.intersect(ray) {
+            Some((primitive, record)) => {
+                record_hit(aov, scene, primitive, &record);
+                self.shade(ray, scene, sampler, primitive, &record, 0)
+            }
+            None => escaped_radiance(ray, scene),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{image::PixelBuffer, Vec3 as Rgb},
+        rtc::{
+            bsdfs::Diffuse,
+            integrators::WhittedIntegrator,
+            lights::{EnvironmentLight, PointLight},
+            sampler::IndependentSampler,
+            shapes::Sphere,
+        },
+    };
+
+    fn mean_radiance(integrator: &dyn Integrator, scene: &Scene, ray: &Ray, n: u32) -> Vec3 {
+        let mut sampler = IndependentSampler::new(7);
+        (0..n).fold(Vec3::ZERO, |l, i| {
+            sampler.start_pixel_sample((0, 0), i);
+            l + integrator.li(ray, scene, &mut sampler, &mut AovSample::default())
+        }) / n as f32
+    }
+
+    #[test]
+    fn diffuse_sphere_in_uniform_environment() {
+        // Without occlusion, a Lambertian surface lit by a uniform
+        // environment reflects its albedo times the environment radiance.
+        let mut map = PixelBuffer::new(16, 8);
+        map.fill(Rgb::splat(1.0));
+        let mut scene = Scene::new();
+        scene.add_light(EnvironmentLight::new(map));
+        let diffuse = scene.add_material(Diffuse::new(Vec3::splat(0.5)));
+        scene.add_shape_with_material(Sphere::new(Vec3::ZERO, 1.0), diffuse);
+        let ray = Ray::new(Vec3::new(0.3, 0.2, 5.0), -Vec3::Z);
+        let l = mean_radiance(&DirectLightingIntegrator::default(), &scene, &ray, 512);
+        assert!(l.abs_diff_eq(Vec3::splat(0.5), 0.02));
+    }
+
+    #[test]
+    fn point_lights_match_whitted() {
+        let mut scene = Scene::new();
+        let diffuse = scene.add_material(Diffuse::new(Vec3::new(0.2, 0.5, 0.8)));
+        scene.add_shape_with_material(Sphere::new(Vec3::ZERO, 1.0), diffuse);
+        scene.add_light(PointLight::new(Vec3::new(1.0, 3.0, 2.0), Vec3::splat(4.0)));
+        scene.add_light(PointLight::new(Vec3::new(-2.0, 1.0, 3.0), Vec3::splat(2.0)));
+        let ray = Ray::new(Vec3::new(0.1, 0.4, 5.0), -Vec3::Z);
+        let direct = mean_radiance(&DirectLightingIntegrator::default(), &scene, &ray, 1);
+        let whitted = mean_radiance(&WhittedIntegrator::default(), &scene, &ray, 1);
+        assert!(direct.cmpgt(Vec3::ZERO).all());
+        assert!(direct.abs_diff_eq(whitted, 1e-6));
+    }
+}
@@ -0,0 +1,240 @@
+//! Primary sample space Metropolis light transport (Kelemen et al. 2002).
+//!
+//! Another integrator estimates the radiance of camera rays from the random
+//! numbers of a [`MltSampler`], and Markov chains mutate these numbers so
+//! that paths are sampled proportionally to the luminance they carry. Once a
+//! path carrying light is found, small mutations explore the paths around
+//! it, which finds light arriving through narrow openings far more often
+//! than independent samples do.
+//!
+//! A bootstrap phase of independent samples estimates the overall
+//! brightness of the image, by which the relative brightness found by the
+//! chains is scaled, and picks the starting states of the chains.
+
+use crate::{
+    core::image::{
+        par::{default_n_threads, run_parallel},
+        PixelBufferRgb32f,
+    },
+    rtc::{
+        aov::AovSample,
+        camera::Camera,
+        film::Film,
+        integrators::Integrator,
+        sampler::{mix_bits, MltSampler, Pcg32, Sampler},
+        scene::Scene,
+    },
+};
+use glam::{Vec2, Vec3};
+
+/// Number of bootstrap samples evaluated by each task.
+const BOOTSTRAP_CHUNK_SIZE: usize = 1024;
+
+fn luminance(v: Vec3) -> f32 {
+    v.dot(Vec3::new(0.2126, 0.7152, 0.0722))
+}
+
+/// Metropolis light transport over the paths sampled by another integrator,
+/// rendering a whole image. The integrator must return all the radiance it
+/// estimates rather than splatting part of it.
+#[derive(Debug, Clone)]
+pub struct MltIntegrator<I: Integrator> {
+    integrator: I,
+    n_bootstrap: u32,
+    n_chains: u32,
+    mutations_per_pixel: u32,
+
+    /// Standard deviation of small steps.
+    sigma: f32,
+
+    large_step_probability: f32,
+    n_threads: usize,
+    seed: u64,
+}
+
+impl<I: Integrator> MltIntegrator<I> {
+    pub fn new(integrator: I) -> Self {
+        Self {
+            integrator,
+            n_bootstrap: 100_000,
+            n_chains: 1000,
+            mutations_per_pixel: 100,
+            sigma: 0.01,
+            large_step_probability: 0.3,
+            n_threads: default_n_threads(),
+            seed: 0,
+        }
+    }
+
+    pub fn with_bootstrap_samples(mut self, n: u32) -> Self {
+        self.n_bootstrap = n.max(1);
+        self
+    }
+
+    pub fn with_chains(mut self, n: u32) -> Self {
+        self.n_chains = n.max(1);
+        self
+    }
+
+    pub fn with_mutations_per_pixel(mut self, n: u32) -> Self {
+        self.mutations_per_pixel = n.max(1);
+        self
+    }
+
+    pub fn with_sigma(mut self, sigma: f32) -> Self {
+        self.sigma = sigma;
+        self
+    }
+
+    pub fn with_large_step_probability(mut self, probability: f32) -> Self {
+        self.large_step_probability = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_threads(mut self, n_threads: usize) -> Self {
+        self.n_threads = n_threads.max(1);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn integrator(&self) -> &I {
+        &self.integrator
+    }
+
+    pub fn render(&self, camera: &dyn Camera, scene: &Scene) -> PixelBufferRgb32f {
+        let (width, height) = camera.resolution();
+        let film = Film::new(width, height);
+
+        // Luminance of independent samples.
+        let mut weights = vec![0.0f32; self.n_bootstrap as usize];
+        run_parallel(
+            weights
+                .chunks_mut(BOOTSTRAP_CHUNK_SIZE)
+                .enumerate()
+                .collect(),
+            self.n_threads,
+            |(chunk, weights): (usize, &mut [f32])| {
+                for (i, weight) in weights.iter_mut().enumerate() {
+                    let mut sampler = self.sampler((chunk * BOOTSTRAP_CHUNK_SIZE + i) as u64);
+                    *weight = luminance(self.evaluate(camera, scene, &mut sampler).1).max(0.0);
+                }
+            },
+        );
+        let mut cdf = Vec::with_capacity(weights.len());
+        let total = weights.iter().fold(0.0f64, |sum, &w| {
+            cdf.push(sum + w as f64);
+            sum + w as f64
+        });
+        if total == 0.0 {
+            return film.resolve(0.0);
+        }
+        let b = (total / weights.len() as f64) as f32;
+
+        let n_mutations = self.mutations_per_pixel as u64 * (width * height) as u64;
+        let n_chains = self.n_chains as u64;
+        run_parallel((0..n_chains).collect(), self.n_threads, |chain| {
+            let mut rng = Pcg32::new(mix_bits(self.seed), self.n_bootstrap as u64 + chain);
+            // Starting state chosen proportionally to the bootstrap weights.
+            let u = rng.next_f32() as f64 * total;
+            let index = cdf.partition_point(|&c| c <= u).min(cdf.len() - 1);
+            let mut sampler = self.sampler(index as u64);
+            let (mut p_current, mut l_current) = self.evaluate(camera, scene, &mut sampler);
+
+            let n = n_mutations / n_chains + u64::from(chain < n_mutations % n_chains);
+            for _ in 0..n {
+                sampler.start_iteration();
+                let (p_proposed, l_proposed) = self.evaluate(camera, scene, &mut sampler);
+                let (y_current, y_proposed) = (luminance(l_current), luminance(l_proposed));
+                let accept = if y_current > 0.0 {
+                    (y_proposed / y_current).clamp(0.0, 1.0)
+                } else {
+                    1.0
+                };
+                // Both states are splatted with their expected weights.
+                if accept > 0.0 {
+                    film.add_splat(p_proposed, l_proposed * accept / y_proposed);
+                }
+                if accept < 1.0 {
+                    film.add_splat(p_current, l_current * (1.0 - accept) / y_current);
+                }
+                if rng.next_f32() < accept {
+                    p_current = p_proposed;
+                    l_current = l_proposed;
+                    sampler.accept();
+                } else {
+                    sampler.reject();
+                }
+            }
+        });
+        film.resolve(b / self.mutations_per_pixel as f32)
+    }
+
+    fn sampler(&self, stream: u64) -> MltSampler {
+        MltSampler::new(self.seed, stream, self.sigma, self.large_step_probability)
+    }
+
+    /// Film position and radiance of the camera ray sampled from the
+    /// current vector of the sampler.
+    fn evaluate(
+        &self,
+        camera: &dyn Camera,
+        scene: &Scene,
+        sampler: &mut MltSampler,
+    ) -> (Vec2, Vec3) {
+        let (width, height) = camera.resolution();
+        let p_film = sampler.get_2d() * Vec2::new(width as f32, height as f32);
+        let ray = camera.generate_ray(p_film, sampler.get_2d(), sampler.get_1d());
+        let l = self
+            .integrator
+            .li(&ray, scene, sampler, &mut AovSample::default());
+        (p_film, if l.is_finite() { l } else { Vec3::ZERO })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{image::PixelBuffer, Vec3 as Rgb},
+        rtc::{
+            bsdfs::Diffuse, camera::PerspectiveCamera, integrators::DirectLightingIntegrator,
+            lights::EnvironmentLight, shapes::Sphere,
+        },
+    };
+
+    #[test]
+    fn diffuse_sphere_in_uniform_environment() {
+        let mut map = PixelBuffer::new(16, 8);
+        map.fill(Rgb::splat(1.0));
+        let mut scene = Scene::new();
+        scene.add_light(EnvironmentLight::new(map));
+        let diffuse = scene.add_material(Diffuse::new(Vec3::splat(0.5)));
+        scene.add_shape_with_material(Sphere::new(Vec3::ZERO, 1.0), diffuse);
+        let camera =
+            PerspectiveCamera::look_at(Vec3::new(0.0, 0.0, 4.0), Vec3::ZERO, Vec3::Y, 40.0, (8, 8));
+        let image = MltIntegrator::new(DirectLightingIntegrator::default())
+            .with_bootstrap_samples(4096)
+            .with_chains(64)
+            .with_mutations_per_pixel(512)
+            .with_threads(4)
+            .render(&camera, &scene);
+
+        let mean = |pixels: &[(u32, u32)]| {
+            pixels
+                .iter()
+                .map(|&(x, y)| image.pixel_at(x, y).unwrap()[1])
+                .sum::<f32>()
+                / pixels.len() as f32
+        };
+        // The sphere reflects half of the environment, which is seen
+        // directly in the corners.
+        let sphere = mean(&[(3, 3), (4, 3), (3, 4), (4, 4)]);
+        let background = mean(&[(0, 0), (7, 0), (0, 7), (7, 7)]);
+        assert!((sphere - 0.5).abs() < 0.05);
+        assert!((background - 1.0).abs() < 0.1);
+    }
+}
@@ -0,0 +1,72 @@
+//! Light transport algorithms.
+
+mod bdpt;
+mod debug;
+mod direct;
+mod mlt;
+mod sppm;
+mod volpath;
+mod whitted;
+
+pub use bdpt::BdptIntegrator;
+pub use debug::{DebugIntegrator, DebugView};
+pub use direct::DirectLightingIntegrator;
+pub use mlt::MltIntegrator;
+pub use sppm::SppmIntegrator;
+pub use volpath::VolPathIntegrator;
+pub use whitted::WhittedIntegrator;
+
+use crate::rtc::{
+    aov::AovSample,
+    ray::{offset_ray_origin, Ray},
+    sampler::Sampler,
+    scene::Scene,
+    IntersectRecord,
+};
+use glam::Vec3;
+
+/// Fraction of the distance to a light left out of shadow rays, so that
+/// surfaces at the sampled point of the light do not occlude it.
+pub(crate) const SHADOW_EPSILON: f32 = 1e-4;
+
+/// Light transport algorithm estimating the radiance along camera rays.
+pub trait Integrator: Send + Sync {
+    /// Estimates the radiance arriving at the origin of `ray` from its
+    /// direction, filling `aov` with the auxiliary quantities of the first
+    /// hit.
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler, aov: &mut AovSample) -> Vec3;
+}
+
+/// Ray leaving the surface at `record` in the direction `d`, started on the
+/// side of the surface `d` points to, beyond the error of the hit point.
+pub(crate) fn spawn_ray(record: &IntersectRecord, d: Vec3) -> Ray {
+    let o = offset_ray_origin(record.p, record.p_error, record.n, d);
+    Ray::new(o, d).with_time(record.time)
+}
+
+/// Radiance of the lights at infinity seen by a ray escaping the scene.
+pub(crate) fn escaped_radiance(ray: &Ray, scene: &Scene) -> Vec3 {
+    scene
+        .lights()
+        .iter()
+        .filter(|light| light.is_infinite())
+        .fold(Vec3::ZERO, |l, light| l + light.le(ray))
+}
+
+/// Fills the AOVs of the first hit of a camera ray.
+pub(crate) fn record_hit(
+    aov: &mut AovSample,
+    scene: &Scene,
+    primitive: usize,
+    record: &IntersectRecord,
+) {
+    aov.hit = true;
+    aov.depth = record.t;
+    aov.position = record.p;
+    aov.normal = record.n;
+    aov.primitive_id = primitive as u32;
+    aov.material_id = scene.material_id(primitive);
+    aov.albedo = scene
+        .material(primitive)
+        .map_or(Vec3::ZERO, |bsdf| bsdf.albedo());
+}
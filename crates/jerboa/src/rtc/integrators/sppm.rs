@@ -0,0 +1,450 @@
+//! Stochastic progressive photon mapping (Hachisuka and Jensen 2009).
+//!
+//! Each iteration traces a camera path per pixel through specular surfaces
+//! to its first diffuse hit, the visible point, then traces photons from the
+//! lights and gathers the ones landing within a radius of each visible
+//! point. The radius of every pixel shrinks as photons are gathered, so that
+//! the estimate converges while still resolving specular-diffuse-specular
+//! paths, e.g. caustics seen through glass, which path tracing cannot sample.
+//!
+//! Direct lighting at visible points is estimated from light samples;
+//! photons only account for light having been scattered at least once.
+
+use crate::{
+    core::image::{
+        par::{default_n_threads, run_parallel},
+        PixelBufferRgb32f,
+    },
+    rtc::{
+        bsdfs::{Bsdf, TransportMode},
+        camera::Camera,
+        integrators::{direct::estimate_direct, escaped_radiance, spawn_ray},
+        photon_map::{Photon, PhotonMap},
+        ray::Ray,
+        sampler::{IndependentSampler, Sampler},
+        scene::Scene,
+    },
+};
+use glam::{Vec2, Vec3};
+use std::{f32::consts::PI, sync::Mutex};
+
+/// Number of photons traced by each task.
+const PHOTON_CHUNK_SIZE: usize = 4096;
+
+/// First diffuse hit of a camera path.
+struct VisiblePoint<'a> {
+    p: Vec3,
+    n: Vec3,
+    wo: Vec3,
+    bsdf: &'a dyn Bsdf,
+
+    /// Throughput of the camera path up to the point.
+    beta: Vec3,
+}
+
+/// Estimate of a pixel, refined at every iteration.
+struct PixelState<'a> {
+    /// Sum over the iterations of the radiance not estimated with photons.
+    ld: Vec3,
+
+    /// Gathering radius.
+    radius: f32,
+
+    /// Number of photons accounted for in `tau`.
+    n: f32,
+
+    /// Accumulated flux, scaled to the current radius.
+    tau: Vec3,
+
+    visible_point: Option<VisiblePoint<'a>>,
+}
+
+/// Stochastic progressive photon mapper, rendering a whole image in a given
+/// number of iterations rather than estimating radiance ray by ray.
+#[derive(Debug, Clone)]
+pub struct SppmIntegrator {
+    initial_radius: f32,
+
+    /// Maximum number of bounces of camera paths and photons.
+    max_depth: u32,
+
+    photons_per_iteration: usize,
+
+    /// Fraction of the newly gathered photons kept at every iteration,
+    /// trading the speed at which the radius shrinks for noise.
+    alpha: f32,
+
+    n_threads: usize,
+    seed: u64,
+}
+
+impl SppmIntegrator {
+    /// Creates an integrator gathering photons within `initial_radius` at
+    /// the first iteration.
+    pub fn new(initial_radius: f32) -> Self {
+        Self {
+            initial_radius,
+            max_depth: 5,
+            photons_per_iteration: 100_000,
+            alpha: 2.0 / 3.0,
+            n_threads: default_n_threads(),
+            seed: 0,
+        }
+    }
+
+    pub fn with_max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_photons_per_iteration(mut self, n: usize) -> Self {
+        self.photons_per_iteration = n.max(1);
+        self
+    }
+
+    /// Sets the fraction of photons kept, in `(0, 1)`.
+    pub fn with_alpha(mut self, alpha: f32) -> Self {
+        self.alpha = alpha.clamp(f32::EPSILON, 1.0);
+        self
+    }
+
+    pub fn with_threads(mut self, n_threads: usize) -> Self {
+        self.n_threads = n_threads.max(1);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn render(
+        &self,
+        camera: &dyn Camera,
+        scene: &Scene,
+        n_iterations: u32,
+    ) -> PixelBufferRgb32f {
+        let (width, height) = camera.resolution();
+        let mut pixels: Vec<PixelState> = (0..width * height)
+            .map(|_| PixelState {
+                ld: Vec3::ZERO,
+                radius: self.initial_radius,
+                n: 0.0,
+                tau: Vec3::ZERO,
+                visible_point: None,
+            })
+            .collect();
+
+        for iteration in 0..n_iterations {
+            run_parallel(
+                pixels
+                    .chunks_mut(width.max(1) as usize)
+                    .enumerate()
+                    .collect(),
+                self.n_threads,
+                |(y, row): (usize, &mut [PixelState])| {
+                    let mut sampler = IndependentSampler::new(self.seed);
+                    for (x, pixel) in row.iter_mut().enumerate() {
+                        sampler.start_pixel_sample((x as u32, y as u32), iteration);
+                        self.trace_camera_path(camera, scene, &mut sampler, (x, y), pixel);
+                    }
+                },
+            );
+
+            let max_radius = pixels.iter().fold(0.0f32, |r, pixel| r.max(pixel.radius));
+            let photon_map = PhotonMap::new(
+                self.trace_photons(scene, camera.shutter(), iteration),
+                max_radius,
+            );
+
+            run_parallel(
+                pixels.chunks_mut(width.max(1) as usize).collect(),
+                self.n_threads,
+                |row: &mut [PixelState]| {
+                    for pixel in row {
+                        self.gather(&photon_map, pixel);
+                    }
+                },
+            );
+        }
+
+        let n_iterations = n_iterations.max(1) as f32;
+        let n_photons = n_iterations * self.photons_per_iteration as f32;
+        let mut image = PixelBufferRgb32f::new(width, height);
+        for ((x, y), pixel) in image.pixels_mut() {
+            let state = &pixels[y * width as usize + x];
+            let area = PI * state.radius * state.radius;
+            let l = state.ld / n_iterations + state.tau / (n_photons * area);
+            *pixel = crate::core::Vec3::from(l.to_array());
+        }
+        image
+    }
+
+    /// Follows the camera ray of a pixel through specular surfaces, storing
+    /// the visible point and adding the radiance found on the way.
+    fn trace_camera_path<'a>(
+        &self,
+        camera: &dyn Camera,
+        scene: &'a Scene,
+        sampler: &mut dyn Sampler,
+        (x, y): (usize, usize),
+        pixel: &mut PixelState<'a>,
+    ) {
+        pixel.visible_point = None;
+        let p_film = Vec2::new(x as f32, y as f32) + sampler.get_2d();
+        let mut ray = camera.generate_ray(p_film, sampler.get_2d(), sampler.get_1d());
+        let mut beta = Vec3::ONE;
+        for depth in 0..=self.max_depth {
+            let (primitive, record) = match scene.intersect(&ray) {
+                Some(hit) => hit,
+                None => {
+                    pixel.ld += beta * escaped_radiance(&ray, scene);
+                    return;
+                }
+            };
+            let bsdf = match scene.material(primitive) {
+                Some(bsdf) => bsdf,
+                None => return,
+            };
+            let wo = -ray.d;
+            if !bsdf.is_specular() {
+                pixel.ld += beta
+                    * scene.lights().iter().fold(Vec3::ZERO, |l, light| {
+                        l + estimate_direct(light.as_ref(), bsdf, wo, &record, scene, sampler)
+                    });
+                pixel.visible_point = Some(VisiblePoint {
+                    p: record.p,
+                    n: record.n,
+                    wo,
+                    bsdf,
+                    beta,
+                });
+                return;
+            }
+            if depth == self.max_depth {
+                return;
+            }
+            let sample =
+                match bsdf.sample_f(wo, record.n, sampler.get_2d(), TransportMode::Radiance) {
+                    Some(sample) if sample.pdf > 0.0 => sample,
+                    _ => return,
+                };
+            beta *= sample.f * sample.wi.dot(record.n).abs() / sample.pdf;
+            ray = spawn_ray(&record, sample.wi);
+        }
+    }
+
+    /// Photons of an iteration, deposited on diffuse surfaces after at least
+    /// one bounce. Photons are emitted while the `shutter` is open, for them
+    /// to see the same moving geometry as the camera paths.
+    fn trace_photons(&self, scene: &Scene, shutter: (f32, f32), iteration: u32) -> Vec<Photon> {
+        let lights = scene.lights();
+        if lights.is_empty() {
+            return Vec::new();
+        }
+        let scene_bounds = scene.bvh().bounds();
+        let chunks: Vec<usize> = (0..self.photons_per_iteration)
+            .step_by(PHOTON_CHUNK_SIZE)
+            .collect();
+        let traced = Mutex::new(Vec::with_capacity(chunks.len()));
+        run_parallel(chunks, self.n_threads, |first| {
+            // Photons use their own stream, not to be correlated with the
+            // camera paths.
+            let mut sampler = IndependentSampler::new(!self.seed);
+            let mut photons = Vec::new();
+            let last = (first + PHOTON_CHUNK_SIZE).min(self.photons_per_iteration);
+            for index in first..last {
+                sampler.start_pixel_sample((index as u32, 0), iteration);
+                let choice = sampler.get_1d();
+                let light =
+                    &lights[((choice * lights.len() as f32) as usize).min(lights.len() - 1)];
+                let choice_pdf = 1.0 / lights.len() as f32;
+                let (u_pos, u_dir) = (sampler.get_2d(), sampler.get_2d());
+                let time = shutter.0 + (shutter.1 - shutter.0) * sampler.get_1d();
+                let emission = match light.sample_le(u_pos, u_dir, &scene_bounds) {
+                    Some(e) if e.pdf_pos > 0.0 && e.pdf_dir > 0.0 && e.le != Vec3::ZERO => e,
+                    _ => continue,
+                };
+                let cos = if emission.n == Vec3::ZERO {
+                    1.0
+                } else {
+                    emission.n.dot(emission.ray.d).abs()
+                };
+                let beta = emission.le * cos / (choice_pdf * emission.pdf_pos * emission.pdf_dir);
+                let ray = emission.ray.with_time(time);
+                self.trace_photon(scene, &mut sampler, ray, beta, &mut photons);
+            }
+            traced.lock().unwrap().push((first, photons));
+        });
+        // Sorted for the estimates not to depend on the scheduling.
+        let mut traced = traced.into_inner().unwrap();
+        traced.sort_by_key(|(first, _)| *first);
+        traced
+            .into_iter()
+            .flat_map(|(_, photons)| photons)
+            .collect()
+    }
+
+    fn trace_photon(
+        &self,
+        scene: &Scene,
+        sampler: &mut dyn Sampler,
+        mut ray: Ray,
+        mut beta: Vec3,
+        photons: &mut Vec<Photon>,
+    ) {
+        for depth in 0..=self.max_depth {
+            let (primitive, record) = match scene.intersect(&ray) {
+                Some(hit) => hit,
+                None => return,
+            };
+            let bsdf = match scene.material(primitive) {
+                Some(bsdf) => bsdf,
+                None => return,
+            };
+            let wo = -ray.d;
+            if depth > 0 && !bsdf.is_specular() {
+                photons.push(Photon {
+                    p: record.p,
+                    wi: wo,
+                    power: beta,
+                });
+            }
+            if depth == self.max_depth {
+                return;
+            }
+            let sample =
+                match bsdf.sample_f(wo, record.n, sampler.get_2d(), TransportMode::Importance) {
+                    Some(sample) if sample.pdf > 0.0 && sample.f != Vec3::ZERO => sample,
+                    _ => return,
+                };
+            beta *= sample.f * sample.wi.dot(record.n).abs() / sample.pdf;
+            ray = spawn_ray(&record, sample.wi);
+        }
+    }
+
+    /// Adds the photons around the visible point of a pixel to its estimate
+    /// and shrinks its radius.
+    fn gather(&self, photon_map: &PhotonMap, pixel: &mut PixelState) {
+        let vp = match &pixel.visible_point {
+            Some(vp) => vp,
+            None => return,
+        };
+        let mut phi = Vec3::ZERO;
+        let mut m = 0u32;
+        photon_map.for_each_within(vp.p, pixel.radius, |photon| {
+            phi += photon.power * vp.bsdf.f(vp.wo, photon.wi, vp.n);
+            m += 1;
+        });
+        if m == 0 {
+            return;
+        }
+        let m = m as f32;
+        let n = pixel.n + self.alpha * m;
+        let radius = pixel.radius * (n / (pixel.n + m)).sqrt();
+        pixel.tau = (pixel.tau + vp.beta * phi) * (radius * radius) / (pixel.radius * pixel.radius);
+        pixel.n = n;
+        pixel.radius = radius;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::{
+        bsdfs::{Diffuse, Mirror},
+        camera::PerspectiveCamera,
+        lights::PointLight,
+        shapes::{AnimatedInstance, Plane, Quad, Triangle},
+        transform::{AnimatedTransform, Keyframe},
+    };
+    use glam::Quat;
+    use std::{f32::consts::FRAC_1_PI, sync::Arc};
+
+    #[test]
+    fn mirror_caustic() {
+        // A point light between a diffuse floor and a mirror ceiling, with
+        // a blocker casting a shadow on the floor around the origin. The
+        // only light reaching the origin is reflected by the mirror, as if
+        // it came from the image of the light above the ceiling.
+        let mut scene = Scene::new();
+        let floor = scene.add_material(Diffuse::new(Vec3::splat(0.8)));
+        let mirror = scene.add_material(Mirror::new(Vec3::ONE));
+        scene.add_shape_with_material(Plane::new(Vec3::ZERO, Vec3::Y), floor);
+        scene.add_shape_with_material(Plane::new(Vec3::new(0.0, 2.0, 0.0), -Vec3::Y), mirror);
+        let light = Vec3::new(1.0, 1.0, 0.0);
+        scene.add_light(PointLight::new(light, Vec3::splat(10.0)));
+        let corners = [
+            Vec3::new(0.35, 0.5, -0.2),
+            Vec3::new(0.65, 0.5, -0.2),
+            Vec3::new(0.65, 0.5, 0.2),
+            Vec3::new(0.35, 0.5, 0.2),
+        ];
+        scene.add_shape(Triangle::new(corners[0], corners[1], corners[2]));
+        scene.add_shape(Triangle::new(corners[0], corners[2], corners[3]));
+
+        let camera =
+            PerspectiveCamera::look_at(Vec3::new(0.0, 1.5, -0.5), Vec3::ZERO, Vec3::Y, 2.0, (4, 4));
+        let image = SppmIntegrator::new(0.2)
+            .with_max_depth(1)
+            .with_photons_per_iteration(50_000)
+            .with_threads(4)
+            .render(&camera, &scene, 32);
+        let mean = image.pixels().map(|(_, p)| p[1]).sum::<f32>() / 16.0;
+
+        let image_light = Vec3::new(light.x, 4.0 - light.y, light.z);
+        let d = image_light.length();
+        let irradiance = 10.0 * (image_light.y / d) / (d * d);
+        let expected = 0.8 * FRAC_1_PI * irradiance;
+        assert!((mean - expected).abs() < 0.05 * expected);
+    }
+
+    #[test]
+    fn photons_see_moving_geometry() {
+        // The mirror caustic, with a mirror that only moves into place above
+        // the floor by the time the shutter opens.
+        let mut scene = Scene::new();
+        let floor = scene.add_material(Diffuse::new(Vec3::splat(0.8)));
+        let mirror = scene.add_material(Mirror::new(Vec3::ONE));
+        scene.add_shape_with_material(Plane::new(Vec3::ZERO, Vec3::Y), floor);
+        let ceiling = Quad::new(
+            Vec3::new(-5.0, 2.0, -5.0),
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 10.0),
+        );
+        let animation = AnimatedTransform::new(vec![
+            Keyframe::new(0.0, Vec3::ONE, Quat::IDENTITY, Vec3::new(100.0, 0.0, 0.0)),
+            Keyframe::new(1.0, Vec3::ONE, Quat::IDENTITY, Vec3::ZERO),
+        ]);
+        scene.add_shape_with_material(AnimatedInstance::new(Arc::new(ceiling), animation), mirror);
+        let light = Vec3::new(1.0, 1.0, 0.0);
+        scene.add_light(PointLight::new(light, Vec3::splat(10.0)));
+        let corners = [
+            Vec3::new(0.35, 0.5, -0.2),
+            Vec3::new(0.65, 0.5, -0.2),
+            Vec3::new(0.65, 0.5, 0.2),
+            Vec3::new(0.35, 0.5, 0.2),
+        ];
+        scene.add_shape(Triangle::new(corners[0], corners[1], corners[2]));
+        scene.add_shape(Triangle::new(corners[0], corners[2], corners[3]));
+
+        let camera =
+            PerspectiveCamera::look_at(Vec3::new(0.0, 1.5, -0.5), Vec3::ZERO, Vec3::Y, 2.0, (4, 4))
+                .with_shutter(1.0, 2.0);
+        let image = SppmIntegrator::new(0.2)
+            .with_max_depth(1)
+            .with_photons_per_iteration(50_000)
+            .with_threads(4)
+            .render(&camera, &scene, 32);
+        let mean = image.pixels().map(|(_, p)| p[1]).sum::<f32>() / 16.0;
+
+        let image_light = Vec3::new(light.x, 4.0 - light.y, light.z);
+        let d = image_light.length();
+        let irradiance = 10.0 * (image_light.y / d) / (d * d);
+        let expected = 0.8 * FRAC_1_PI * irradiance;
+        assert!(
+            (mean - expected).abs() < 0.05 * expected,
+            "{mean} {expected}"
+        );
+    }
+}
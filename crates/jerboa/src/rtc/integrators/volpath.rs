@@ -0,0 +1,328 @@
+use crate::rtc::{
+    aov::AovSample,
+    bsdfs::TransportMode,
+    integrators::{escaped_radiance, record_hit, spawn_ray, Integrator, SHADOW_EPSILON},
+    media::{HenyeyGreenstein, VACUUM},
+    ray::Ray,
+    sampler::Sampler,
+    scene::Scene,
+    IntersectRecord,
+};
+use glam::Vec3;
+
+/// Number of bounces after which paths are terminated with Russian roulette.
+const ROULETTE_DEPTH: u32 = 3;
+
+/// Path tracer accounting for participating media, with light samples at
+/// every scattering event, on surfaces and in media.
+///
+/// Shapes separating media without a material are crossed without counting
+/// as bounces. Lights at infinity are only reached through the media
+/// surrounding the camera if they are bounded, e.g. by a large sphere with
+/// vacuum outside.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct VolPathIntegrator {
+    /// Maximum number of bounces.
+    pub max_depth: u32,
+
+    /// Medium around the camera, [`VACUUM`] for none.
+    pub camera_medium: u32,
+}
+
+impl Default for VolPathIntegrator {
+    fn default() -> Self {
+        Self {
+            max_depth: 16,
+            camera_medium: VACUUM,
+        }
+    }
+}
+
+/// Point where light is scattered towards the previous vertex of a path.
+enum Scatterer<'a> {
+    Surface {
+        record: &'a IntersectRecord,
+        primitive: usize,
+    },
+    Medium {
+        p: Vec3,
+        time: f32,
+        phase: HenyeyGreenstein,
+    },
+}
+
+impl VolPathIntegrator {
+    pub fn new(max_depth: u32) -> Self {
+        Self {
+            max_depth,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_camera_medium(mut self, medium: u32) -> Self {
+        self.camera_medium = medium;
+        self
+    }
+
+    /// Radiance scattered at `scatterer` towards `wo` from a light chosen
+    /// uniformly, whose contribution is divided by its probability.
+    fn sample_light(
+        &self,
+        scene: &Scene,
+        scatterer: Scatterer,
+        wo: Vec3,
+        medium: u32,
+        sampler: &mut dyn Sampler,
+    ) -> Vec3 {
+        let lights = scene.lights();
+        if lights.is_empty() {
+            return Vec3::ZERO;
+        }
+        let choice = sampler.get_1d();
+        let light = &lights[((choice * lights.len() as f32) as usize).min(lights.len() - 1)];
+        let u = sampler.get_2d();
+        let (p, n) = match &scatterer {
+            Scatterer::Surface { record, .. } => (record.p, record.n),
+            Scatterer::Medium { p, .. } => (*p, Vec3::ZERO),
+        };
+        let sample = match light.sample_li(p, u) {
+            Some(sample) if sample.pdf > 0.0 && sample.li != Vec3::ZERO => sample,
+            _ => return Vec3::ZERO,
+        };
+        let (f, ray, medium) = match scatterer {
+            Scatterer::Surface { record, primitive } => {
+                let f = scene
+                    .material(primitive)
+                    .map_or(Vec3::ZERO, |bsdf| bsdf.f(wo, sample.wi, n))
+                    * sample.wi.dot(n).abs();
+                let medium = medium_after(scene, primitive, record, sample.wi, medium);
+                (f, spawn_ray(record, sample.wi), medium)
+            }
+            Scatterer::Medium { p, time, phase } => (
+                Vec3::splat(phase.p(wo, sample.wi)),
+                Ray::new(p, sample.wi).with_time(time),
+                medium,
+            ),
+        };
+        if f == Vec3::ZERO {
+            return Vec3::ZERO;
+        }
+        let tr = transmittance(
+            scene,
+            ray,
+            sample.distance * (1.0 - SHADOW_EPSILON),
+            medium,
+            sampler,
+        );
+        f * tr * sample.li * lights.len() as f32 / sample.pdf
+    }
+}
+
+/// Medium entered when leaving the surface of a primitive in direction `d`,
+/// coming from `medium`.
+fn medium_after(
+    scene: &Scene,
+    primitive: usize,
+    record: &IntersectRecord,
+    d: Vec3,
+    medium: u32,
+) -> u32 {
+    scene
+        .medium_interface(primitive)
+        .map_or(medium, |interface| interface.towards(record.n, d))
+}
+
+/// Transmittance along the ray up to `t_max`, starting in `medium`, through
+/// the boundaries of media; zero if a surface with a material blocks it.
+fn transmittance(
+    scene: &Scene,
+    mut ray: Ray,
+    mut t_max: f32,
+    mut medium: u32,
+    sampler: &mut dyn Sampler,
+) -> Vec3 {
+    let mut tr = Vec3::ONE;
+    loop {
+        let hit = scene.intersect(&ray).filter(|(_, record)| record.t < t_max);
+        let t = hit.as_ref().map_or(t_max, |(_, record)| record.t);
+        if let Some(m) = scene.medium(medium) {
+            tr *= m.tr(&ray, t, sampler);
+        }
+        let (primitive, record) = match hit {
+            Some(hit) => hit,
+            None => return tr,
+        };
+        if scene.material(primitive).is_some() || tr == Vec3::ZERO {
+            return Vec3::ZERO;
+        }
+        medium = medium_after(scene, primitive, &record, ray.d, medium);
+        ray = spawn_ray(&record, ray.d);
+        t_max -= record.t;
+    }
+}
+
+impl Integrator for VolPathIntegrator {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler, aov: &mut AovSample) -> Vec3 {
+        let mut l = Vec3::ZERO;
+        let mut beta = Vec3::ONE;
+        let mut ray = *ray;
+        let mut medium = self.camera_medium;
+        let mut specular_bounce = false;
+        let mut depth = 0;
+        let mut first_hit = true;
+        loop {
+            let hit = scene.intersect(&ray);
+            let t_max = hit.as_ref().map_or(f32::INFINITY, |(_, record)| record.t);
+            let mut interaction = None;
+            if let Some(m) = scene.medium(medium) {
+                let (sampled, weight) = m.sample(&ray, t_max, sampler);
+                beta *= weight;
+                interaction = sampled;
+            }
+            if beta == Vec3::ZERO {
+                break;
+            }
+
+            if let Some(mi) = interaction {
+                if depth >= self.max_depth {
+                    break;
+                }
+                let wo = mi.wo;
+                l += beta
+                    * self.sample_light(
+                        scene,
+                        Scatterer::Medium {
+                            p: mi.p,
+                            time: ray.time,
+                            phase: mi.phase,
+                        },
+                        wo,
+                        medium,
+                        sampler,
+                    );
+                let (wi, _) = mi.phase.sample_p(wo, sampler.get_2d());
+                // The weight of a phase function sample is one.
+                ray = Ray::new(mi.p, wi).with_time(ray.time);
+                specular_bounce = false;
+            } else {
+                let (primitive, record) = match hit {
+                    Some(hit) => hit,
+                    None => {
+                        if depth == 0 || specular_bounce {
+                            l += beta * escaped_radiance(&ray, scene);
+                        }
+                        break;
+                    }
+                };
+                let bsdf = match scene.material(primitive) {
+                    Some(bsdf) => bsdf,
+                    None if scene.medium_interface(primitive).is_some() => {
+                        // Boundary between media, crossed without scattering.
+                        medium = medium_after(scene, primitive, &record, ray.d, medium);
+                        ray = spawn_ray(&record, ray.d);
+                        continue;
+                    }
+                    None => break,
+                };
+                if first_hit {
+                    record_hit(aov, scene, primitive, &record);
+                    first_hit = false;
+                }
+                if depth >= self.max_depth {
+                    break;
+                }
+                let wo = -ray.d;
+                if !bsdf.is_specular() {
+                    let scatterer = Scatterer::Surface {
+                        record: &record,
+                        primitive,
+                    };
+                    l += beta * self.sample_light(scene, scatterer, wo, medium, sampler);
+                }
+                let sample =
+                    match bsdf.sample_f(wo, record.n, sampler.get_2d(), TransportMode::Radiance) {
+                        Some(sample) if sample.pdf > 0.0 && sample.f != Vec3::ZERO => sample,
+                        _ => break,
+                    };
+                beta *= sample.f * sample.wi.dot(record.n).abs() / sample.pdf;
+                specular_bounce = sample.specular;
+                medium = medium_after(scene, primitive, &record, sample.wi, medium);
+                ray = spawn_ray(&record, sample.wi);
+            }
+
+            depth += 1;
+            if depth > ROULETTE_DEPTH {
+                let q = (1.0 - beta.max_element()).max(0.05);
+                if sampler.get_1d() < q {
+                    break;
+                }
+                beta /= 1.0 - q;
+            }
+        }
+        l
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{image::PixelBuffer, Vec3 as Rgb},
+        rtc::{
+            lights::EnvironmentLight,
+            media::{HomogeneousMedium, MediumInterface},
+            sampler::IndependentSampler,
+            shapes::Sphere,
+        },
+    };
+
+    /// Scene lit by a uniform environment of unit radiance, with a sphere of
+    /// radius 2 filled with `medium`.
+    fn medium_sphere(medium: HomogeneousMedium) -> (Scene, u32) {
+        let mut map = PixelBuffer::new(16, 8);
+        map.fill(Rgb::splat(1.0));
+        let mut scene = Scene::new();
+        scene.add_light(EnvironmentLight::new(map));
+        let medium = scene.add_medium(medium);
+        scene.add_medium_boundary(
+            Sphere::new(Vec3::ZERO, 2.0),
+            MediumInterface::new(medium, VACUUM),
+        );
+        (scene, medium)
+    }
+
+    fn mean_li(integrator: &VolPathIntegrator, scene: &Scene, ray: &Ray, n: u32) -> Vec3 {
+        let mut sampler = IndependentSampler::new(0);
+        (0..n).fold(Vec3::ZERO, |l, i| {
+            sampler.start_pixel_sample((0, 0), i);
+            l + integrator.li(ray, scene, &mut sampler, &mut AovSample::default()) / n as f32
+        })
+    }
+
+    #[test]
+    fn absorption() {
+        let sigma_a = Vec3::new(0.1, 0.5, 1.0);
+        let (scene, medium) = medium_sphere(HomogeneousMedium::new(sigma_a, Vec3::ZERO, 0.0));
+        // From the centre of the sphere, and across it from outside. Paths
+        // absorbed at sampled distances make the estimate stochastic.
+        let integrator = VolPathIntegrator::default().with_camera_medium(medium);
+        let l = mean_li(&integrator, &scene, &Ray::new(Vec3::ZERO, Vec3::Y), 4096);
+        let expected = Vec3::new((-0.2f32).exp(), (-1.0f32).exp(), (-2.0f32).exp());
+        assert!(l.abs_diff_eq(expected, 0.05), "{l}");
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), -Vec3::Z);
+        let l = mean_li(&VolPathIntegrator::default(), &scene, &ray, 4096);
+        assert!(l.abs_diff_eq(expected * expected, 0.05), "{l}");
+    }
+
+    #[test]
+    fn scattering_furnace() {
+        // Without absorption, light is neither created nor lost.
+        for g in [0.0, 0.7] {
+            let medium = HomogeneousMedium::new(Vec3::ZERO, Vec3::splat(1.0), g);
+            let (scene, _) = medium_sphere(medium);
+            let ray = Ray::new(Vec3::new(0.5, 0.0, 5.0), -Vec3::Z);
+            let l = mean_li(&VolPathIntegrator::new(64), &scene, &ray, 4096);
+            assert!((l.x - 1.0).abs() < 0.03, "{l}");
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use crate::rtc::{
+    aov::AovSample,
+    integrators::{escaped_radiance, record_hit, spawn_ray, Integrator, SHADOW_EPSILON},
+    ray::Ray,
+    sampler::Sampler,
+    scene::Scene,
+    IntersectRecord,
+};
+use glam::Vec3;
+
+/// Whitted-style ray tracer: direct lighting from shadow rays towards the
+/// lights, and recursion along perfectly specular reflection and refraction.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WhittedIntegrator {
+    /// Maximum number of specular bounces.
+    pub max_depth: u32,
+}
+
+impl Default for WhittedIntegrator {
+    fn default() -> Self {
+        Self { max_depth: 5 }
+    }
+}
+
+impl WhittedIntegrator {
+    pub fn new(max_depth: u32) -> Self {
+        Self { max_depth }
+    }
+
+    fn trace(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler, depth: u32) -> Vec3 {
+        match scene.intersect(ray) {
+            Some((primitive, record)) => self.shade(ray, scene, sampler, primitive, &record, depth),
+            None => escaped_radiance(ray, scene),
+        }
+    }
+
+    fn shade(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        sampler: &mut dyn Sampler,
+        primitive: usize,
+        record: &IntersectRecord,
+        depth: u32,
+    ) -> Vec3 {
+        let bsdf = match scene.material(primitive) {
+            Some(bsdf) => bsdf,
+            None => return Vec3::ZERO,
+        };
+        let wo = -ray.d;
+        let mut l = Vec3::ZERO;
+        if !bsdf.is_specular() {
+            for light in scene.lights() {
+                let sample = match light.sample_li(record.p, sampler.get_2d()) {
+                    Some(sample) if sample.pdf > 0.0 && sample.li != Vec3::ZERO => sample,
+                    _ => continue,
+                };
+                let f = bsdf.f(wo, sample.wi, record.n) * sample.wi.dot(record.n).abs();
+                if f == Vec3::ZERO {
+                    continue;
+                }
+                let shadow_ray = spawn_ray(record, sample.wi);
+                if !scene.occluded(&shadow_ray, sample.distance * (1.0 - SHADOW_EPSILON)) {
+                    l += f * sample.li / sample.pdf;
+                }
+            }
+        }
+        if depth < self.max_depth {
+            for (wi, weight) in bsdf.specular_directions(wo, record.n) {
+                if weight != Vec3::ZERO {
+                    l += weight * self.trace(&spawn_ray(record, wi), scene, sampler, depth + 1);
+                }
+            }
+        }
+        l
+    }
+}
+
+impl Integrator for WhittedIntegrator {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler, aov: &mut AovSample) -> Vec3 {
+        match scene.intersect(ray) {
+            Some((primitive, record)) => {
+                record_hit(aov, scene, primitive, &record);
+                self.shade(ray, scene, sampler, primitive, &record, 0)
+            }
+            None => escaped_radiance(ray, scene),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{image::PixelBuffer, Vec3 as Rgb},
+        rtc::{
+            bsdfs::{Diffuse, Mirror},
+            lights::{EnvironmentLight, PointLight},
+            sampler::IndependentSampler,
+            shapes::Sphere,
+        },
+    };
+    use std::f32::consts::FRAC_1_PI;
+
+    /// Unit diffuse sphere lit by a point light above it, seen from a ray
+    /// hitting its top.
+    fn lit_sphere() -> (Scene, Ray) {
+        let mut scene = Scene::new();
+        let diffuse = scene.add_material(Diffuse::new(Vec3::splat(0.5)));
+        scene.add_shape_with_material(Sphere::new(Vec3::ZERO, 1.0), diffuse);
+        scene.add_light(PointLight::new(Vec3::new(0.0, 3.0, 0.0), Vec3::splat(4.0)));
+        let o = Vec3::new(-3.0, 4.0, 0.0);
+        (scene, Ray::new(o, (Vec3::Y - o).normalize()))
+    }
+
+    #[test]
+    fn shadow_rays() {
+        let (mut scene, ray) = lit_sphere();
+        let integrator = WhittedIntegrator::default();
+        let mut sampler = IndependentSampler::new(0);
+        let mut aov = AovSample::default();
+        let l = integrator.li(&ray, &scene, &mut sampler, &mut aov);
+        assert!(l.abs_diff_eq(Vec3::splat(0.5 * FRAC_1_PI), 1e-4));
+        assert_eq!(aov.primitive_id, 0);
+        assert_eq!(aov.material_id, 0);
+        assert_eq!(aov.albedo, Vec3::splat(0.5));
+        assert!(aov.normal.abs_diff_eq(Vec3::Y, 1e-5));
+
+        // Occluder between the light and the lit point, off the camera ray.
+        scene.add_shape(Sphere::new(Vec3::new(0.0, 2.0, 0.0), 0.3));
+        let l = integrator.li(&ray, &scene, &mut sampler, &mut AovSample::default());
+        assert_eq!(l, Vec3::ZERO);
+    }
+
+    #[test]
+    fn mirror_reflects_environment() {
+        let mut map = PixelBuffer::new(16, 8);
+        map.fill(Rgb::splat(1.0));
+        let mut scene = Scene::new();
+        scene.add_light(EnvironmentLight::new(map));
+        let mirror = scene.add_material(Mirror::new(Vec3::splat(0.8)));
+        scene.add_shape_with_material(Sphere::new(Vec3::ZERO, 1.0), mirror);
+        let ray = Ray::new(Vec3::new(0.5, 0.0, 5.0), -Vec3::Z);
+        let mut sampler = IndependentSampler::new(0);
+        let l =
+            WhittedIntegrator::default().li(&ray, &scene, &mut sampler, &mut AovSample::default());
+        assert!(l.abs_diff_eq(Vec3::splat(0.8), 1e-6));
+        let l = WhittedIntegrator::new(0).li(&ray, &scene, &mut sampler, &mut AovSample::default());
+        assert_eq!(l, Vec3::ZERO);
+    }
+}
@@ -0,0 +1,256 @@
+use crate::{
+    core::image::{error::ImageError, ImageBuffer, ImageDecoder, PixelBufferRgb32f},
+    rtc::{
+        lights::{Light, LightSample},
+        ray::Ray,
+        sampling::Distribution2D,
+    },
+};
+use glam::{Quat, Vec2, Vec3};
+use std::{
+    f32::consts::{PI, TAU},
+    path::Path,
+};
+
+/// Maps a normalised direction to equirectangular coordinates in `[0, 1]^2`.
+///
+/// The map is y-up: `v = 0` is the `+y` pole, `v = 1` the `-y` pole, and `u`
+/// grows with the azimuth measured from `+x` towards `+z`.
+pub fn direction_to_equirect(d: Vec3) -> Vec2 {
+    let theta = d.y.clamp(-1.0, 1.0).acos();
+    let phi = d.z.atan2(d.x).rem_euclid(TAU);
+    Vec2::new(phi / TAU, theta / PI)
+}
+
+/// Inverse of [`direction_to_equirect`].
+pub fn equirect_to_direction(uv: Vec2) -> Vec3 {
+    let (sin_theta, cos_theta) = (uv.y * PI).sin_cos();
+    let (sin_phi, cos_phi) = (uv.x * TAU).sin_cos();
+    Vec3::new(sin_theta * cos_phi, cos_theta, sin_theta * sin_phi)
+}
+
+/// Infinitely distant light surrounding the scene, with radiance given by an
+/// equirectangular image.
+///
+/// Radiance is piecewise constant over the pixels of the map, and directions
+/// are importance sampled proportionally to the luminance of each pixel
+/// weighted by its solid angle.
+#[derive(Clone)]
+pub struct EnvironmentLight {
+    map: PixelBufferRgb32f,
+    distribution: Distribution2D,
+
+    /// Rotation from the frame of the map to world space.
+    rotation: Quat,
+
+    /// Scale applied to the radiance of the map.
+    intensity: f32,
+}
+
+impl EnvironmentLight {
+    pub fn new(map: PixelBufferRgb32f) -> Self {
+        assert!(
+            map.width() > 0 && map.height() > 0,
+            "environment map is empty"
+        );
+        let (w, h) = map.dimensions();
+        let mut func = Vec::with_capacity((w * h) as usize);
+        for (y, row) in map.enumerate_rows() {
+            let sin_theta = ((y as f32 + 0.5) / h as f32 * PI).sin();
+            func.extend(row.map(|(_, p)| luminance(p) * sin_theta));
+        }
+        Self {
+            distribution: Distribution2D::new(&func, w as usize, h as usize),
+            map,
+            rotation: Quat::IDENTITY,
+            intensity: 1.0,
+        }
+    }
+
+    /// Loads an environment map with [`ImageDecoder`]. The image must hold
+    /// floating point samples; single channel images are used as grey.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ImageError> {
+        let map = match ImageDecoder::open(path)?.decode()? {
+            ImageBuffer::Rgb32F(map) => map,
+            ImageBuffer::Luma32F(map) => map.map(|p| crate::core::Vec3::splat(p[0])),
+            _ => {
+                return Err(ImageError::UnsupportedFormat(
+                    "environment maps must hold floating point samples".to_string(),
+                ))
+            }
+        };
+        Ok(Self::new(map))
+    }
+
+    /// Sets the rotation from the frame of the map to world space.
+    pub fn with_rotation(mut self, rotation: Quat) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Sets the scale applied to the radiance of the map.
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn map(&self) -> &PixelBufferRgb32f {
+        &self.map
+    }
+
+    /// Radiance of the map at equirectangular coordinates.
+    fn lookup(&self, uv: Vec2) -> Vec3 {
+        let (w, h) = self.map.dimensions();
+        let x = ((uv.x * w as f32) as u32).min(w - 1);
+        let y = ((uv.y * h as f32) as u32).min(h - 1);
+        let p = self.map.pixel_at(x, y).unwrap();
+        Vec3::new(p[0], p[1], p[2]) * self.intensity
+    }
+
+    /// Radiance arriving from the world space direction `wi`.
+    pub fn radiance(&self, wi: Vec3) -> Vec3 {
+        let local = self.rotation.inverse() * wi.normalize();
+        self.lookup(direction_to_equirect(local))
+    }
+}
+
+fn luminance(p: &crate::core::Vec3<f32>) -> f32 {
+    0.2126 * p[0] + 0.7152 * p[1] + 0.0722 * p[2]
+}
+
+impl Light for EnvironmentLight {
+    fn sample_li(&self, _p: Vec3, u: Vec2) -> Option<LightSample> {
+        let (uv, map_pdf) = self.distribution.sample_continuous(u);
+        if map_pdf == 0.0 {
+            return None;
+        }
+        let sin_theta = (uv.y * PI).sin();
+        if sin_theta == 0.0 {
+            return None;
+        }
+        let wi = self.rotation * equirect_to_direction(uv);
+        Some(LightSample {
+            wi,
+            li: self.lookup(uv),
+            // Change of variables from the unit square to the sphere.
+            pdf: map_pdf / (2.0 * PI * PI * sin_theta),
+            distance: f32::INFINITY,
+        })
+    }
+
+    fn pdf_li(&self, _p: Vec3, wi: Vec3) -> f32 {
+        let uv = direction_to_equirect(self.rotation.inverse() * wi.normalize());
+        let sin_theta = (uv.y * PI).sin();
+        if sin_theta == 0.0 {
+            return 0.0;
+        }
+        self.distribution.pdf(uv) / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn le(&self, ray: &Ray) -> Vec3 {
+        self.radiance(ray.d)
+    }
+
+    fn is_infinite(&self) -> bool {
+        true
+    }
+}
+
+impl From<PixelBufferRgb32f> for EnvironmentLight {
+    fn from(map: PixelBufferRgb32f) -> Self {
+        Self::new(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{image::PixelBuffer, Vec1, Vec3 as Rgb},
+        rtc::aabb::Aabb,
+    };
+
+    fn constant_map(value: f32) -> PixelBufferRgb32f {
+        let mut map = PixelBuffer::new(16, 8);
+        map.fill(Rgb::splat(value));
+        map
+    }
+
+    #[test]
+    fn equirect_roundtrip() {
+        for d in [
+            Vec3::new(0.3, 0.5, -0.8),
+            Vec3::new(-1.0, 0.2, 0.1),
+            Vec3::new(0.0, -0.4, 0.9),
+        ] {
+            let d = d.normalize();
+            let back = equirect_to_direction(direction_to_equirect(d));
+            assert!(back.abs_diff_eq(d, 1e-5));
+        }
+        assert!(direction_to_equirect(Vec3::Y).y.abs() < 1e-6);
+    }
+
+    #[test]
+    fn opens_float_maps() {
+        // Bright sky over a dark ground, stored as a grey PFM.
+        let samples = [[4.0; 4], [0.5; 4]].concat();
+        let map = PixelBuffer::<Vec1<f32>>::from_samples(4, 2, samples);
+        let path = std::env::temp_dir().join(format!("jerboa-env-{}.pfm", std::process::id()));
+        map.write_as_pfm(&path).unwrap();
+        let light = EnvironmentLight::open(&path);
+        std::fs::remove_file(&path).unwrap();
+        let light = light.unwrap();
+        assert_eq!(light.map().dimensions(), (4, 2));
+        assert_eq!(light.radiance(Vec3::Y), Vec3::splat(4.0));
+        assert_eq!(light.radiance(-Vec3::Y), Vec3::splat(0.5));
+    }
+
+    #[test]
+    fn constant_map_is_nearly_uniform() {
+        let light = EnvironmentLight::new(constant_map(2.0)).with_intensity(0.5);
+        let sample = light.sample_li(Vec3::ZERO, Vec2::new(0.3, 0.6)).unwrap();
+        assert_eq!(sample.li, Vec3::ONE);
+        // The piecewise-constant sine weighting is only approximately
+        // uniform over the sphere.
+        assert!((sample.pdf * 4.0 * PI - 1.0).abs() < 0.05);
+        assert!((light.pdf_li(Vec3::ZERO, sample.wi) - sample.pdf).abs() < 1e-4);
+    }
+
+    #[test]
+    fn samples_bright_pixels() {
+        let mut map = constant_map(0.0);
+        *map.pixel_at_mut(5, 2).unwrap() = Rgb::splat(100.0);
+        let rotation = Quat::from_rotation_y(1.0);
+        let light = EnvironmentLight::new(map).with_rotation(rotation);
+        for i in 0..8 {
+            let x = (i as f32 + 0.5) / 8.0;
+            let u = Vec2::new(x, 1.0 - x);
+            let sample = light.sample_li(Vec3::ZERO, u).unwrap();
+            assert_eq!(sample.li, Vec3::splat(100.0));
+            let ray = Ray::new(Vec3::ZERO, sample.wi);
+            assert_eq!(light.le(&ray), sample.li);
+            assert!((light.pdf_li(Vec3::ZERO, sample.wi) - sample.pdf).abs() < 1e-3 * sample.pdf);
+        }
+        assert_eq!(light.pdf_li(Vec3::ZERO, rotation * Vec3::X), 0.0);
+    }
+
+    #[test]
+    fn emission_enters_the_scene() {
+        let light = EnvironmentLight::new(constant_map(1.0));
+        let bounds = Aabb::new(Vec3::splat(-1.0), Vec3::splat(3.0));
+        let (centre, radius) = bounds.bounding_sphere();
+        let emission = light
+            .sample_le(Vec2::new(0.9, 0.2), Vec2::new(0.3, 0.6), &bounds)
+            .unwrap();
+        // The origin lies on the disk tangent to the bounding sphere, facing
+        // the scene.
+        let to_centre = centre - emission.ray.o;
+        assert!((to_centre.dot(emission.ray.d) - radius).abs() < 1e-4);
+        assert!(
+            (to_centre - to_centre.dot(emission.ray.d) * emission.ray.d).length() <= radius + 1e-4
+        );
+        let (pdf_pos, pdf_dir) = light.pdf_le(&emission.ray, &bounds);
+        assert!((pdf_pos - emission.pdf_pos).abs() < 1e-6);
+        assert!((pdf_dir - emission.pdf_dir).abs() < 1e-4 * pdf_dir);
+    }
+}
@@ -0,0 +1,116 @@
+//! Light sources.
+
+mod environment;
+mod point;
+mod sky;
+
+pub use environment::{direction_to_equirect, equirect_to_direction, EnvironmentLight};
+pub use point::PointLight;
+pub use sky::Sky;
+
+use crate::rtc::{
+    aabb::Aabb,
+    ray::Ray,
+    sampling::{concentric_sample_disk, coordinate_system},
+};
+use glam::{Vec2, Vec3};
+use std::f32::consts::PI;
+
+/// Incident radiance sampled from a light.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LightSample {
+    /// Direction towards the light, normalised.
+    pub wi: Vec3,
+
+    /// Radiance arriving along `wi`.
+    pub li: Vec3,
+
+    /// Density of `wi` with respect to solid angle.
+    pub pdf: f32,
+
+    /// Distance to the sampled point on the light; infinite for lights at
+    /// infinity.
+    pub distance: f32,
+}
+
+/// Ray of light leaving a light, to start light paths.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LightEmission {
+    pub ray: Ray,
+
+    /// Radiance carried by the ray.
+    pub le: Vec3,
+
+    /// Normal at the origin of the ray; zero for point lights.
+    pub n: Vec3,
+
+    /// Density of the origin with respect to area.
+    pub pdf_pos: f32,
+
+    /// Density of the direction with respect to solid angle.
+    pub pdf_dir: f32,
+}
+
+pub trait Light: Send + Sync {
+    /// Samples a direction from `p` towards the light with the random numbers
+    /// `u`. Returns `None` if no radiance can arrive from the sample.
+    fn sample_li(&self, p: Vec3, u: Vec2) -> Option<LightSample>;
+
+    /// Density with respect to solid angle of [`Light::sample_li`] returning
+    /// `wi` from `p`.
+    fn pdf_li(&self, p: Vec3, wi: Vec3) -> f32;
+
+    /// Radiance carried by a ray escaping the scene.
+    fn le(&self, _ray: &Ray) -> Vec3 {
+        Vec3::ZERO
+    }
+
+    /// Whether the light lies at infinity, so that escaping rays see it.
+    fn is_infinite(&self) -> bool {
+        false
+    }
+
+    /// Whether the light is described by a delta distribution, e.g. a point
+    /// light, so that it can only be reached by sampling it.
+    fn is_delta(&self) -> bool {
+        false
+    }
+
+    /// Samples a ray leaving the light, from the position and direction
+    /// random numbers `u_pos` and `u_dir`. Lights at infinity emit from a
+    /// disk facing the scene, whose bounds are `scene_bounds`, and derive
+    /// their direction from [`Light::sample_li`].
+    fn sample_le(&self, u_pos: Vec2, u_dir: Vec2, scene_bounds: &Aabb) -> Option<LightEmission> {
+        if !self.is_infinite() {
+            return None;
+        }
+        let (centre, radius) = scene_bounds.bounding_sphere();
+        if radius == 0.0 {
+            return None;
+        }
+        let sample = self.sample_li(centre, u_dir)?;
+        let (s, t) = coordinate_system(sample.wi);
+        let d = concentric_sample_disk(u_pos);
+        let o = centre + radius * (sample.wi + s * d.x + t * d.y);
+        Some(LightEmission {
+            ray: Ray::new(o, -sample.wi),
+            le: sample.li,
+            n: -sample.wi,
+            pdf_pos: 1.0 / (PI * radius * radius),
+            pdf_dir: sample.pdf,
+        })
+    }
+
+    /// Densities of the origin and direction of [`Light::sample_le`]
+    /// returning `ray`.
+    fn pdf_le(&self, ray: &Ray, scene_bounds: &Aabb) -> (f32, f32) {
+        if !self.is_infinite() {
+            return (0.0, 0.0);
+        }
+        let (centre, radius) = scene_bounds.bounding_sphere();
+        if radius == 0.0 {
+            return (0.0, 0.0);
+        }
+        (1.0 / (PI * radius * radius), self.pdf_li(centre, -ray.d))
+    }
+}
@@ -0,0 +1,87 @@
+use crate::rtc::{
+    aabb::Aabb,
+    lights::{Light, LightEmission, LightSample},
+    ray::Ray,
+    sampling::{uniform_sample_sphere, uniform_sphere_pdf},
+};
+use glam::{Vec2, Vec3};
+
+/// Isotropic point light.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PointLight {
+    pub position: Vec3,
+
+    /// Radiant intensity, in W/sr.
+    pub intensity: Vec3,
+}
+
+impl PointLight {
+    pub fn new(position: Vec3, intensity: Vec3) -> Self {
+        Self {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample_li(&self, p: Vec3, _u: Vec2) -> Option<LightSample> {
+        let to_light = self.position - p;
+        let distance_squared = to_light.length_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        Some(LightSample {
+            wi: to_light / distance,
+            li: self.intensity / distance_squared,
+            pdf: 1.0,
+            distance,
+        })
+    }
+
+    fn pdf_li(&self, _p: Vec3, _wi: Vec3) -> f32 {
+        0.0
+    }
+
+    fn is_delta(&self) -> bool {
+        true
+    }
+
+    fn sample_le(&self, _u_pos: Vec2, u_dir: Vec2, _scene_bounds: &Aabb) -> Option<LightEmission> {
+        Some(LightEmission {
+            ray: Ray::new(self.position, uniform_sample_sphere(u_dir)),
+            le: self.intensity,
+            n: Vec3::ZERO,
+            pdf_pos: 1.0,
+            pdf_dir: uniform_sphere_pdf(),
+        })
+    }
+
+    fn pdf_le(&self, _ray: &Ray, _scene_bounds: &Aabb) -> (f32, f32) {
+        (0.0, uniform_sphere_pdf())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_off_with_squared_distance() {
+        let light = PointLight::new(Vec3::new(0.0, 2.0, 0.0), Vec3::splat(8.0));
+        let sample = light.sample_li(Vec3::ZERO, Vec2::ZERO).unwrap();
+        assert_eq!(sample.wi, Vec3::Y);
+        assert_eq!(sample.li, Vec3::splat(2.0));
+        assert_eq!(sample.distance, 2.0);
+        assert_eq!(light.pdf_li(Vec3::ZERO, Vec3::Y), 0.0);
+        let emission = light
+            .sample_le(Vec2::ZERO, Vec2::new(0.2, 0.7), &Aabb::EMPTY)
+            .unwrap();
+        assert_eq!(emission.ray.o, light.position);
+        assert_eq!(
+            emission.pdf_dir,
+            light.pdf_le(&emission.ray, &Aabb::EMPTY).1
+        );
+    }
+}
@@ -0,0 +1,403 @@
+use crate::{
+    core::image::PixelBufferRgb32f,
+    rtc::{
+        lights::{
+            direction_to_equirect, equirect_to_direction, EnvironmentLight, Light, LightSample,
+        },
+        ray::Ray,
+        sampling::{
+            coordinate_system, uniform_cone_pdf, uniform_sample_cone, uniform_sample_sphere,
+            uniform_sphere_pdf,
+        },
+    },
+};
+use glam::{Vec2, Vec3};
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+/// Angular radius of the sun seen from the ground, in radians.
+const SUN_ANGULAR_RADIUS: f32 = 0.004_65;
+
+/// Luminance of the sun outside of the atmosphere, in kcd/m².
+const SUN_LUMINANCE: f32 = 1.6e6;
+
+/// Probability of sampling the sun disk rather than the whole sphere.
+const SUN_SAMPLING_PROBABILITY: f32 = 0.5;
+
+/// Wavelengths, in micrometres, at which the sun transmittance is evaluated
+/// for the red, green and blue channels.
+const RGB_WAVELENGTHS: [f32; 3] = [0.68, 0.55, 0.44];
+
+/// Coefficients of the Perez distribution function.
+#[derive(Debug, Copy, Clone)]
+struct Perez([f32; 5]);
+
+impl Perez {
+    fn eval(&self, cos_theta: f32, gamma: f32) -> f32 {
+        let [a, b, c, d, e] = self.0;
+        let cos_gamma = gamma.cos();
+        (1.0 + a * (b / cos_theta.max(0.01)).exp())
+            * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+    }
+}
+
+/// Analytic daylight model of Preetham et al. 1999, with a sun disk and a
+/// diffuse ground below the horizon.
+///
+/// The sky is y-up, matching [`direction_to_equirect`]. Radiance is given in
+/// kcd/m² for the luminance, converted to linear sRGB. The model is fitted
+/// for turbidities between 2 (very clear) and 10 (hazy) and for suns above
+/// the horizon; lower suns are clamped to the horizon, towards +x for a sun
+/// straight below.
+#[derive(Debug, Clone)]
+pub struct Sky {
+    sun_direction: Vec3,
+    turbidity: f32,
+    ground_albedo: Vec3,
+    intensity: f32,
+
+    /// Zenith values and Perez coefficients of the luminance `Y` and the
+    /// chromaticities `x` and `y`, in that order.
+    zenith: [f32; 3],
+    perez: [Perez; 3],
+
+    /// Radiance of the sun disk, attenuated by the atmosphere.
+    sun_radiance: Vec3,
+
+    /// Irradiance of a horizontal surface lit by the sky and the sun.
+    horizontal_irradiance: Vec3,
+}
+
+impl Sky {
+    /// Creates a sky lit by the sun in the direction `sun_direction` with the
+    /// given atmospheric turbidity. The ground albedo defaults to 0.3.
+    pub fn new(sun_direction: Vec3, turbidity: f32) -> Self {
+        let mut sun_direction = sun_direction.normalize();
+        if sun_direction.y < 0.0 {
+            sun_direction = Vec3::new(sun_direction.x, 0.0, sun_direction.z)
+                .try_normalize()
+                .unwrap_or(Vec3::X);
+        }
+        let theta_s = sun_direction.y.clamp(-1.0, 1.0).acos();
+        let t = turbidity;
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_y = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let cubic = |c: [f32; 4]| {
+            c[0] * theta_s * theta_s * theta_s + c[1] * theta_s * theta_s + c[2] * theta_s + c[3]
+        };
+        let zenith_x = t * t * cubic([0.00166, -0.00375, 0.00209, 0.0])
+            + t * cubic([-0.02903, 0.06377, -0.03202, 0.00394])
+            + cubic([0.11693, -0.21196, 0.06052, 0.25886]);
+        let zenith_yc = t * t * cubic([0.00275, -0.00610, 0.00317, 0.0])
+            + t * cubic([-0.04214, 0.08970, -0.04153, 0.00516])
+            + cubic([0.15346, -0.26756, 0.06670, 0.26688]);
+
+        let perez = [
+            Perez([
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ]),
+            Perez([
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ]),
+            Perez([
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ]),
+        ];
+
+        let mut sky = Sky {
+            sun_direction,
+            turbidity,
+            ground_albedo: Vec3::splat(0.3),
+            intensity: 1.0,
+            // Normalised so that the Perez function evaluates to the zenith
+            // values straight up.
+            zenith: [
+                zenith_y.max(0.0) / perez[0].eval(1.0, theta_s),
+                zenith_x / perez[1].eval(1.0, theta_s),
+                zenith_yc / perez[2].eval(1.0, theta_s),
+            ],
+            perez,
+            sun_radiance: sun_transmittance(theta_s, turbidity) * SUN_LUMINANCE,
+            horizontal_irradiance: Vec3::ZERO,
+        };
+        sky.horizontal_irradiance = sky.compute_horizontal_irradiance();
+        sky
+    }
+
+    /// Sets the albedo of the diffuse ground seen below the horizon.
+    pub fn with_ground_albedo(mut self, albedo: Vec3) -> Self {
+        self.ground_albedo = albedo;
+        self
+    }
+
+    /// Sets the scale applied to all radiance values.
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn sun_direction(&self) -> Vec3 {
+        self.sun_direction
+    }
+
+    pub fn turbidity(&self) -> f32 {
+        self.turbidity
+    }
+
+    pub fn ground_albedo(&self) -> Vec3 {
+        self.ground_albedo
+    }
+
+    /// Radiance of the sun disk.
+    pub fn sun_radiance(&self) -> Vec3 {
+        self.sun_radiance * self.intensity
+    }
+
+    /// Cosine of the angular radius of the sun disk.
+    fn sun_cos_max(&self) -> f32 {
+        SUN_ANGULAR_RADIUS.cos()
+    }
+
+    /// Radiance of the sky and ground, without the sun disk, not scaled by
+    /// the intensity.
+    fn sky_radiance(&self, wi: Vec3) -> Vec3 {
+        if wi.y <= 0.0 {
+            return self.ground_albedo * self.horizontal_irradiance / PI;
+        }
+        let gamma = wi.dot(self.sun_direction).clamp(-1.0, 1.0).acos();
+        let luminance = self.zenith[0] * self.perez[0].eval(wi.y, gamma);
+        let x = self.zenith[1] * self.perez[1].eval(wi.y, gamma);
+        let y = self.zenith[2] * self.perez[2].eval(wi.y, gamma);
+        xyy_to_rgb(x, y, luminance)
+    }
+
+    /// Radiance arriving from the direction `wi`, including the sun disk.
+    pub fn radiance(&self, wi: Vec3) -> Vec3 {
+        let wi = wi.normalize();
+        let mut l = self.sky_radiance(wi);
+        if wi.dot(self.sun_direction) >= self.sun_cos_max() {
+            l += self.sun_radiance;
+        }
+        l * self.intensity
+    }
+
+    /// Integrates the light reaching an upward facing surface, used to shade
+    /// the ground.
+    fn compute_horizontal_irradiance(&self) -> Vec3 {
+        const N_THETA: usize = 64;
+        const N_PHI: usize = 128;
+        let d_theta = FRAC_PI_2 / N_THETA as f32;
+        let d_phi = TAU / N_PHI as f32;
+        let mut irradiance = Vec3::ZERO;
+        for i in 0..N_THETA {
+            let theta = (i as f32 + 0.5) * d_theta;
+            let (sin_theta, cos_theta) = theta.sin_cos();
+            for j in 0..N_PHI {
+                let phi = (j as f32 + 0.5) * d_phi;
+                let wi = Vec3::new(sin_theta * phi.cos(), cos_theta, sin_theta * phi.sin());
+                irradiance += self.sky_radiance(wi) * cos_theta * sin_theta * d_theta * d_phi;
+            }
+        }
+        let sun_solid_angle = TAU * (1.0 - self.sun_cos_max());
+        irradiance + self.sun_radiance * sun_solid_angle * self.sun_direction.y.max(0.0)
+    }
+
+    /// Bakes the sky into an equirectangular map. The energy of the sun disk
+    /// is spread over the pixel containing its centre.
+    pub fn bake(&self, width: u32, height: u32) -> PixelBufferRgb32f {
+        let mut map = PixelBufferRgb32f::new(width, height);
+        for ((x, y), pixel) in map.pixels_mut() {
+            let uv = Vec2::new(
+                (x as f32 + 0.5) / width as f32,
+                (y as f32 + 0.5) / height as f32,
+            );
+            let l = self.sky_radiance(equirect_to_direction(uv)) * self.intensity;
+            *pixel = crate::core::Vec3::from(l.to_array());
+        }
+
+        let uv = direction_to_equirect(self.sun_direction);
+        let x = ((uv.x * width as f32) as u32).min(width - 1);
+        let y = ((uv.y * height as f32) as u32).min(height - 1);
+        let sin_theta = ((y as f32 + 0.5) / height as f32 * PI).sin();
+        let pixel_solid_angle = TAU / width as f32 * PI / height as f32 * sin_theta;
+        let sun_solid_angle = TAU * (1.0 - self.sun_cos_max());
+        let sun = self.sun_radiance() * sun_solid_angle / pixel_solid_angle;
+        *map.pixel_at_mut(x, y).unwrap() += crate::core::Vec3::from(sun.to_array());
+        map
+    }
+
+    /// Bakes the sky into an importance sampled environment light.
+    pub fn to_environment_light(&self, width: u32, height: u32) -> EnvironmentLight {
+        EnvironmentLight::new(self.bake(width, height))
+    }
+}
+
+/// Converts CIE xyY to linear sRGB, clamping out of gamut values.
+fn xyy_to_rgb(x: f32, y: f32, luminance: f32) -> Vec3 {
+    if y <= 0.0 {
+        return Vec3::ZERO;
+    }
+    let cx = x / y * luminance;
+    let cz = (1.0 - x - y) / y * luminance;
+    Vec3::new(
+        3.2406 * cx - 1.5372 * luminance - 0.4986 * cz,
+        -0.9689 * cx + 1.8758 * luminance + 0.0415 * cz,
+        0.0557 * cx - 0.2040 * luminance + 1.0570 * cz,
+    )
+    .max(Vec3::ZERO)
+}
+
+/// Fraction of the sunlight crossing the atmosphere through Rayleigh and
+/// aerosol scattering (Preetham et al. 1999, appendix A.2), per channel.
+fn sun_transmittance(theta_s: f32, turbidity: f32) -> Vec3 {
+    // Relative optical mass of the atmosphere along the sun direction.
+    let m = 1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+    let beta = 0.04608 * turbidity - 0.04586;
+    let alpha = 1.3;
+    let tau = |lambda: f32| {
+        let rayleigh = (-0.008735 * lambda.powf(-4.08) * m).exp();
+        let aerosol = (-beta * lambda.powf(-alpha) * m).exp();
+        rayleigh * aerosol
+    };
+    Vec3::from(RGB_WAVELENGTHS.map(tau))
+}
+
+impl Light for Sky {
+    fn sample_li(&self, _p: Vec3, u: Vec2) -> Option<LightSample> {
+        let cos_max = self.sun_cos_max();
+        let wi = if u.x < SUN_SAMPLING_PROBABILITY {
+            let u = Vec2::new(u.x / SUN_SAMPLING_PROBABILITY, u.y);
+            let (s, t) = coordinate_system(self.sun_direction);
+            let d = uniform_sample_cone(u, cos_max);
+            s * d.x + t * d.y + self.sun_direction * d.z
+        } else {
+            let u = Vec2::new(
+                (u.x - SUN_SAMPLING_PROBABILITY) / (1.0 - SUN_SAMPLING_PROBABILITY),
+                u.y,
+            );
+            uniform_sample_sphere(u)
+        };
+        let pdf = self.pdf_li(Vec3::ZERO, wi);
+        Some(LightSample {
+            wi,
+            li: self.radiance(wi),
+            pdf,
+            distance: f32::INFINITY,
+        })
+    }
+
+    fn pdf_li(&self, _p: Vec3, wi: Vec3) -> f32 {
+        let cos_max = self.sun_cos_max();
+        let mut pdf = (1.0 - SUN_SAMPLING_PROBABILITY) * uniform_sphere_pdf();
+        if wi.normalize().dot(self.sun_direction) >= cos_max {
+            pdf += SUN_SAMPLING_PROBABILITY * uniform_cone_pdf(cos_max);
+        }
+        pdf
+    }
+
+    fn le(&self, ray: &Ray) -> Vec3 {
+        self.radiance(ray.d)
+    }
+
+    fn is_infinite(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sun_at(elevation_deg: f32) -> Vec3 {
+        let e = elevation_deg.to_radians();
+        Vec3::new(e.cos(), e.sin(), 0.0)
+    }
+
+    #[test]
+    fn zenith_luminance_is_plausible() {
+        let sky = Sky::new(sun_at(45.0), 3.0);
+        let zenith = sky.radiance(Vec3::Y);
+        let luminance = 0.2126 * zenith.x + 0.7152 * zenith.y + 0.0722 * zenith.z;
+        // Clear skies have a zenith luminance of a few kcd/m².
+        assert!((1.0..20.0).contains(&luminance), "{}", luminance);
+        // Clear skies are blue.
+        assert!(zenith.z > zenith.x);
+        assert!(sky.sun_radiance().min_element() > 1e3 * zenith.max_element());
+    }
+
+    #[test]
+    fn low_sun_is_redder() {
+        let ratio = |elevation| {
+            let sun = Sky::new(sun_at(elevation), 3.0).sun_radiance();
+            sun.x / sun.z
+        };
+        assert!(ratio(5.0) > ratio(60.0));
+    }
+
+    #[test]
+    fn low_suns_are_clamped_to_the_horizon() {
+        let sky = Sky::new(Vec3::new(0.0, -1.0, 2.0), 3.0);
+        assert_eq!(sky.sun_direction(), Vec3::Z);
+        // Straight below, the horizon direction is arbitrary but defined.
+        let sky = Sky::new(-Vec3::Y, 3.0);
+        assert_eq!(sky.sun_direction(), Vec3::X);
+        assert!(sky.sun_radiance().is_finite());
+        for wi in [
+            Vec3::Y,
+            Vec3::X,
+            -Vec3::Z,
+            Vec3::new(0.3, 0.2, 0.9).normalize(),
+        ] {
+            assert!(sky.radiance(wi).is_finite());
+        }
+    }
+
+    #[test]
+    fn ground_reflects_albedo() {
+        let sky = Sky::new(sun_at(30.0), 4.0).with_ground_albedo(Vec3::ZERO);
+        assert_eq!(sky.radiance(-Vec3::Y), Vec3::ZERO);
+        let sky = sky.with_ground_albedo(Vec3::ONE);
+        assert!(sky.radiance(-Vec3::Y).min_element() > 0.0);
+    }
+
+    #[test]
+    fn sampling_pdf_is_consistent() {
+        let sky = Sky::new(sun_at(20.0), 2.5).with_intensity(1e-3);
+        for i in 0..16 {
+            let u = Vec2::new((i as f32 + 0.5) / 16.0, (i * 3 % 16) as f32 / 16.0);
+            let sample = sky.sample_li(Vec3::ZERO, u).unwrap();
+            assert!((sky.pdf_li(Vec3::ZERO, sample.wi) - sample.pdf).abs() <= 1e-3 * sample.pdf);
+            if u.x < SUN_SAMPLING_PROBABILITY {
+                assert!(sample.li.cmpge(sky.sun_radiance()).all());
+            }
+        }
+    }
+
+    #[test]
+    fn baked_map_preserves_irradiance() {
+        let sky = Sky::new(sun_at(40.0), 3.0);
+        let map = sky.bake(256, 128);
+        let (w, h) = map.dimensions();
+        let mut irradiance = Vec3::ZERO;
+        for ((x, y), p) in map.pixels() {
+            let uv = Vec2::new((x as f32 + 0.5) / w as f32, (y as f32 + 0.5) / h as f32);
+            let wi = equirect_to_direction(uv);
+            let solid_angle = TAU / w as f32 * PI / h as f32 * (uv.y * PI).sin();
+            irradiance += Vec3::new(p[0], p[1], p[2]) * wi.y.max(0.0) * solid_angle;
+        }
+        let expected = sky.horizontal_irradiance;
+        assert!((irradiance - expected).abs().max_element() < 0.05 * expected.max_element());
+    }
+}
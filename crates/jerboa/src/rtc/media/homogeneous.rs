@@ -0,0 +1,117 @@
+use crate::rtc::{
+    media::{HenyeyGreenstein, Medium, MediumInteraction},
+    ray::Ray,
+    sampler::Sampler,
+};
+use glam::Vec3;
+
+/// Medium of constant absorption and scattering coefficients, e.g. fog.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HomogeneousMedium {
+    /// Absorption coefficient, per unit distance.
+    pub sigma_a: Vec3,
+
+    /// Scattering coefficient, per unit distance.
+    pub sigma_s: Vec3,
+
+    pub phase: HenyeyGreenstein,
+}
+
+impl HomogeneousMedium {
+    pub fn new(sigma_a: Vec3, sigma_s: Vec3, g: f32) -> Self {
+        Self {
+            sigma_a,
+            sigma_s,
+            phase: HenyeyGreenstein::new(g),
+        }
+    }
+
+    fn sigma_t(&self) -> Vec3 {
+        self.sigma_a + self.sigma_s
+    }
+}
+
+/// `exp(-sigma_t t)`, without the NaN of an infinite distance in a channel
+/// that does not attenuate.
+fn transmittance(sigma_t: Vec3, t: f32) -> Vec3 {
+    let tr = |sigma: f32| {
+        if sigma == 0.0 {
+            1.0
+        } else {
+            (-sigma * t).exp()
+        }
+    };
+    Vec3::new(tr(sigma_t.x), tr(sigma_t.y), tr(sigma_t.z))
+}
+
+impl Medium for HomogeneousMedium {
+    fn tr(&self, _ray: &Ray, t_max: f32, _sampler: &mut dyn Sampler) -> Vec3 {
+        transmittance(self.sigma_t(), t_max)
+    }
+
+    fn sample(
+        &self,
+        ray: &Ray,
+        t_max: f32,
+        sampler: &mut dyn Sampler,
+    ) -> (Option<MediumInteraction>, Vec3) {
+        let sigma_t = self.sigma_t();
+        // Distances are sampled in a channel chosen uniformly, the density
+        // being the average over the channels.
+        let channel = ((sampler.get_1d() * 3.0) as usize).min(2);
+        let u = sampler.get_1d();
+        let t = if sigma_t[channel] > 0.0 {
+            -(1.0 - u).ln() / sigma_t[channel]
+        } else {
+            f32::INFINITY
+        };
+        if t < t_max {
+            let tr = transmittance(sigma_t, t);
+            let pdf = (sigma_t * tr).dot(Vec3::splat(1.0 / 3.0));
+            let interaction = MediumInteraction {
+                t,
+                p: ray.o + ray.d * t,
+                wo: -ray.d,
+                phase: self.phase,
+            };
+            (Some(interaction), tr * self.sigma_s / pdf)
+        } else {
+            let tr = transmittance(sigma_t, t_max);
+            let pdf = tr.dot(Vec3::splat(1.0 / 3.0));
+            let weight = if pdf > 0.0 { tr / pdf } else { Vec3::ZERO };
+            (None, weight)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::sampler::IndependentSampler;
+
+    #[test]
+    fn sampled_distances_follow_transmittance() {
+        let medium = HomogeneousMedium::new(Vec3::splat(0.2), Vec3::splat(0.3), 0.0);
+        let ray = Ray::new(Vec3::ZERO, Vec3::X);
+        let mut sampler = IndependentSampler::new(0);
+        sampler.start_pixel_sample((0, 0), 0);
+        let tr = medium.tr(&ray, 2.0, &mut sampler);
+        assert!(tr.abs_diff_eq(Vec3::splat((-1.0f32).exp()), 1e-6));
+
+        let n = 10000;
+        let (mut n_scattered, mut weight) = (0, Vec3::ZERO);
+        for _ in 0..n {
+            let (interaction, w) = medium.sample(&ray, 2.0, &mut sampler);
+            if let Some(interaction) = interaction {
+                assert!(interaction.t < 2.0 && interaction.p.x == interaction.t);
+                n_scattered += 1;
+            }
+            weight += w / n as f32;
+        }
+        let scattered = n_scattered as f32 / n as f32;
+        assert!((scattered - (1.0 - tr.x)).abs() < 0.02);
+        // Events are weighted by the single scattering albedo, the others
+        // are not.
+        assert!(weight.abs_diff_eq(Vec3::splat(0.6 * scattered + 1.0 - scattered), 1e-3));
+    }
+}
@@ -0,0 +1,155 @@
+//! Participating media, scattering and absorbing light between surfaces.
+//!
+//! Media fill the space on either side of the shapes carrying a
+//! [`MediumInterface`]; rays keep their medium when crossing other shapes.
+//! Distances along rays are measured in units of their direction, which
+//! must be normalised.
+
+mod grid;
+mod homogeneous;
+
+pub use grid::GridMedium;
+pub use homogeneous::HomogeneousMedium;
+
+use crate::rtc::{ray::Ray, sampler::Sampler, sampling::coordinate_system};
+use glam::{Vec2, Vec3};
+use std::f32::consts::{FRAC_1_PI, TAU};
+
+/// Medium index standing for vacuum, where rays travel unaffected.
+pub const VACUUM: u32 = u32::MAX;
+
+/// Henyey–Greenstein phase function, with `wo` and `wi` both pointing away
+/// from the scattering point.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HenyeyGreenstein {
+    /// Mean cosine of the scattering angle, in `(-1, 1)`: positive values
+    /// scatter forward, negative ones backward, zero is isotropic.
+    pub g: f32,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(g: f32) -> Self {
+        Self {
+            g: g.clamp(-0.999, 0.999),
+        }
+    }
+
+    /// Density of scattering light travelling along `-wi` into `wo`, with
+    /// respect to solid angle.
+    pub fn p(&self, wo: Vec3, wi: Vec3) -> f32 {
+        henyey_greenstein(wo.dot(wi), self.g)
+    }
+
+    /// Samples `wi` proportionally to the phase function, returning it with
+    /// its density.
+    pub fn sample_p(&self, wo: Vec3, u: Vec2) -> (Vec3, f32) {
+        let g = self.g;
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u.x
+        } else {
+            let sq = (1.0 - g * g) / (1.0 + g - 2.0 * g * u.x);
+            -(1.0 + g * g - sq * sq) / (2.0 * g)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let (sin_phi, cos_phi) = (TAU * u.y).sin_cos();
+        // The angle is measured from `wo`, backward scattering for positive
+        // cosines.
+        let (s, t) = coordinate_system(wo);
+        let wi = (s * cos_phi + t * sin_phi) * sin_theta + wo * cos_theta;
+        (wi, henyey_greenstein(cos_theta, g))
+    }
+}
+
+/// Henyey–Greenstein distribution for `cos_theta` the cosine between the
+/// outgoing and incident directions, both pointing away.
+fn henyey_greenstein(cos_theta: f32, g: f32) -> f32 {
+    let denom = 1.0 + g * g + 2.0 * g * cos_theta;
+    0.25 * FRAC_1_PI * (1.0 - g * g) / (denom * denom.max(0.0).sqrt())
+}
+
+/// Scattering event sampled inside a medium.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MediumInteraction {
+    /// Distance along the ray.
+    pub t: f32,
+    pub p: Vec3,
+
+    /// Direction back along the ray.
+    pub wo: Vec3,
+
+    pub phase: HenyeyGreenstein,
+}
+
+pub trait Medium: Send + Sync {
+    /// Transmittance along the ray between its origin and `t_max`, possibly
+    /// estimated stochastically.
+    fn tr(&self, ray: &Ray, t_max: f32, sampler: &mut dyn Sampler) -> Vec3;
+
+    /// Samples a distance along the ray proportionally to the transmittance,
+    /// returning the scattering event if it lies before `t_max`, with the
+    /// weight by which the path throughput is multiplied: the transmittance
+    /// (times the scattering coefficient for events) over the density of
+    /// the sample.
+    fn sample(
+        &self,
+        ray: &Ray,
+        t_max: f32,
+        sampler: &mut dyn Sampler,
+    ) -> (Option<MediumInteraction>, Vec3);
+}
+
+/// Media on both sides of a shape, as indices into the media of the scene;
+/// [`VACUUM`] stands for no medium. The outside is the side the normal
+/// points to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MediumInterface {
+    pub inside: u32,
+    pub outside: u32,
+}
+
+impl Default for MediumInterface {
+    fn default() -> Self {
+        Self::new(VACUUM, VACUUM)
+    }
+}
+
+impl MediumInterface {
+    pub fn new(inside: u32, outside: u32) -> Self {
+        Self { inside, outside }
+    }
+
+    /// Medium entered when leaving a surface of normal `n` in direction `d`.
+    pub fn towards(&self, n: Vec3, d: Vec3) -> u32 {
+        if d.dot(n) > 0.0 {
+            self.outside
+        } else {
+            self.inside
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::sampler::{IndependentSampler, Sampler};
+
+    #[test]
+    fn henyey_greenstein_sampling() {
+        let mut sampler = IndependentSampler::new(3);
+        sampler.start_pixel_sample((0, 0), 0);
+        let wo = Vec3::new(0.3, 0.4, -0.5).normalize();
+        for g in [-0.6, 0.0, 0.8] {
+            let phase = HenyeyGreenstein::new(g);
+            let n = 20000;
+            let mut mean_cos = 0.0;
+            for _ in 0..n {
+                let (wi, pdf) = phase.sample_p(wo, sampler.get_2d());
+                assert!((wi.length() - 1.0).abs() < 1e-4);
+                assert!((pdf - phase.p(wo, wi)).abs() < 1e-3 * pdf.max(1.0));
+                mean_cos += (-wo).dot(wi) / n as f32;
+            }
+            // The mean cosine with the direction of propagation is `g`.
+            assert!((mean_cos - g).abs() < 0.02);
+        }
+    }
+}
@@ -1,17 +1,64 @@
-mod integrators;
-mod ray;
-mod shapes;
+pub mod aabb;
+pub mod aov;
+pub mod bsdfs;
+pub mod bvh;
+pub mod camera;
+pub mod film;
+pub mod filters;
+pub mod integrators;
+pub mod lights;
+pub mod media;
+pub mod photon_map;
+pub mod progressive;
+pub mod ray;
+pub mod sampler;
+pub mod sampling;
+pub mod scene;
+pub mod shapes;
+pub mod textures;
+pub mod transform;
 
-use crate::rtc::ray::Ray;
-use glam::Vec3;
+use crate::rtc::{aabb::Aabb, ray::Ray};
+use glam::{Vec2, Vec3};
 
 pub struct IntersectRecord {
     pub t: f32,
     pub p: Vec3,
+
+    /// Bound on the absolute error of `p` in each dimension: the surface
+    /// passes through the box of this half extent around `p`.
+    pub p_error: Vec3,
     pub n: Vec3,
+
+    /// Surface coordinates of the hit. For triangles, barycentric
+    /// coordinates of the second and third vertices.
+    pub uv: Vec2,
+
+    /// Time of the ray, given to the rays leaving the hit.
+    pub time: f32,
 }
 
-pub trait Shape {
+pub trait Shape: Send + Sync {
     fn intersect_p(&self, ray: &Ray) -> bool;
     fn intersect(&self, ray: &Ray) -> Option<IntersectRecord>;
+
+    /// World space bounding box; infinite for unbounded shapes.
+    fn bounds(&self) -> Aabb;
+}
+
+/// Point sampled on the surface of a shape.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SurfaceSample {
+    pub p: Vec3,
+    pub n: Vec3,
+    pub uv: Vec2,
+}
+
+/// Shape of finite area whose surface can be sampled, e.g. to emit light.
+pub trait SampleableShape: Shape {
+    fn area(&self) -> f32;
+
+    /// Samples a point uniformly over the surface, i.e. with a density of
+    /// the inverse of the area.
+    fn sample(&self, u: Vec2) -> SurfaceSample;
 }
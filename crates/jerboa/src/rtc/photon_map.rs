@@ -0,0 +1,151 @@
+//! Photons deposited on surfaces, and a hashed grid to gather them.
+//!
+//! The grid has cells of the size of the largest gathering radius, hashed
+//! into as many buckets as there are photons. Photons are sorted by bucket,
+//! so that a query only reads the buckets of the cells overlapping its
+//! sphere. Distinct cells sharing a bucket are told apart by the distance
+//! test.
+
+use crate::rtc::sampler::mix_bits;
+use glam::{IVec3, Vec3};
+
+/// Light flux arriving at a surface point.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Photon {
+    pub p: Vec3,
+
+    /// Direction the photon came from, pointing away from the surface.
+    pub wi: Vec3,
+
+    /// Flux carried by the photon.
+    pub power: Vec3,
+}
+
+#[derive(Debug, Clone)]
+pub struct PhotonMap {
+    photons: Vec<Photon>,
+    cell_size: f32,
+
+    /// Index of the first photon of each bucket, plus the total number of
+    /// photons at the end.
+    offsets: Vec<u32>,
+}
+
+impl PhotonMap {
+    /// Builds the map for gathering radii up to `max_radius`.
+    pub fn new(mut photons: Vec<Photon>, max_radius: f32) -> Self {
+        let cell_size = max_radius.max(f32::MIN_POSITIVE);
+        let n_buckets = photons.len().max(1).next_power_of_two();
+        let bucket_of = |p: Vec3| bucket(cell(p, cell_size), n_buckets);
+
+        let mut offsets = vec![0u32; n_buckets + 1];
+        for photon in &photons {
+            offsets[bucket_of(photon.p) + 1] += 1;
+        }
+        for i in 1..offsets.len() {
+            offsets[i] += offsets[i - 1];
+        }
+        photons.sort_by_cached_key(|photon| bucket_of(photon.p));
+        Self {
+            photons,
+            cell_size,
+            offsets,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.photons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.photons.is_empty()
+    }
+
+    pub fn photons(&self) -> &[Photon] {
+        &self.photons
+    }
+
+    /// Calls `f` on every photon closer than `radius` to `p`. The radius
+    /// should not exceed the one the map was built for, or queries visit
+    /// many cells.
+    pub fn for_each_within<F: FnMut(&Photon)>(&self, p: Vec3, radius: f32, mut f: F) {
+        if self.photons.is_empty() {
+            return;
+        }
+        let n_buckets = self.offsets.len() - 1;
+        let (lo, hi) = (
+            cell(p - Vec3::splat(radius), self.cell_size),
+            cell(p + Vec3::splat(radius), self.cell_size),
+        );
+        let radius_squared = radius * radius;
+        // Buckets already read, as neighbouring cells can share a bucket.
+        let mut visited = Vec::with_capacity(27);
+        for z in lo.z..=hi.z {
+            for y in lo.y..=hi.y {
+                for x in lo.x..=hi.x {
+                    let b = bucket(IVec3::new(x, y, z), n_buckets);
+                    if visited.contains(&b) {
+                        continue;
+                    }
+                    visited.push(b);
+                    let range = self.offsets[b] as usize..self.offsets[b + 1] as usize;
+                    for photon in &self.photons[range] {
+                        if photon.p.distance_squared(p) < radius_squared {
+                            f(photon);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn cell(p: Vec3, cell_size: f32) -> IVec3 {
+    (p / cell_size).floor().as_ivec3()
+}
+
+/// Bucket of a cell, `n_buckets` being a power of two.
+fn bucket(cell: IVec3, n_buckets: usize) -> usize {
+    let c = cell.as_uvec3();
+    let key = (c.x as u64) ^ ((c.y as u64) << 21) ^ ((c.z as u64) << 42);
+    (mix_bits(key) as usize) & (n_buckets - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::sampler::Pcg32;
+
+    #[test]
+    fn gathers_like_brute_force() {
+        let mut rng = Pcg32::new(7, 0);
+        let mut next = || Vec3::new(rng.next_f32(), rng.next_f32(), rng.next_f32()) * 4.0 - 2.0;
+        let photons: Vec<Photon> = (0..2000)
+            .map(|_| Photon {
+                p: next(),
+                wi: Vec3::Y,
+                power: Vec3::ONE,
+            })
+            .collect();
+        let map = PhotonMap::new(photons.clone(), 0.3);
+        assert_eq!(map.len(), 2000);
+        for _ in 0..50 {
+            let p = next();
+            for radius in [0.1, 0.3] {
+                let mut gathered = Vec::new();
+                map.for_each_within(p, radius, |photon| gathered.push(photon.p.to_array()));
+                let mut expected: Vec<_> = photons
+                    .iter()
+                    .filter(|photon| photon.p.distance_squared(p) < radius * radius)
+                    .map(|photon| photon.p.to_array())
+                    .collect();
+                gathered.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                assert_eq!(gathered, expected);
+            }
+        }
+        let empty = PhotonMap::new(Vec::new(), 1.0);
+        empty.for_each_within(Vec3::ZERO, 1.0, |_| panic!());
+        assert!(empty.is_empty());
+    }
+}
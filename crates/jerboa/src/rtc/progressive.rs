@@ -0,0 +1,329 @@
+//! Progressive rendering driver with adaptive sampling.
+//!
+//! The image is rendered in passes. After a minimum number of samples, each
+//! pass only samples the pixels whose estimated relative error is still above
+//! the target, so that flat regions stop consuming samples early. Rendering
+//! stops when every pixel has converged, when the sample budget of every
+//! active pixel is spent, or when the time budget is exhausted.
+
+use crate::{
+    core::image::{par::run_parallel, PixelBufferRgb32f},
+    rtc::{
+        aov::{AovKind, AovSample, Aovs},
+        camera::Camera,
+        integrators::Integrator,
+        sampler::{IndependentSampler, Sampler},
+        scene::Scene,
+    },
+};
+use glam::{Vec2, Vec3};
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Small radiance added to the mean when estimating relative errors, so that
+/// black pixels can converge.
+const ERROR_EPSILON: f32 = 1e-3;
+
+/// State of a rendering, passed to snapshot callbacks and returned at the end.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RenderProgress {
+    /// Number of completed passes.
+    pub passes: u32,
+
+    /// Total number of samples taken.
+    pub samples: u64,
+
+    /// Number of pixels still above the error threshold.
+    pub active_pixels: usize,
+
+    pub elapsed: Duration,
+}
+
+/// Result of a rendering.
+pub struct RenderOutput {
+    /// Per-pixel mean of the radiance estimates.
+    pub image: PixelBufferRgb32f,
+
+    /// Auxiliary outputs, with the statistics of every pixel.
+    pub aovs: Aovs,
+
+    pub progress: RenderProgress,
+}
+
+/// Configuration of a progressive rendering.
+#[derive(Debug, Clone)]
+pub struct ProgressiveRenderer {
+    samples_per_pass: u32,
+    min_samples: u32,
+    max_samples: u32,
+    relative_error: Option<f32>,
+    time_budget: Option<Duration>,
+    snapshot_interval: Option<Duration>,
+    aov_kinds: Vec<AovKind>,
+    tile_size: u32,
+    n_threads: usize,
+    seed: u64,
+}
+
+impl Default for ProgressiveRenderer {
+    fn default() -> Self {
+        Self {
+            samples_per_pass: 4,
+            min_samples: 16,
+            max_samples: 1024,
+            relative_error: None,
+            time_budget: None,
+            snapshot_interval: None,
+            aov_kinds: Vec::new(),
+            tile_size: 16,
+            n_threads: crate::core::image::par::default_n_threads(),
+            seed: 0,
+        }
+    }
+}
+
+impl ProgressiveRenderer {
+    /// Creates a renderer taking 4 samples per pass, from 16 to 1024 samples
+    /// per pixel, with neither error target nor time budget.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of samples taken in an active pixel at each pass.
+    pub fn with_samples_per_pass(mut self, n: u32) -> Self {
+        self.samples_per_pass = n.max(1);
+        self
+    }
+
+    /// Sets the number of samples every pixel gets before its error is
+    /// estimated, and the maximum number of samples of a pixel.
+    pub fn with_sample_range(mut self, min: u32, max: u32) -> Self {
+        self.min_samples = min.max(2);
+        self.max_samples = max.max(self.min_samples);
+        self
+    }
+
+    /// Sets the relative standard error of the mean below which a pixel is
+    /// considered converged.
+    pub fn with_relative_error(mut self, error: f32) -> Self {
+        self.relative_error = Some(error);
+        self
+    }
+
+    /// Stops rendering after the first pass ending past `budget`.
+    pub fn with_time_budget(mut self, budget: Duration) -> Self {
+        self.time_budget = Some(budget);
+        self
+    }
+
+    /// Sets the minimum time between two snapshots.
+    pub fn with_snapshot_interval(mut self, interval: Duration) -> Self {
+        self.snapshot_interval = Some(interval);
+        self
+    }
+
+    /// Sets the auxiliary outputs recorded along the beauty.
+    pub fn with_aovs(mut self, kinds: &[AovKind]) -> Self {
+        self.aov_kinds = kinds.to_vec();
+        self
+    }
+
+    pub fn with_threads(mut self, n_threads: usize) -> Self {
+        self.n_threads = n_threads;
+        self
+    }
+
+    /// Sets the seed of the samplers; renderings with the same seed and
+    /// number of passes are identical.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn render(
+        &self,
+        camera: &dyn Camera,
+        scene: &Scene,
+        integrator: &dyn Integrator,
+    ) -> RenderOutput {
+        self.render_with_snapshots(camera, scene, integrator, |_, _| {})
+    }
+
+    /// Renders, calling `snapshot` with the current image at the configured
+    /// interval, e.g. to write it to disk.
+    pub fn render_with_snapshots<F: FnMut(&PixelBufferRgb32f, &RenderProgress)>(
+        &self,
+        camera: &dyn Camera,
+        scene: &Scene,
+        integrator: &dyn Integrator,
+        mut snapshot: F,
+    ) -> RenderOutput {
+        let start = Instant::now();
+        let (width, height) = camera.resolution();
+        let aovs = Mutex::new(Aovs::new(width, height, &self.aov_kinds));
+        let mut active: Vec<(u32, u32)> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .collect();
+        let mut progress = RenderProgress {
+            passes: 0,
+            samples: 0,
+            active_pixels: active.len(),
+            elapsed: Duration::ZERO,
+        };
+        let mut last_snapshot = start;
+
+        while !active.is_empty() {
+            let chunks: Vec<&[(u32, u32)]> = active
+                .chunks((self.tile_size * self.tile_size) as usize)
+                .collect();
+            run_parallel(chunks, self.n_threads, |pixels| {
+                let mut sampler = IndependentSampler::new(self.seed);
+                let mut samples = Vec::with_capacity(pixels.len() * self.samples_per_pass as usize);
+                let first = progress.passes * self.samples_per_pass;
+                for &(x, y) in pixels {
+                    for index in first..first + self.samples_per_pass {
+                        sampler.start_pixel_sample((x, y), index);
+                        let p_film = Vec2::new(x as f32, y as f32) + sampler.get_2d();
+                        let ray = camera.generate_ray(p_film, sampler.get_2d(), sampler.get_1d());
+                        let mut aov = AovSample::default();
+                        let l = integrator.li(&ray, scene, &mut sampler, &mut aov);
+                        samples.push((x, y, l, aov));
+                    }
+                }
+                let mut aovs = aovs.lock().unwrap();
+                for (x, y, l, aov) in &samples {
+                    aovs.record(*x, *y, *l, aov);
+                }
+            });
+            progress.passes += 1;
+            progress.samples += active.len() as u64 * self.samples_per_pass as u64;
+
+            let aovs = aovs.lock().unwrap();
+            if progress.passes * self.samples_per_pass >= self.min_samples {
+                active.retain(|&(x, y)| {
+                    let n = aovs.sample_count(x, y);
+                    n < self.max_samples && !self.converged(&aovs, x, y)
+                });
+            }
+            progress.active_pixels = active.len();
+            progress.elapsed = start.elapsed();
+
+            let out_of_time =
+                matches!(self.time_budget, Some(budget) if progress.elapsed >= budget);
+            let snapshot_due = matches!(self.snapshot_interval, Some(interval) if last_snapshot.elapsed() >= interval);
+            if snapshot_due && !active.is_empty() && !out_of_time {
+                snapshot(&mean_image(&aovs), &progress);
+                last_snapshot = Instant::now();
+            }
+            if out_of_time {
+                break;
+            }
+        }
+
+        let aovs = aovs.into_inner().unwrap();
+        RenderOutput {
+            image: mean_image(&aovs),
+            aovs,
+            progress,
+        }
+    }
+
+    /// Whether the relative standard error of the mean of every channel of a
+    /// pixel is below the target. Without a target, pixels converge once
+    /// they have their minimum number of samples.
+    fn converged(&self, aovs: &Aovs, x: u32, y: u32) -> bool {
+        let threshold = match self.relative_error {
+            Some(threshold) => threshold,
+            None => return true,
+        };
+        let n = aovs.sample_count(x, y) as f32;
+        let std_error = (aovs.variance(x, y) / n).max(Vec3::ZERO);
+        let error = Vec3::new(std_error.x.sqrt(), std_error.y.sqrt(), std_error.z.sqrt())
+            / (aovs.mean(x, y).abs() + Vec3::splat(ERROR_EPSILON));
+        error.max_element() <= threshold
+    }
+}
+
+fn mean_image(aovs: &Aovs) -> PixelBufferRgb32f {
+    let (width, height) = aovs.dimensions();
+    let mut image = PixelBufferRgb32f::new(width, height);
+    for ((x, y), pixel) in image.pixels_mut() {
+        *pixel = crate::core::Vec3::from(aovs.mean(x as u32, y as u32).to_array());
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::{camera::PerspectiveCamera, ray::Ray};
+
+    /// Returns noise in the left half of the image and a constant elsewhere.
+    struct HalfNoise;
+
+    impl Integrator for HalfNoise {
+        fn li(&self, ray: &Ray, _: &Scene, sampler: &mut dyn Sampler, aov: &mut AovSample) -> Vec3 {
+            aov.hit = true;
+            aov.depth = 1.0;
+            if ray.d.x < 0.0 {
+                Vec3::splat(2.0 * sampler.get_1d())
+            } else {
+                Vec3::ONE
+            }
+        }
+    }
+
+    fn camera() -> PerspectiveCamera {
+        PerspectiveCamera::look_at(Vec3::ZERO, -Vec3::Z, Vec3::Y, 60.0, (8, 4))
+    }
+
+    #[test]
+    fn noisy_pixels_get_more_samples() {
+        let output = ProgressiveRenderer::new()
+            .with_sample_range(8, 256)
+            .with_relative_error(0.01)
+            .with_threads(2)
+            .render(&camera(), &Scene::new(), &HalfNoise);
+        let aovs = &output.aovs;
+        assert_eq!(aovs.sample_count(7, 0), 8);
+        assert_eq!(aovs.sample_count(0, 0), 256);
+        assert_eq!(output.image.pixel_at(7, 3).unwrap()[0], 1.0);
+        assert!((output.image.pixel_at(0, 3).unwrap()[0] - 1.0).abs() < 0.2);
+        assert_eq!(output.progress.samples, 16 * 8 + 16 * 256);
+        assert_eq!(output.progress.active_pixels, 0);
+    }
+
+    #[test]
+    fn renders_are_reproducible() {
+        let renderer = ProgressiveRenderer::new().with_sample_range(4, 4);
+        let a = renderer
+            .clone()
+            .with_threads(1)
+            .render(&camera(), &Scene::new(), &HalfNoise);
+        let b = renderer
+            .with_threads(3)
+            .render(&camera(), &Scene::new(), &HalfNoise);
+        assert_eq!(a.image.samples(), b.image.samples());
+    }
+
+    #[test]
+    fn time_budget_and_snapshots() {
+        let mut n_snapshots = 0;
+        let output = ProgressiveRenderer::new()
+            .with_samples_per_pass(1)
+            .with_sample_range(2, u32::MAX)
+            .with_relative_error(0.0)
+            .with_time_budget(Duration::from_millis(50))
+            .with_snapshot_interval(Duration::ZERO)
+            .render_with_snapshots(&camera(), &Scene::new(), &HalfNoise, |image, progress| {
+                assert_eq!(image.dimensions(), (8, 4));
+                assert!(progress.passes > 0);
+                n_snapshots += 1;
+            });
+        assert!(output.progress.elapsed >= Duration::from_millis(50));
+        assert!(output.progress.active_pixels > 0);
+        assert_eq!(n_snapshots, output.progress.passes - 1);
+    }
+}
@@ -1,5 +1,7 @@
+use crate::core::rounding::{next_float_down, next_float_up};
 use glam::Vec3;
 
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Ray {
     /// The origin of the ray.
     pub o: Vec3,
@@ -9,6 +11,9 @@ pub struct Ray {
 
     /// Component wise reciprocal of the direction vector.
     pub d_rcp: Vec3,
+
+    /// Instant at which the ray samples the scene, for motion blur.
+    pub time: f32,
 }
 
 impl Ray {
@@ -17,6 +22,52 @@ impl Ray {
             o,
             d,
             d_rcp: d.recip(),
+            time: 0.0,
         }
     }
+
+    pub fn with_time(mut self, time: f32) -> Self {
+        self.time = time;
+        self
+    }
+}
+
+/// Origin of a ray leaving the surface point `p` in direction `w`, `p`
+/// being within `p_error` of the surface in each dimension. The point is
+/// moved along the normal `n` out of the error box, on the side `w` points
+/// to, then rounded away from the surface, so that the ray cannot hit the
+/// surface again at its origin.
+pub fn offset_ray_origin(p: Vec3, p_error: Vec3, n: Vec3, w: Vec3) -> Vec3 {
+    let n = if w.dot(n) < 0.0 { -n } else { n };
+    let mut o = p + n * n.abs().dot(p_error);
+    for i in 0..3 {
+        if n[i] > 0.0 {
+            o[i] = next_float_up(o[i]);
+        } else if n[i] < 0.0 {
+            o[i] = next_float_down(o[i]);
+        }
+    }
+    o
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offset_leaves_the_error_box() {
+        let (p, p_error) = (Vec3::new(1.0, -2.0, 3.0), Vec3::new(1e-3, 2e-3, 0.0));
+        let n = Vec3::new(1.0, 1.0, 0.0).normalize();
+        // Beyond the plane through the corner of the box along the normal,
+        // on the side of the direction.
+        let d = n.abs().dot(p_error) as f64;
+        let moved = |o: Vec3, sign: f64| {
+            (0..2).all(|i| sign * (o[i] as f64 - p[i] as f64) > n[i] as f64 * d)
+        };
+        assert!(moved(offset_ray_origin(p, p_error, n, Vec3::X), 1.0));
+        assert!(moved(offset_ray_origin(p, p_error, n, -Vec3::X), -1.0));
+        // Without error, the point still moves off an axis-aligned surface.
+        let o = offset_ray_origin(p, Vec3::ZERO, Vec3::Z, Vec3::Z);
+        assert!(o.z > p.z && o.x == p.x);
+    }
 }
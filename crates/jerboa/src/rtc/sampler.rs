@@ -0,0 +1,267 @@
+//! Sources of the random numbers consumed by integrators.
+
+use glam::Vec2;
+use std::f32::consts::TAU;
+
+/// PCG32 random number generator (O'Neill 2014).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Pcg32 {
+    state: u64,
+    inc: u64,
+}
+
+impl Pcg32 {
+    const MULTIPLIER: u64 = 6364136223846793005;
+
+    /// Creates a generator from a starting state and a stream selector.
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut rng = Pcg32 {
+            state: 0,
+            inc: (stream << 1) | 1,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(Self::MULTIPLIER).wrapping_add(self.inc);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    /// Returns a uniformly distributed number in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 * (1.0 / (1u32 << 24) as f32)
+    }
+}
+
+/// Mixes a 64 bits value (the finaliser of SplitMix64).
+pub(crate) fn mix_bits(mut v: u64) -> u64 {
+    v ^= v >> 31;
+    v = v.wrapping_mul(0x7fb5_d329_728e_a185);
+    v ^= v >> 27;
+    v = v.wrapping_mul(0x81da_def4_bc2d_d44d);
+    v ^ (v >> 33)
+}
+
+pub trait Sampler: Send {
+    /// Prepares the sampler for the `index`-th sample of a pixel, so that the
+    /// numbers generated only depend on the pixel, the index and the seed.
+    fn start_pixel_sample(&mut self, pixel: (u32, u32), index: u32);
+
+    /// Returns a number in `[0, 1)`.
+    fn get_1d(&mut self) -> f32;
+
+    /// Returns a point in `[0, 1)^2`.
+    fn get_2d(&mut self) -> Vec2 {
+        let x = self.get_1d();
+        Vec2::new(x, self.get_1d())
+    }
+}
+
+/// Sampler returning independent uniform random numbers.
+#[derive(Debug, Clone)]
+pub struct IndependentSampler {
+    seed: u64,
+    rng: Pcg32,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: Pcg32::new(mix_bits(seed), 0),
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, (x, y): (u32, u32), index: u32) {
+        let pixel = ((x as u64) << 32) | y as u64;
+        self.rng = Pcg32::new(mix_bits(pixel ^ self.seed), mix_bits(index as u64));
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        self.rng.next_f32()
+    }
+}
+
+/// Component of a primary sample vector, mutated lazily.
+#[derive(Debug, Copy, Clone, Default)]
+struct PrimarySample {
+    value: f32,
+
+    /// Iteration at which the value was last mutated.
+    last_modification: u64,
+
+    /// State before the current iteration, restored if the mutation is
+    /// rejected.
+    value_backup: f32,
+    modification_backup: u64,
+}
+
+/// Sampler of primary sample space Metropolis light transport (Kelemen et
+/// al. 2002), returning the components of a vector mutated at every
+/// iteration of a Markov chain.
+///
+/// Large steps replace the vector with uniform numbers, small steps perturb
+/// it with a normal distribution of deviation `sigma`. Components are only
+/// mutated when requested, by applying all the mutations they missed at
+/// once.
+#[derive(Debug, Clone)]
+pub struct MltSampler {
+    rng: Pcg32,
+    sigma: f32,
+    large_step_probability: f32,
+    x: Vec<PrimarySample>,
+    current_iteration: u64,
+    large_step: bool,
+    last_large_step: u64,
+    sample_index: usize,
+}
+
+impl MltSampler {
+    /// Creates a sampler whose initial vector is uniformly distributed,
+    /// drawn from the stream `stream` of the seed.
+    pub fn new(seed: u64, stream: u64, sigma: f32, large_step_probability: f32) -> Self {
+        Self {
+            rng: Pcg32::new(mix_bits(seed), stream),
+            sigma,
+            large_step_probability,
+            x: Vec::new(),
+            current_iteration: 0,
+            large_step: true,
+            last_large_step: 0,
+            sample_index: 0,
+        }
+    }
+
+    /// Starts a mutation, drawing whether it is a large step.
+    pub fn start_iteration(&mut self) {
+        self.current_iteration += 1;
+        self.large_step = self.rng.next_f32() < self.large_step_probability;
+        self.sample_index = 0;
+    }
+
+    pub fn is_large_step(&self) -> bool {
+        self.large_step
+    }
+
+    /// Keeps the mutated vector.
+    pub fn accept(&mut self) {
+        if self.large_step {
+            self.last_large_step = self.current_iteration;
+        }
+    }
+
+    /// Restores the vector from before the mutation.
+    pub fn reject(&mut self) {
+        for xi in &mut self.x {
+            if xi.last_modification == self.current_iteration {
+                xi.value = xi.value_backup;
+                xi.last_modification = xi.modification_backup;
+            }
+        }
+        self.current_iteration -= 1;
+    }
+
+    /// Brings the `index`-th component up to date with the current
+    /// iteration.
+    fn ensure_ready(&mut self, index: usize) {
+        if index >= self.x.len() {
+            self.x.resize(index + 1, PrimarySample::default());
+        }
+        let xi = &mut self.x[index];
+        // Components unused since the last large step are reset as if they
+        // had been mutated by it.
+        if xi.last_modification < self.last_large_step {
+            xi.value = self.rng.next_f32();
+            xi.last_modification = self.last_large_step;
+        }
+        xi.value_backup = xi.value;
+        xi.modification_backup = xi.last_modification;
+        if self.large_step {
+            xi.value = self.rng.next_f32();
+        } else {
+            // The sum of the missed small steps is normally distributed.
+            let n_small = (self.current_iteration - xi.last_modification) as f32;
+            let sigma = self.sigma * n_small.sqrt();
+            let (u0, u1) = (self.rng.next_f32(), self.rng.next_f32());
+            let normal = (-2.0 * (1.0 - u0).ln()).sqrt() * (TAU * u1).cos();
+            xi.value += sigma * normal;
+            xi.value -= xi.value.floor();
+            if xi.value >= 1.0 {
+                xi.value = 0.0;
+            }
+        }
+        xi.last_modification = self.current_iteration;
+    }
+}
+
+impl Sampler for MltSampler {
+    /// Does nothing: the numbers are driven by the Markov chain, see
+    /// [`MltSampler::start_iteration`].
+    fn start_pixel_sample(&mut self, _pixel: (u32, u32), _index: u32) {}
+
+    fn get_1d(&mut self) -> f32 {
+        let index = self.sample_index;
+        self.ensure_ready(index);
+        self.sample_index += 1;
+        self.x[index].value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixel_samples_are_reproducible() {
+        let mut a = IndependentSampler::new(7);
+        let mut b = IndependentSampler::new(7);
+        a.start_pixel_sample((3, 4), 2);
+        let first: Vec<f32> = (0..4).map(|_| a.get_1d()).collect();
+        b.start_pixel_sample((0, 0), 0);
+        b.get_2d();
+        b.start_pixel_sample((3, 4), 2);
+        let second: Vec<f32> = (0..4).map(|_| b.get_1d()).collect();
+        assert_eq!(first, second);
+        a.start_pixel_sample((3, 4), 3);
+        assert_ne!(a.get_1d(), first[0]);
+    }
+
+    #[test]
+    fn uniform_numbers_are_in_range() {
+        let mut rng = Pcg32::new(42, 54);
+        let mean = (0..10000)
+            .map(|_| rng.next_f32())
+            .inspect(|u| assert!((0.0..1.0).contains(u)))
+            .sum::<f32>()
+            / 10000.0;
+        assert!((mean - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn rejected_mutations_are_undone() {
+        let mut sampler = MltSampler::new(1, 0, 0.01, 0.0);
+        let values = |sampler: &MltSampler| sampler.x.iter().map(|xi| xi.value).collect::<Vec<_>>();
+        let initial: Vec<f32> = (0..3).map(|_| sampler.get_1d()).collect();
+        sampler.start_iteration();
+        let mutated: Vec<f32> = (0..3).map(|_| sampler.get_1d()).collect();
+        assert_ne!(initial, mutated);
+        // Small steps, up to the wrapping around the unit interval.
+        assert!(initial
+            .iter()
+            .zip(&mutated)
+            .all(|(a, b)| (a - b).abs().min(1.0 - (a - b).abs()) < 0.1));
+        sampler.reject();
+        assert_eq!(values(&sampler), initial);
+        sampler.start_iteration();
+        let mutated: Vec<f32> = (0..3).map(|_| sampler.get_1d()).collect();
+        sampler.accept();
+        assert_eq!(values(&sampler), mutated);
+    }
+}
@@ -0,0 +1,309 @@
+//! Sampling routines turning uniform random numbers into samples of other
+//! distributions.
+
+use glam::{Vec2, Vec3};
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI, TAU};
+
+/// Builds two unit vectors forming an orthonormal basis with the unit vector
+/// `v` (Duff et al. 2017).
+pub fn coordinate_system(v: Vec3) -> (Vec3, Vec3) {
+    let sign = 1f32.copysign(v.z);
+    let a = -1.0 / (sign + v.z);
+    let b = v.x * v.y * a;
+    (
+        Vec3::new(1.0 + sign * v.x * v.x * a, sign * b, -sign * v.x),
+        Vec3::new(b, sign + v.y * v.y * a, -v.y),
+    )
+}
+
+/// Uniformly samples a direction on the unit sphere.
+pub fn uniform_sample_sphere(u: Vec2) -> Vec3 {
+    let z = 1.0 - 2.0 * u.x;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let (sin_phi, cos_phi) = (TAU * u.y).sin_cos();
+    Vec3::new(r * cos_phi, r * sin_phi, z)
+}
+
+pub fn uniform_sphere_pdf() -> f32 {
+    1.0 / (4.0 * PI)
+}
+
+/// Uniformly samples a direction in the cone around `+z` of directions whose
+/// angle to the axis has a cosine of at least `cos_max`.
+pub fn uniform_sample_cone(u: Vec2, cos_max: f32) -> Vec3 {
+    let cos_theta = 1.0 - u.x * (1.0 - cos_max);
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let (sin_phi, cos_phi) = (TAU * u.y).sin_cos();
+    Vec3::new(sin_theta * cos_phi, sin_theta * sin_phi, cos_theta)
+}
+
+pub fn uniform_cone_pdf(cos_max: f32) -> f32 {
+    1.0 / (TAU * (1.0 - cos_max))
+}
+
+/// Uniformly samples a point on the unit disk, mapping concentric squares to
+/// concentric circles (Shirley and Chiu 1997).
+pub fn concentric_sample_disk(u: Vec2) -> Vec2 {
+    let u = 2.0 * u - Vec2::ONE;
+    if u == Vec2::ZERO {
+        return Vec2::ZERO;
+    }
+    let (r, theta) = if u.x.abs() > u.y.abs() {
+        (u.x, FRAC_PI_4 * (u.y / u.x))
+    } else {
+        (u.y, FRAC_PI_2 - FRAC_PI_4 * (u.x / u.y))
+    };
+    let (sin, cos) = theta.sin_cos();
+    r * Vec2::new(cos, sin)
+}
+
+/// Uniformly samples a point on a triangle, returning the barycentric
+/// coordinates of its second and third vertices.
+pub fn uniform_sample_triangle(u: Vec2) -> Vec2 {
+    let su = u.x.sqrt();
+    Vec2::new(su * (1.0 - u.y), su * u.y)
+}
+
+/// Samples a direction in the hemisphere around `+z` with a density
+/// proportional to the cosine of its angle to the axis (Malley's method).
+pub fn cosine_sample_hemisphere(u: Vec2) -> Vec3 {
+    let r = u.x.sqrt();
+    let (sin_phi, cos_phi) = (TAU * u.y).sin_cos();
+    Vec3::new(r * cos_phi, r * sin_phi, (1.0 - u.x).max(0.0).sqrt())
+}
+
+pub fn cosine_hemisphere_pdf(cos_theta: f32) -> f32 {
+    cos_theta.max(0.0) / PI
+}
+
+/// Multiple importance sampling weight of a sample drawn `nf` times from a
+/// density `f_pdf`, combined with `ng` samples from a density `g_pdf`.
+pub fn power_heuristic(nf: u32, f_pdf: f32, ng: u32, g_pdf: f32) -> f32 {
+    let f = nf as f32 * f_pdf;
+    let g = ng as f32 * g_pdf;
+    if f.is_infinite() {
+        return 1.0;
+    }
+    if f == 0.0 && g == 0.0 {
+        return 0.0;
+    }
+    (f * f) / (f * f + g * g)
+}
+
+/// Piecewise-constant distribution over `[0, 1)` built from a tabulated
+/// function.
+#[derive(Debug, Clone)]
+pub struct Distribution1D {
+    func: Vec<f32>,
+    cdf: Vec<f32>,
+    func_int: f32,
+}
+
+impl Distribution1D {
+    /// Builds the distribution of the absolute values of `func`. A function
+    /// integrating to zero gives the uniform distribution.
+    pub fn new(func: &[f32]) -> Self {
+        assert!(!func.is_empty(), "cannot build an empty distribution");
+        let n = func.len();
+        let func: Vec<f32> = func.iter().map(|f| f.abs()).collect();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + func[i] / n as f32;
+        }
+        let func_int = cdf[n];
+        if func_int == 0.0 {
+            for (i, c) in cdf.iter_mut().enumerate() {
+                *c = i as f32 / n as f32;
+            }
+        } else {
+            cdf.iter_mut().for_each(|c| *c /= func_int);
+        }
+        Self {
+            func,
+            cdf,
+            func_int,
+        }
+    }
+
+    /// Number of pieces.
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    /// Integral of the tabulated function over `[0, 1)`.
+    pub fn integral(&self) -> f32 {
+        self.func_int
+    }
+
+    /// Index of the piece containing `u` in the cumulative distribution.
+    fn find(&self, u: f32) -> usize {
+        // Last index whose cdf is not greater than `u`, clamped to a valid
+        // piece.
+        let i = self.cdf.partition_point(|&c| c <= u);
+        i.saturating_sub(1).min(self.count() - 1)
+    }
+
+    /// Samples a point in `[0, 1)`, returning it with its density and the
+    /// index of the piece it lies in.
+    pub fn sample_continuous(&self, u: f32) -> (f32, f32, usize) {
+        let i = self.find(u);
+        let mut du = u - self.cdf[i];
+        let width = self.cdf[i + 1] - self.cdf[i];
+        if width > 0.0 {
+            du /= width;
+        }
+        let pdf = if self.func_int > 0.0 {
+            self.func[i] / self.func_int
+        } else {
+            1.0
+        };
+        let x = ((i as f32 + du) / self.count() as f32).min(1.0 - f32::EPSILON);
+        (x, pdf, i)
+    }
+
+    /// Samples a piece, returning it with its probability.
+    pub fn sample_discrete(&self, u: f32) -> (usize, f32) {
+        let i = self.find(u);
+        (i, self.discrete_pdf(i))
+    }
+
+    /// Probability of sampling the piece `i` with [`sample_discrete`].
+    ///
+    /// [`sample_discrete`]: Self::sample_discrete
+    pub fn discrete_pdf(&self, i: usize) -> f32 {
+        self.cdf[i + 1] - self.cdf[i]
+    }
+
+    /// Density of [`sample_continuous`] at `x`.
+    ///
+    /// [`sample_continuous`]: Self::sample_continuous
+    pub fn pdf(&self, x: f32) -> f32 {
+        let i = ((x * self.count() as f32) as usize).min(self.count() - 1);
+        if self.func_int > 0.0 {
+            self.func[i] / self.func_int
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Piecewise-constant distribution over `[0, 1)^2` built from a function
+/// tabulated on a `nu` x `nv` grid, stored row by row.
+#[derive(Debug, Clone)]
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(func: &[f32], nu: usize, nv: usize) -> Self {
+        assert_eq!(func.len(), nu * nv, "function size mismatch");
+        let conditional: Vec<_> = func.chunks_exact(nu).map(Distribution1D::new).collect();
+        let marginal =
+            Distribution1D::new(&conditional.iter().map(|d| d.integral()).collect::<Vec<_>>());
+        Self {
+            conditional,
+            marginal,
+        }
+    }
+
+    /// Samples a point, returning it with its density.
+    pub fn sample_continuous(&self, u: Vec2) -> (Vec2, f32) {
+        let (v, pdf_v, row) = self.marginal.sample_continuous(u.y);
+        let (u, pdf_u, _) = self.conditional[row].sample_continuous(u.x);
+        (Vec2::new(u, v), pdf_u * pdf_v)
+    }
+
+    /// Density of [`sample_continuous`] at `p`.
+    ///
+    /// [`sample_continuous`]: Self::sample_continuous
+    pub fn pdf(&self, p: Vec2) -> f32 {
+        let row = ((p.y * self.marginal.count() as f32) as usize).min(self.marginal.count() - 1);
+        self.conditional[row].pdf(p.x) * self.marginal.pdf(p.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coordinate_system_is_orthonormal() {
+        for v in [Vec3::X, -Vec3::Z, Vec3::new(0.3, -0.5, 0.2).normalize()] {
+            let (a, b) = coordinate_system(v);
+            assert!((a.length() - 1.0).abs() < 1e-5 && (b.length() - 1.0).abs() < 1e-5);
+            assert!(a.dot(v).abs() < 1e-5 && b.dot(v).abs() < 1e-5 && a.dot(b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn cosine_samples_are_cosine_distributed() {
+        // The mean cosine of a cosine distributed direction is 2/3.
+        let n = 64;
+        let mut mean_cos = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let u = Vec2::new((i as f32 + 0.5) / n as f32, (j as f32 + 0.5) / n as f32);
+                let d = cosine_sample_hemisphere(u);
+                assert!(d.z >= 0.0 && (d.length() - 1.0).abs() < 1e-5);
+                mean_cos += d.z;
+            }
+        }
+        assert!((mean_cos / (n * n) as f32 - 2.0 / 3.0).abs() < 1e-3);
+        assert!((cosine_hemisphere_pdf(1.0) - 1.0 / PI).abs() < 1e-7);
+        assert_eq!(power_heuristic(1, 1.0, 1, 0.0), 1.0);
+        for u in [
+            Vec2::new(0.1, 0.9),
+            Vec2::new(0.5, 0.5),
+            Vec2::new(1.0, 0.3),
+        ] {
+            assert!(concentric_sample_disk(u).length() <= 1.0 + 1e-6);
+        }
+        assert!(concentric_sample_disk(Vec2::new(1.0, 0.5)).abs_diff_eq(Vec2::X, 1e-6));
+        assert_eq!(power_heuristic(1, 1.0, 1, 1.0), 0.5);
+    }
+
+    #[test]
+    fn cone_samples_stay_in_cone() {
+        let cos_max = 0.9;
+        for i in 0..16 {
+            let u = Vec2::new(i as f32 / 16.0, (i * 5 % 16) as f32 / 16.0);
+            assert!(uniform_sample_cone(u, cos_max).z >= cos_max - 1e-6);
+            assert!((uniform_sample_sphere(u).length() - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn distribution_1d_follows_function() {
+        let d = Distribution1D::new(&[1.0, 0.0, 3.0]);
+        assert_eq!(d.integral(), 4.0 / 3.0);
+        assert_eq!(d.sample_discrete(0.1), (0, 0.25));
+        assert_eq!(d.sample_discrete(0.5).0, 2);
+        let (x, pdf, i) = d.sample_continuous(0.625);
+        assert_eq!(i, 2);
+        assert!((x - 0.8333).abs() < 1e-3);
+        assert_eq!(pdf, 2.25);
+        assert_eq!(d.pdf(x), pdf);
+        assert_eq!(d.pdf(0.5), 0.0);
+    }
+
+    #[test]
+    fn distribution_1d_of_zero_is_uniform() {
+        let d = Distribution1D::new(&[0.0; 4]);
+        let (x, pdf, _) = d.sample_continuous(0.3);
+        assert!((x - 0.3).abs() < 1e-6);
+        assert_eq!(pdf, 1.0);
+    }
+
+    #[test]
+    fn distribution_2d_pdf_matches_samples() {
+        let func = [1.0, 2.0, 0.0, 4.0, 0.5, 1.0];
+        let d = Distribution2D::new(&func, 3, 2);
+        for i in 0..16 {
+            let u = Vec2::new((i as f32 + 0.5) / 16.0, ((i * 7) % 16) as f32 / 16.0);
+            let (p, pdf) = d.sample_continuous(u);
+            assert!(pdf > 0.0);
+            assert!((d.pdf(p) - pdf).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Collection of the shapes, materials, lights and media rendered.
+
+use crate::rtc::{
+    aov::INVALID_ID,
+    bsdfs::Bsdf,
+    bvh::{Bvh, TraversalStats},
+    lights::Light,
+    media::{Medium, MediumInterface},
+    ray::Ray,
+    IntersectRecord, Shape,
+};
+use std::sync::OnceLock;
+
+#[derive(Default)]
+pub struct Scene {
+    shapes: Vec<Box<dyn Shape>>,
+
+    /// Index of the material of each shape, [`INVALID_ID`] if it has none.
+    shape_materials: Vec<u32>,
+
+    materials: Vec<Box<dyn Bsdf>>,
+    lights: Vec<Box<dyn Light>>,
+
+    /// Media on both sides of each shape, `None` for shapes which rays
+    /// cross without changing medium.
+    shape_media: Vec<Option<MediumInterface>>,
+
+    media: Vec<Box<dyn Medium>>,
+
+    /// Hierarchy over the shapes, built by the first query after the shapes
+    /// change.
+    bvh: OnceLock<Bvh>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a shape without material, which absorbs all light, returning
+    /// its primitive index.
+    pub fn add_shape(&mut self, shape: impl Shape + 'static) -> usize {
+        self.add_shape_with_material(shape, INVALID_ID)
+    }
+
+    /// Adds a shape made of the material of index `material`, returning its
+    /// primitive index.
+    pub fn add_shape_with_material(&mut self, shape: impl Shape + 'static, material: u32) -> usize {
+        self.shapes.push(Box::new(shape));
+        self.shape_materials.push(material);
+        self.shape_media.push(None);
+        self.bvh = OnceLock::new();
+        self.shapes.len() - 1
+    }
+
+    /// Adds a material, returning its index.
+    pub fn add_material(&mut self, bsdf: impl Bsdf + 'static) -> u32 {
+        self.materials.push(Box::new(bsdf));
+        self.materials.len() as u32 - 1
+    }
+
+    /// Adds a shape without material separating two media, which rays
+    /// cross without scattering, returning its primitive index.
+    pub fn add_medium_boundary(
+        &mut self,
+        shape: impl Shape + 'static,
+        interface: MediumInterface,
+    ) -> usize {
+        let primitive = self.add_shape(shape);
+        self.shape_media[primitive] = Some(interface);
+        primitive
+    }
+
+    /// Sets the media on both sides of a primitive, e.g. the inside of a
+    /// glass of water.
+    pub fn set_medium_interface(&mut self, primitive: usize, interface: MediumInterface) {
+        self.shape_media[primitive] = Some(interface);
+    }
+
+    /// Adds a medium, returning its index.
+    pub fn add_medium(&mut self, medium: impl Medium + 'static) -> u32 {
+        self.media.push(Box::new(medium));
+        self.media.len() as u32 - 1
+    }
+
+    pub fn add_light(&mut self, light: impl Light + 'static) -> usize {
+        self.lights.push(Box::new(light));
+        self.lights.len() - 1
+    }
+
+    pub fn shapes(&self) -> &[Box<dyn Shape>] {
+        &self.shapes
+    }
+
+    pub fn lights(&self) -> &[Box<dyn Light>] {
+        &self.lights
+    }
+
+    /// Index of the material of a primitive, [`INVALID_ID`] if it has none.
+    pub fn material_id(&self, primitive: usize) -> u32 {
+        self.shape_materials[primitive]
+    }
+
+    /// Material of a primitive.
+    pub fn material(&self, primitive: usize) -> Option<&dyn Bsdf> {
+        self.materials
+            .get(self.shape_materials[primitive] as usize)
+            .map(|bsdf| bsdf.as_ref())
+    }
+
+    /// Media on both sides of a primitive, if it separates media.
+    pub fn medium_interface(&self, primitive: usize) -> Option<MediumInterface> {
+        self.shape_media[primitive]
+    }
+
+    /// Medium of index `id`, `None` for vacuum.
+    pub fn medium(&self, id: u32) -> Option<&dyn Medium> {
+        self.media.get(id as usize).map(|medium| medium.as_ref())
+    }
+
+    pub fn bvh(&self) -> &Bvh {
+        self.bvh.get_or_init(|| Bvh::new(&self.shapes))
+    }
+
+    /// Returns the closest hit along the ray, with the index of the primitive
+    /// hit.
+    pub fn intersect(&self, ray: &Ray) -> Option<(usize, IntersectRecord)> {
+        self.intersect_with_stats(ray, &mut TraversalStats::default())
+    }
+
+    /// Same as [`Scene::intersect`], accumulating the work done into `stats`.
+    pub fn intersect_with_stats(
+        &self,
+        ray: &Ray,
+        stats: &mut TraversalStats,
+    ) -> Option<(usize, IntersectRecord)> {
+        self.bvh().intersect(&self.shapes, ray, stats)
+    }
+
+    /// Whether anything lies along the ray closer than `t_max`.
+    pub fn occluded(&self, ray: &Ray, t_max: f32) -> bool {
+        self.bvh()
+            .occluded(&self.shapes, ray, t_max, &mut TraversalStats::default())
+    }
+}
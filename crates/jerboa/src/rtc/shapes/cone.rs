@@ -0,0 +1,152 @@
+use crate::{
+    core::rounding::mre,
+    rtc::{
+        aabb::Aabb,
+        ray::Ray,
+        shapes::{azimuth, local_frame, solve_quadratic},
+        transform::Transform,
+        IntersectRecord, SampleableShape, Shape, SurfaceSample,
+    },
+};
+use glam::{Vec2, Vec3};
+use std::f32::consts::{PI, TAU};
+
+/// Side of a cone, open at its base; a [`Disk`] can close it.
+///
+/// [`Disk`]: crate::rtc::shapes::Disk
+pub struct Cone {
+    /// Frame centred on the base, whose z axis points to the apex.
+    frame: Transform,
+    r: f32,
+    h: f32,
+}
+
+impl Cone {
+    /// Creates the cone of base radius `r` centred on `base`, with its apex
+    /// at `apex`.
+    pub fn new(base: Vec3, apex: Vec3, r: f32) -> Self {
+        let h = base.distance(apex);
+        Self {
+            frame: local_frame(base, (apex - base) / h),
+            r,
+            h,
+        }
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.r
+    }
+
+    pub fn height(&self) -> f32 {
+        self.h
+    }
+
+    /// Distance along the ray and local position of the closest hit.
+    fn hit(&self, ray: &Ray) -> Option<(f32, Vec3)> {
+        let Ray { o, d, .. } = self.frame.inverse().transform_ray(ray);
+        // x² + y² = (k (h - z))², k being the slope of the radius.
+        let k2 = (self.r / self.h).powi(2);
+        let dz = self.h - o.z;
+        let a = d.x * d.x + d.y * d.y - k2 * d.z * d.z;
+        let b = 2.0 * (o.x * d.x + o.y * d.y + k2 * dz * d.z);
+        let c = o.x * o.x + o.y * o.y - k2 * dz * dz;
+        let (t0, t1) = solve_quadratic(a, b, c)?;
+        [t0, t1]
+            .into_iter()
+            .filter(|&t| t > 0.0)
+            .map(|t| (t, o + d * t))
+            .find(|(_, p)| (0.0..=self.h).contains(&p.z))
+    }
+
+    /// Outward normal at the local point `p`, the gradient of the implicit
+    /// surface.
+    fn normal(&self, p: Vec3) -> Vec3 {
+        let k2 = (self.r / self.h).powi(2);
+        Vec3::new(p.x, p.y, k2 * (self.h - p.z)).normalize()
+    }
+
+    /// Angle around the axis and height, relative to that of the apex.
+    fn uv(&self, p: Vec3) -> Vec2 {
+        Vec2::new(azimuth(p), p.z / self.h)
+    }
+}
+
+impl Shape for Cone {
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        self.hit(ray).is_some()
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<IntersectRecord> {
+        let (t, mut p) = self.hit(ray)?;
+        // Reprojected onto the surface at the same height, with the rounding
+        // error of the reprojection.
+        let rho = p.x.hypot(p.y);
+        if rho > 0.0 {
+            let scale = self.r * (self.h - p.z) / (self.h * rho);
+            p = Vec3::new(p.x * scale, p.y * scale, p.z);
+        }
+        let p_error = Vec3::new(p.x, p.y, 0.0).abs() * mre(7);
+        let (p_world, p_error) = self.frame.transform_point_with_error(p, p_error);
+        Some(IntersectRecord {
+            t,
+            p: p_world,
+            p_error,
+            n: self.frame.transform_vector(self.normal(p)),
+            uv: self.uv(p),
+            time: ray.time,
+        })
+    }
+
+    fn bounds(&self) -> Aabb {
+        let r = Vec3::new(self.r, self.r, 0.0);
+        self.frame
+            .transform_bounds(&Aabb::new(-r, r + Vec3::Z * self.h))
+    }
+}
+
+impl SampleableShape for Cone {
+    fn area(&self) -> f32 {
+        PI * self.r * self.r.hypot(self.h)
+    }
+
+    fn sample(&self, u: Vec2) -> SurfaceSample {
+        // The area within a distance s of the apex grows as s².
+        let s = u.y.sqrt();
+        let (sin_phi, cos_phi) = (TAU * u.x).sin_cos();
+        let p = Vec3::new(
+            self.r * s * cos_phi,
+            self.r * s * sin_phi,
+            self.h * (1.0 - s),
+        );
+        let n = Vec3::new(cos_phi, sin_phi, self.r / self.h).normalize();
+        SurfaceSample {
+            p: self.frame.transform_point(p),
+            n: self.frame.transform_vector(n),
+            uv: self.uv(p),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::shapes::tests::check_samples;
+
+    #[test]
+    fn hits_the_side() {
+        // Half-angle of 45 degrees at the apex.
+        let cone = Cone::new(Vec3::ZERO, Vec3::Z, 1.0);
+        let record = cone
+            .intersect(&Ray::new(Vec3::new(5.0, 0.0, 0.5), -Vec3::X))
+            .unwrap();
+        assert!((record.t - 4.5).abs() < 1e-5);
+        let expected = Vec3::new(1.0, 0.0, 1.0).normalize();
+        assert!(record.n.abs_diff_eq(expected, 1e-5));
+        assert!((record.uv.y - 0.5).abs() < 1e-5);
+        // Missing the apex, and the other nappe of the double cone.
+        assert!(!cone.intersect_p(&Ray::new(Vec3::new(5.0, 0.0, 1.1), -Vec3::X)));
+        assert!(!cone.intersect_p(&Ray::new(Vec3::new(5.0, 0.0, 1.5), -Vec3::X)));
+        assert!((cone.area() - PI * 2.0f32.sqrt()).abs() < 1e-5);
+        check_samples(&cone);
+    }
+}
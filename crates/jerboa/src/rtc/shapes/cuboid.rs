@@ -0,0 +1,133 @@
+use crate::rtc::{aabb::Aabb, ray::Ray, IntersectRecord, SampleableShape, Shape, SurfaceSample};
+use glam::{Vec2, Vec3};
+
+/// Axis-aligned box.
+pub struct Cuboid {
+    pub bounds: Aabb,
+}
+
+impl Cuboid {
+    /// Creates the box with opposite corners `a` and `b`.
+    pub fn new(a: Vec3, b: Vec3) -> Self {
+        Self {
+            bounds: Aabb::new(a, b),
+        }
+    }
+
+    /// Distance along the ray at which it enters the box, or leaves it if it
+    /// starts inside.
+    fn hit_distance(&self, ray: &Ray) -> Option<f32> {
+        let (t0, t1) = self.bounds.intersect_p(ray, f32::INFINITY)?;
+        if t0 > 0.0 {
+            Some(t0)
+        } else {
+            (t1 > 0.0).then_some(t1)
+        }
+    }
+
+    /// Axis and outward normal of the face nearest to the point `p` on the
+    /// surface, with the coordinates of `p` on that face, both in `[0, 1]`,
+    /// along the two other axes in cyclic order.
+    fn face(&self, p: Vec3) -> (usize, Vec3, Vec2) {
+        let offset = self.bounds.offset(p);
+        let centred = (offset - Vec3::splat(0.5)) * self.bounds.diagonal();
+        let distance = self.bounds.diagonal() * 0.5 - centred.abs();
+        let axis = if distance.x <= distance.y && distance.x <= distance.z {
+            0
+        } else if distance.y <= distance.z {
+            1
+        } else {
+            2
+        };
+        let mut n = Vec3::ZERO;
+        n[axis] = centred[axis].signum();
+        let uv = Vec2::new(offset[(axis + 1) % 3], offset[(axis + 2) % 3]);
+        (axis, n, uv)
+    }
+}
+
+impl Shape for Cuboid {
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        self.hit_distance(ray).is_some()
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<IntersectRecord> {
+        let t = self.hit_distance(ray)?;
+        let mut p = ray.o + ray.d * t;
+        let (axis, n, uv) = self.face(p);
+        // Moved onto the plane of its face, the point is on the surface.
+        p[axis] = if n[axis] > 0.0 {
+            self.bounds.max[axis]
+        } else {
+            self.bounds.min[axis]
+        };
+        Some(IntersectRecord {
+            t,
+            p,
+            p_error: Vec3::ZERO,
+            n,
+            uv,
+            time: ray.time,
+        })
+    }
+
+    fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+}
+
+impl SampleableShape for Cuboid {
+    fn area(&self) -> f32 {
+        self.bounds.surface_area()
+    }
+
+    fn sample(&self, u: Vec2) -> SurfaceSample {
+        let d = self.bounds.diagonal();
+        // Face areas, each shared by two opposite faces.
+        let areas = [d.y * d.z, d.z * d.x, d.x * d.y];
+        let mut x = u.x * 2.0 * (areas[0] + areas[1] + areas[2]);
+        let mut face = 0;
+        while face < 5 && x >= areas[face / 2] {
+            x -= areas[face / 2];
+            face += 1;
+        }
+        let (axis, side) = (face / 2, face % 2);
+        let uv = Vec2::new((x / areas[axis]).clamp(0.0, 1.0), u.y);
+        let mut offset = Vec3::ZERO;
+        offset[axis] = side as f32;
+        offset[(axis + 1) % 3] = uv.x;
+        offset[(axis + 2) % 3] = uv.y;
+        let mut n = Vec3::ZERO;
+        n[axis] = if side == 0 { -1.0 } else { 1.0 };
+        SurfaceSample {
+            p: self.bounds.min + offset * d,
+            n,
+            uv,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::shapes::tests::check_samples;
+
+    #[test]
+    fn hits_faces() {
+        let cuboid = Cuboid::new(Vec3::new(1.0, 2.0, 3.0), Vec3::new(-1.0, 0.0, 0.0));
+        let record = cuboid
+            .intersect(&Ray::new(Vec3::new(0.5, 1.0, 5.0), -Vec3::Z))
+            .unwrap();
+        assert_eq!(record.t, 2.0);
+        assert_eq!(record.n, Vec3::Z);
+        assert!(record.uv.abs_diff_eq(Vec2::new(0.75, 0.5), 1e-6));
+        // Leaving the box from inside.
+        let record = cuboid
+            .intersect(&Ray::new(Vec3::new(0.0, 1.0, 1.0), -Vec3::Y))
+            .unwrap();
+        assert_eq!((record.t, record.n), (1.0, -Vec3::Y));
+        assert!(!cuboid.intersect_p(&Ray::new(Vec3::new(0.0, 1.0, 5.0), Vec3::Z)));
+        assert_eq!(cuboid.area(), 2.0 * (4.0 + 6.0 + 6.0));
+        check_samples(&cuboid);
+    }
+}
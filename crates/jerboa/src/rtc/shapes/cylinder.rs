@@ -0,0 +1,132 @@
+use crate::{
+    core::rounding::mre,
+    rtc::{
+        aabb::Aabb,
+        ray::Ray,
+        shapes::{azimuth, local_frame, solve_quadratic},
+        transform::Transform,
+        IntersectRecord, SampleableShape, Shape, SurfaceSample,
+    },
+};
+use glam::{Vec2, Vec3};
+use std::f32::consts::TAU;
+
+/// Cylinder around a segment, open at both ends; [`Disk`]s can close it.
+///
+/// [`Disk`]: crate::rtc::shapes::Disk
+pub struct Cylinder {
+    /// Frame centred on the first end, whose z axis runs along the segment.
+    frame: Transform,
+    r: f32,
+    h: f32,
+}
+
+impl Cylinder {
+    /// Creates the cylinder of radius `r` around the segment from `p0` to
+    /// `p1`.
+    pub fn new(p0: Vec3, p1: Vec3, r: f32) -> Self {
+        let h = p0.distance(p1);
+        Self {
+            frame: local_frame(p0, (p1 - p0) / h),
+            r,
+            h,
+        }
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.r
+    }
+
+    pub fn height(&self) -> f32 {
+        self.h
+    }
+
+    /// Distance along the ray and local position of the closest hit.
+    fn hit(&self, ray: &Ray) -> Option<(f32, Vec3)> {
+        let Ray { o, d, .. } = self.frame.inverse().transform_ray(ray);
+        let a = d.x * d.x + d.y * d.y;
+        let b = 2.0 * (o.x * d.x + o.y * d.y);
+        let c = o.x * o.x + o.y * o.y - self.r * self.r;
+        let (t0, t1) = solve_quadratic(a, b, c)?;
+        [t0, t1]
+            .into_iter()
+            .filter(|&t| t > 0.0)
+            .map(|t| (t, o + d * t))
+            .find(|(_, p)| (0.0..=self.h).contains(&p.z))
+    }
+
+    /// Angle around the axis and height, relative to the length.
+    fn uv(&self, p: Vec3) -> Vec2 {
+        Vec2::new(azimuth(p), p.z / self.h)
+    }
+}
+
+impl Shape for Cylinder {
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        self.hit(ray).is_some()
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<IntersectRecord> {
+        let (t, p) = self.hit(ray)?;
+        let n = Vec3::new(p.x, p.y, 0.0).normalize();
+        // Reprojected onto the surface, with the rounding error of the
+        // reprojection.
+        let p = Vec3::new(n.x * self.r, n.y * self.r, p.z);
+        let p_error = Vec3::new(p.x, p.y, 0.0).abs() * mre(4);
+        let (p_world, p_error) = self.frame.transform_point_with_error(p, p_error);
+        Some(IntersectRecord {
+            t,
+            p: p_world,
+            p_error,
+            n: self.frame.transform_vector(n),
+            uv: self.uv(p),
+            time: ray.time,
+        })
+    }
+
+    fn bounds(&self) -> Aabb {
+        let r = Vec3::new(self.r, self.r, 0.0);
+        self.frame
+            .transform_bounds(&Aabb::new(-r, r + Vec3::Z * self.h))
+    }
+}
+
+impl SampleableShape for Cylinder {
+    fn area(&self) -> f32 {
+        TAU * self.r * self.h
+    }
+
+    fn sample(&self, u: Vec2) -> SurfaceSample {
+        let (sin_phi, cos_phi) = (TAU * u.x).sin_cos();
+        let n = Vec3::new(cos_phi, sin_phi, 0.0);
+        let p = n * self.r + Vec3::Z * (self.h * u.y);
+        SurfaceSample {
+            p: self.frame.transform_point(p),
+            n: self.frame.transform_vector(n),
+            uv: self.uv(p),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::shapes::tests::check_samples;
+
+    #[test]
+    fn hits_the_side() {
+        let cylinder = Cylinder::new(Vec3::ZERO, Vec3::new(0.0, 4.0, 0.0), 1.0);
+        let record = cylinder
+            .intersect(&Ray::new(Vec3::new(0.0, 3.0, 5.0), -Vec3::Z))
+            .unwrap();
+        assert!((record.t - 4.0).abs() < 1e-5);
+        assert!(record.n.abs_diff_eq(Vec3::Z, 1e-5));
+        assert!((record.uv.y - 0.75).abs() < 1e-5);
+        // From inside, through the open end.
+        let record = cylinder.intersect(&Ray::new(Vec3::ZERO, Vec3::X)).unwrap();
+        assert!((record.t - 1.0).abs() < 1e-5);
+        assert!(!cylinder.intersect_p(&Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::X)));
+        assert!(!cylinder.intersect_p(&Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::Y)));
+        check_samples(&cylinder);
+    }
+}
@@ -0,0 +1,113 @@
+use crate::rtc::{
+    aabb::Aabb,
+    ray::Ray,
+    sampling::concentric_sample_disk,
+    shapes::{azimuth, local_frame},
+    transform::Transform,
+    IntersectRecord, SampleableShape, Shape, SurfaceSample,
+};
+use glam::{Vec2, Vec3};
+use std::f32::consts::PI;
+
+/// Two-sided disk.
+pub struct Disk {
+    /// Frame centred on the disk, whose z axis is its normal.
+    frame: Transform,
+    r: f32,
+}
+
+impl Disk {
+    pub fn new(c: Vec3, n: Vec3, r: f32) -> Self {
+        Self {
+            frame: local_frame(c, n.normalize()),
+            r,
+        }
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.r
+    }
+
+    pub fn normal(&self) -> Vec3 {
+        self.frame.transform_vector(Vec3::Z)
+    }
+
+    /// Distance along the ray and local position of the hit.
+    fn hit(&self, ray: &Ray) -> Option<(f32, Vec3)> {
+        let local = self.frame.inverse().transform_ray(ray);
+        if local.d.z == 0.0 {
+            return None;
+        }
+        let t = -local.o.z / local.d.z;
+        let p = local.o + local.d * t;
+        (t > 0.0 && p.x * p.x + p.y * p.y <= self.r * self.r).then_some((t, p))
+    }
+
+    /// Angle around the centre and distance to it, relative to the radius.
+    fn uv(&self, p: Vec3) -> Vec2 {
+        Vec2::new(azimuth(p), p.truncate().length() / self.r)
+    }
+}
+
+impl Shape for Disk {
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        self.hit(ray).is_some()
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<IntersectRecord> {
+        let (t, mut p) = self.hit(ray)?;
+        // Moved onto the plane of the disk, the point is on the surface.
+        p.z = 0.0;
+        let (p_world, p_error) = self.frame.transform_point_with_error(p, Vec3::ZERO);
+        Some(IntersectRecord {
+            t,
+            p: p_world,
+            p_error,
+            n: self.normal(),
+            uv: self.uv(p),
+            time: ray.time,
+        })
+    }
+
+    fn bounds(&self) -> Aabb {
+        let r = Vec3::new(self.r, self.r, 0.0);
+        self.frame.transform_bounds(&Aabb::new(-r, r))
+    }
+}
+
+impl SampleableShape for Disk {
+    fn area(&self) -> f32 {
+        PI * self.r * self.r
+    }
+
+    fn sample(&self, u: Vec2) -> SurfaceSample {
+        let p = (concentric_sample_disk(u) * self.r).extend(0.0);
+        SurfaceSample {
+            p: self.frame.transform_point(p),
+            n: self.normal(),
+            uv: self.uv(p),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::shapes::tests::check_samples;
+
+    #[test]
+    fn hits_within_radius() {
+        let disk = Disk::new(Vec3::new(0.0, 1.0, 0.0), Vec3::Y, 2.0);
+        let record = disk
+            .intersect(&Ray::new(Vec3::new(1.0, 3.0, 1.0), -Vec3::Y))
+            .unwrap();
+        assert!((record.t - 2.0).abs() < 1e-5);
+        assert!(record.n.abs_diff_eq(Vec3::Y, 1e-6));
+        assert!((record.uv.y - 2.0f32.sqrt() / 2.0).abs() < 1e-5);
+        // Both sides are hit.
+        assert!(disk.intersect_p(&Ray::new(Vec3::new(1.0, -3.0, 1.0), Vec3::Y)));
+        assert!(!disk.intersect_p(&Ray::new(Vec3::new(1.5, 3.0, 1.5), -Vec3::Y)));
+        assert!((disk.area() - 4.0 * PI).abs() < 1e-5);
+        check_samples(&disk);
+    }
+}
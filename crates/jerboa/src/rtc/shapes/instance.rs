@@ -0,0 +1,257 @@
+use crate::rtc::{
+    aabb::Aabb,
+    bvh::{Bvh, TraversalStats},
+    ray::Ray,
+    transform::{AnimatedTransform, Transform},
+    IntersectRecord, Shape,
+};
+use std::sync::Arc;
+
+/// Shapes grouped under a hierarchy of their own, e.g. the triangles of a
+/// mesh, to be instanced as a whole.
+pub struct Aggregate {
+    shapes: Vec<Box<dyn Shape>>,
+    bvh: Bvh,
+    bounds: Aabb,
+}
+
+impl Aggregate {
+    pub fn new(shapes: Vec<Box<dyn Shape>>) -> Self {
+        let bvh = Bvh::new(&shapes);
+        let bounds = shapes
+            .iter()
+            .fold(Aabb::EMPTY, |b, shape| b.union(&shape.bounds()));
+        Self {
+            shapes,
+            bvh,
+            bounds,
+        }
+    }
+
+    pub fn shapes(&self) -> &[Box<dyn Shape>] {
+        &self.shapes
+    }
+
+    /// Closest hit with the index of the shape hit.
+    pub fn intersect_shape(&self, ray: &Ray) -> Option<(usize, IntersectRecord)> {
+        self.bvh
+            .intersect(&self.shapes, ray, &mut TraversalStats::default())
+    }
+}
+
+impl Shape for Aggregate {
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        self.bvh.occluded(
+            &self.shapes,
+            ray,
+            f32::INFINITY,
+            &mut TraversalStats::default(),
+        )
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<IntersectRecord> {
+        self.intersect_shape(ray).map(|(_, record)| record)
+    }
+
+    fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+}
+
+/// Shape placed in the world by a transform from its object space. The
+/// shape is shared, so that it is stored once however many times it is
+/// instanced.
+pub struct Instance {
+    shape: Arc<dyn Shape>,
+
+    /// Transform from object to world space.
+    transform: Transform,
+}
+
+impl Instance {
+    pub fn new(shape: Arc<dyn Shape>, transform: Transform) -> Self {
+        Self { shape, transform }
+    }
+
+    pub fn shape(&self) -> &Arc<dyn Shape> {
+        &self.shape
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+}
+
+impl Shape for Instance {
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        let local = self.transform.inverse().transform_ray(ray);
+        self.shape.intersect_p(&local)
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<IntersectRecord> {
+        intersect_transformed(self.shape.as_ref(), &self.transform, ray)
+    }
+
+    fn bounds(&self) -> Aabb {
+        self.transform.transform_bounds(&self.shape.bounds())
+    }
+}
+
+/// Shape moving along an animated transform, intersected at the time of
+/// each ray. Its bounds cover the whole motion, so that hierarchies built
+/// over it hold at any time.
+pub struct AnimatedInstance {
+    shape: Arc<dyn Shape>,
+
+    /// Transform from object to world space.
+    transform: AnimatedTransform,
+
+    bounds: Aabb,
+}
+
+impl AnimatedInstance {
+    pub fn new(shape: Arc<dyn Shape>, transform: AnimatedTransform) -> Self {
+        let bounds = transform.motion_bounds(&shape.bounds());
+        Self {
+            shape,
+            transform,
+            bounds,
+        }
+    }
+
+    pub fn shape(&self) -> &Arc<dyn Shape> {
+        &self.shape
+    }
+
+    pub fn transform(&self) -> &AnimatedTransform {
+        &self.transform
+    }
+}
+
+impl Shape for AnimatedInstance {
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        let transform = self.transform.interpolate(ray.time);
+        self.shape
+            .intersect_p(&transform.inverse().transform_ray(ray))
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<IntersectRecord> {
+        let transform = self.transform.interpolate(ray.time);
+        intersect_transformed(self.shape.as_ref(), &transform, ray)
+    }
+
+    fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+}
+
+/// Intersects `shape` placed by `transform` with the world space `ray`.
+fn intersect_transformed(
+    shape: &dyn Shape,
+    transform: &Transform,
+    ray: &Ray,
+) -> Option<IntersectRecord> {
+    // The direction of the local ray is not normalised, so that hits are at
+    // the same distance along both rays.
+    let local = transform.inverse().transform_ray(ray);
+    let record = shape.intersect(&local)?;
+    let (p, p_error) = transform.transform_point_with_error(record.p, record.p_error);
+    Some(IntersectRecord {
+        p,
+        p_error,
+        n: transform.transform_normal(record.n).normalize(),
+        ..record
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::{
+        shapes::{Sphere, Triangle},
+        transform::Keyframe,
+    };
+    use glam::{Quat, Vec3};
+
+    #[test]
+    fn transformed_hits() {
+        let sphere: Arc<dyn Shape> = Arc::new(Sphere::new(Vec3::ZERO, 1.0));
+        // Ellipsoid of semi-axes 2, 1 and 1, centred at (0, 0, -5).
+        let transform = Transform::from_translation(Vec3::new(0.0, 0.0, -5.0))
+            * Transform::from_scale(Vec3::new(2.0, 1.0, 1.0));
+        let instance = Instance::new(sphere.clone(), transform);
+        let record = instance
+            .intersect(&Ray::new(Vec3::new(5.0, 0.0, -5.0), -Vec3::X))
+            .unwrap();
+        assert!((record.t - 3.0).abs() < 1e-5);
+        assert!(record.p.abs_diff_eq(Vec3::new(2.0, 0.0, -5.0), 1e-5));
+        assert!(record.n.abs_diff_eq(Vec3::X, 1e-5));
+
+        // On the ellipse x² / 4 + y² = 1, the normal is along (x / 4, y).
+        let p = Vec3::new(2.0f32.sqrt(), 0.5f32.sqrt(), -5.0);
+        let record = instance
+            .intersect(&Ray::new(Vec3::new(p.x, p.y, 0.0), -Vec3::Z))
+            .unwrap();
+        let expected = Vec3::new(p.x / 4.0, p.y, 0.0).normalize();
+        assert!(record.n.abs_diff_eq(expected, 1e-3));
+        assert!(!instance.intersect_p(&Ray::new(Vec3::new(2.1, 0.0, 0.0), -Vec3::Z)));
+        assert!(instance
+            .bounds()
+            .max
+            .abs_diff_eq(Vec3::new(2.0, 1.0, -4.0), 1e-5));
+    }
+
+    #[test]
+    fn instanced_aggregate() {
+        // Unit square in the xy plane, instanced twice as large.
+        let square: Vec<Box<dyn Shape>> = vec![
+            Box::new(Triangle::new(Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0))),
+            Box::new(Triangle::new(Vec3::ZERO, Vec3::new(1.0, 1.0, 0.0), Vec3::Y)),
+        ];
+        let square: Arc<dyn Shape> = Arc::new(Aggregate::new(square));
+        let instances: Vec<Instance> = (0..3)
+            .map(|i| {
+                let transform = Transform::from_translation(Vec3::new(3.0 * i as f32, 0.0, -2.0))
+                    * Transform::from_scale(Vec3::splat(2.0));
+                Instance::new(square.clone(), transform)
+            })
+            .collect();
+        assert_eq!(Arc::strong_count(&square), 4);
+        let hit = |o: Vec3| {
+            let ray = Ray::new(o, -Vec3::Z);
+            instances
+                .iter()
+                .find_map(|instance| instance.intersect(&ray))
+        };
+        let record = hit(Vec3::new(7.5, 1.5, 5.0)).unwrap();
+        assert_eq!(record.t, 7.0);
+        assert_eq!(record.n, Vec3::Z);
+        assert!(hit(Vec3::new(5.5, 1.5, 5.0)).is_none());
+        assert_eq!(
+            instances[2].bounds(),
+            Aabb::new(Vec3::new(6.0, 0.0, -2.0), Vec3::new(8.0, 2.0, -2.0))
+        );
+    }
+
+    #[test]
+    fn moving_sphere() {
+        let sphere: Arc<dyn Shape> = Arc::new(Sphere::new(Vec3::ZERO, 1.0));
+        // Moving from x = 0 to x = 4 while the ray time goes from 0 to 1.
+        let animation = AnimatedTransform::new(vec![
+            Keyframe::new(0.0, Vec3::ONE, Quat::IDENTITY, Vec3::ZERO),
+            Keyframe::new(1.0, Vec3::ONE, Quat::IDENTITY, Vec3::new(4.0, 0.0, 0.0)),
+        ]);
+        let instance = AnimatedInstance::new(sphere, animation);
+        let ray = Ray::new(Vec3::new(2.0, 0.0, 5.0), -Vec3::Z);
+        assert!(!instance.intersect_p(&ray));
+        let record = instance.intersect(&ray.with_time(0.5)).unwrap();
+        assert!((record.t - 4.0).abs() < 1e-5);
+        assert_eq!(record.time, 0.5);
+        assert!(record.n.abs_diff_eq(Vec3::Z, 1e-5));
+        assert!(!instance.intersect_p(&ray.with_time(1.0)));
+        assert_eq!(
+            instance.bounds(),
+            Aabb::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(5.0, 1.0, 1.0))
+        );
+    }
+}
@@ -1,13 +1,168 @@
+mod cone;
+mod cuboid;
+mod cylinder;
+mod disk;
+mod instance;
 mod plane;
+mod quad;
+mod sphere;
+mod torus;
+mod triangle;
 
+pub use cone::Cone;
+pub use cuboid::Cuboid;
+pub use cylinder::Cylinder;
+pub use disk::Disk;
+pub use instance::{Aggregate, AnimatedInstance, Instance};
 pub use plane::Plane;
+pub use quad::Quad;
+pub use sphere::Sphere;
+pub use torus::Torus;
+pub use triangle::Triangle;
 
-use glam::Vec3;
+use crate::rtc::transform::Transform;
+use glam::{Quat, Vec3};
+use std::f32::consts::TAU;
 
-pub struct Sphere {
-    /// The center of the sphere.
-    pub c: Vec3,
+/// Transform from a local frame whose origin is `origin` and whose z axis is
+/// the unit vector `axis`, in which shapes of revolution are defined. The
+/// frame is orthonormal, so that distances along rays are preserved.
+fn local_frame(origin: Vec3, axis: Vec3) -> Transform {
+    Transform::from_translation(origin)
+        * Transform::from_rotation(Quat::from_rotation_arc(Vec3::Z, axis))
+}
+
+/// Angle of the local point `p` around the z axis, as a fraction of a turn.
+fn azimuth(p: Vec3) -> f32 {
+    p.y.atan2(p.x).rem_euclid(TAU) / TAU
+}
+
+/// Roots of a x² + b x + c in increasing order, avoiding the cancellation
+/// between close values.
+fn solve_quadratic(a: f32, b: f32, c: f32) -> Option<(f32, f32)> {
+    let (a, b, c) = (a as f64, b as f64, c as f64);
+    if a == 0.0 {
+        if b == 0.0 {
+            return None;
+        }
+        let t = (-c / b) as f32;
+        return Some((t, t));
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    if q == 0.0 {
+        // b and c are both zero.
+        return Some((0.0, 0.0));
+    }
+    let (t0, t1) = ((q / a) as f32, (c / q) as f32);
+    Some((t0.min(t1), t0.max(t1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::{
+        ray::{offset_ray_origin, Ray},
+        sampler::Pcg32,
+        sampling::uniform_sample_sphere,
+        SampleableShape, Shape,
+    };
+    use glam::Vec2;
+
+    /// Checks that points sampled on `shape` lie inside its bounds and are
+    /// found again, with the same normal and surface coordinates, by rays
+    /// arriving against the normal.
+    pub(super) fn check_samples(shape: &dyn SampleableShape) {
+        let bounds = shape.bounds();
+        for i in 0..64 {
+            let u = Vec2::new((i % 8) as f32 + 0.37, (i / 8) as f32 + 0.61) / 8.0;
+            let sample = shape.sample(u);
+            assert!((sample.n.length() - 1.0).abs() < 1e-4);
+            let slack = Vec3::splat(1e-4);
+            assert!(
+                bounds.min.cmple(sample.p + slack).all()
+                    && bounds.max.cmpge(sample.p - slack).all()
+            );
+            let ray = Ray::new(sample.p + sample.n * 1e-2, -sample.n);
+            let record = shape.intersect(&ray).unwrap();
+            assert!(
+                record.p.abs_diff_eq(sample.p, 1e-3),
+                "{} {}",
+                record.p,
+                sample.p
+            );
+            assert!(
+                record.n.abs_diff_eq(sample.n, 1e-3),
+                "{} {}",
+                record.n,
+                sample.n
+            );
+            assert!(
+                record.uv.abs_diff_eq(sample.uv, 1e-3),
+                "{} {}",
+                record.uv,
+                sample.uv
+            );
+        }
+    }
+
+    #[test]
+    fn spawned_rays_leave_surfaces() {
+        // Far from the origin, where the rounding errors of hit points exceed
+        // any fixed offset suited to the scale of the shapes.
+        let c = Vec3::new(1e5, -3e4, 2e5);
+        let shapes: Vec<Box<dyn Shape>> = vec![
+            Box::new(Triangle::new(
+                c,
+                c + Vec3::new(3.0, 1.0, 0.5),
+                c + Vec3::new(-1.0, 2.0, 1.0),
+            )),
+            Box::new(Quad::new(
+                c,
+                Vec3::new(2.0, 0.3, 1.0),
+                Vec3::new(-0.5, 2.0, 0.7),
+            )),
+            Box::new(Disk::new(c, Vec3::new(1.0, 2.0, 3.0), 2.0)),
+            Box::new(Plane::new(c, Vec3::new(-1.0, 0.5, 2.0))),
+            Box::new(Sphere::new(c, 2.0)),
+            Box::new(Cylinder::new(c, c + Vec3::new(1.0, 1.0, 2.0), 1.0)),
+        ];
+        let mut rng = Pcg32::new(3, 0);
+        let mut next = || Vec2::new(rng.next_f32(), rng.next_f32());
+        let mut n_hits = 0;
+        for shape in &shapes {
+            for _ in 0..2000 {
+                let o = c + uniform_sample_sphere(next()) * 10.0;
+                let aim = c + uniform_sample_sphere(next());
+                let record = match shape.intersect(&Ray::new(o, (aim - o).normalize())) {
+                    Some(record) => record,
+                    None => continue,
+                };
+                n_hits += 1;
+                // Leaving on the side the ray came from.
+                let mut w = uniform_sample_sphere(next());
+                if w.dot(record.n) * (o - record.p).dot(record.n) < 0.0 {
+                    w = -w;
+                }
+                let ray = Ray::new(offset_ray_origin(record.p, record.p_error, record.n, w), w);
+                // Only the far side of the open cylinder can be hit.
+                let hit = shape.intersect(&ray);
+                assert!(hit.is_none_or(|hit| hit.t > 0.1), "{ray:?}");
+            }
+        }
+        assert!(n_hits > 5000);
+    }
 
-    /// The radius of the sphere.
-    pub r: f32,
+    #[test]
+    fn quadratic_roots() {
+        assert_eq!(solve_quadratic(1.0, -3.0, 2.0), Some((1.0, 2.0)));
+        assert_eq!(solve_quadratic(0.0, 2.0, -1.0), Some((0.5, 0.5)));
+        assert_eq!(solve_quadratic(1.0, 0.0, 1.0), None);
+        // Roots of very different magnitudes.
+        let (t0, t1) = solve_quadratic(1.0, -1e4, 1.0).unwrap();
+        assert!((t0 - 1e-4).abs() < 1e-9 && (t1 - 1e4).abs() < 1e-2);
+    }
 }
@@ -1,5 +1,8 @@
-use crate::rtc::{IntersectRecord, ray::Ray, Shape};
-use glam::Vec3;
+use crate::{
+    core::rounding::ErrorFloat,
+    rtc::{aabb::Aabb, ray::Ray, sampling::coordinate_system, IntersectRecord, Shape},
+};
+use glam::{Vec2, Vec3};
 
 pub struct Plane {
     /// A point on the plane.
@@ -9,28 +12,79 @@ pub struct Plane {
     pub n: Vec3,
 }
 
-// todo: floating point error analysis
+impl Plane {
+    pub fn new(p: Vec3, n: Vec3) -> Self {
+        Self {
+            p,
+            n: n.normalize(),
+        }
+    }
+}
+
 impl Shape for Plane {
     fn intersect_p(&self, ray: &Ray) -> bool {
+        let denom = self.n.dot(ray.d);
         if denom >= 0.0 {
             // Ray is on the wrong side of the plane.
             false
         } else {
-            let t = self.normal.dot(self.p - ray.o) / denom;
+            let t = self.n.dot(self.p - ray.o) / denom;
             t >= 0.0
         }
     }
+
     fn intersect(&self, ray: &Ray) -> Option<IntersectRecord> {
-        let denom = self.normal.dot(ray.d);
-        if denom > 0.0 {
+        let denom = self.n.dot(ray.d);
+        if denom >= 0.0 {
             return None;
         }
-        let t = self.point.dot(self.normal) / denom;
+        let t = self.n.dot(self.p - ray.o) / denom;
         if t < 0.0 {
             return None;
         }
-        let p = ray.o + ray.d * t;
-        let n = self.normal;
-        Some(IntersectRecord { t, p, n })
+        // Projected onto the plane, as the point along the ray drifts away
+        // from it at grazing angles, keeping track of the rounding errors.
+        let along = ray.o + ray.d * t;
+        let e = ErrorFloat::new;
+        let dot = |a: [ErrorFloat; 3], b: [ErrorFloat; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+        let n = self.n.to_array().map(e);
+        let offset = [0, 1, 2].map(|i| e(along[i]) - e(self.p[i]));
+        let s = dot(n, offset) / dot(n, n);
+        let projected = [0, 1, 2].map(|i| e(along[i]) - n[i] * s);
+        let p = Vec3::from(projected.map(ErrorFloat::value));
+        let p_error = Vec3::from(projected.map(ErrorFloat::error));
+        let n = self.n;
+        // Coordinates in a frame of the plane centred on `self.p`.
+        let (s, r) = coordinate_system(n);
+        let uv = Vec2::new(s.dot(p - self.p), r.dot(p - self.p));
+        Some(IntersectRecord {
+            t,
+            p,
+            p_error,
+            n,
+            uv,
+            time: ray.time,
+        })
+    }
+
+    fn bounds(&self) -> Aabb {
+        Aabb::INFINITE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_is_measured_from_ray_origin() {
+        let plane = Plane::new(Vec3::new(1.0, 1.0, -2.0), Vec3::Y);
+        let ray = Ray::new(Vec3::new(2.0, 5.0, 3.0), -Vec3::Y);
+        let record = plane.intersect(&ray).unwrap();
+        assert_eq!(record.t, 4.0);
+        assert_eq!(record.p, Vec3::new(2.0, 1.0, 3.0));
+        assert!(plane.intersect_p(&ray));
+        assert!(plane.intersect(&Ray::new(Vec3::ZERO, -Vec3::Y)).is_none());
+        assert!(!plane.intersect_p(&Ray::new(Vec3::new(0.0, 2.0, 0.0), Vec3::Y)));
     }
 }
@@ -0,0 +1,112 @@
+use crate::{
+    core::rounding::mre,
+    rtc::{aabb::Aabb, ray::Ray, IntersectRecord, SampleableShape, Shape, SurfaceSample},
+};
+use glam::{Vec2, Vec3};
+
+/// Two-sided parallelogram spanned by two edges from a corner.
+pub struct Quad {
+    /// The corner at which both edges start.
+    pub p: Vec3,
+
+    pub e0: Vec3,
+    pub e1: Vec3,
+}
+
+impl Quad {
+    pub fn new(p: Vec3, e0: Vec3, e1: Vec3) -> Self {
+        Self { p, e0, e1 }
+    }
+
+    /// Normal given by the order of the edges.
+    pub fn normal(&self) -> Vec3 {
+        self.e0.cross(self.e1).normalize()
+    }
+
+    /// Distance along the ray and coordinates of the hit along both edges.
+    fn hit(&self, ray: &Ray) -> Option<(f32, Vec2)> {
+        let n = self.e0.cross(self.e1);
+        let denom = n.dot(ray.d);
+        if denom == 0.0 {
+            return None;
+        }
+        let t = n.dot(self.p - ray.o) / denom;
+        if t <= 0.0 {
+            return None;
+        }
+        // Coordinates of q = a e0 + b e1, from the cross products of q with
+        // the edges.
+        let q = ray.o + ray.d * t - self.p;
+        let w = n / n.length_squared();
+        let uv = Vec2::new(w.dot(q.cross(self.e1)), w.dot(self.e0.cross(q)));
+        let inside = (0.0..=1.0).contains(&uv.x) && (0.0..=1.0).contains(&uv.y);
+        inside.then_some((t, uv))
+    }
+}
+
+impl Shape for Quad {
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        self.hit(ray).is_some()
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<IntersectRecord> {
+        let (t, uv) = self.hit(ray)?;
+        // The point given by its coordinates along the edges is on the plane
+        // of the quad, up to the rounding of the sum.
+        let (a, b) = (self.e0 * uv.x, self.e1 * uv.y);
+        Some(IntersectRecord {
+            t,
+            p: self.p + a + b,
+            p_error: (self.p.abs() + a.abs() + b.abs()) * mre(3),
+            n: self.normal(),
+            uv,
+            time: ray.time,
+        })
+    }
+
+    fn bounds(&self) -> Aabb {
+        Aabb::new(self.p, self.p + self.e0 + self.e1)
+            .union_point(self.p + self.e0)
+            .union_point(self.p + self.e1)
+    }
+}
+
+impl SampleableShape for Quad {
+    fn area(&self) -> f32 {
+        self.e0.cross(self.e1).length()
+    }
+
+    fn sample(&self, u: Vec2) -> SurfaceSample {
+        SurfaceSample {
+            p: self.p + self.e0 * u.x + self.e1 * u.y,
+            n: self.normal(),
+            uv: u,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::shapes::tests::check_samples;
+
+    #[test]
+    fn hits_the_parallelogram() {
+        let quad = Quad::new(
+            Vec3::ZERO,
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+        );
+        let record = quad
+            .intersect(&Ray::new(Vec3::new(2.5, 0.5, 3.0), -Vec3::Z))
+            .unwrap();
+        assert_eq!(record.t, 3.0);
+        assert_eq!(record.n, Vec3::Z);
+        assert!(record.uv.abs_diff_eq(Vec2::new(1.0, 0.5), 1e-6));
+        assert!(quad.intersect_p(&Ray::new(Vec3::new(0.5, 0.4, -3.0), Vec3::Z)));
+        // Inside the bounds, outside of the slanted edge.
+        assert!(!quad.intersect_p(&Ray::new(Vec3::new(0.2, 0.8, 3.0), -Vec3::Z)));
+        assert_eq!(quad.area(), 2.0);
+        check_samples(&quad);
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{
+    core::rounding::mre,
+    rtc::{
+        aabb::Aabb, ray::Ray, sampling::uniform_sample_sphere, IntersectRecord, SampleableShape,
+        Shape, SurfaceSample,
+    },
+};
+use glam::{Vec2, Vec3};
+use std::f32::consts::{PI, TAU};
+
+pub struct Sphere {
+    /// The center of the sphere.
+    pub c: Vec3,
+
+    /// The radius of the sphere.
+    pub r: f32,
+}
+
+impl Sphere {
+    pub fn new(c: Vec3, r: f32) -> Self {
+        Self { c, r }
+    }
+
+    /// Closest positive distance along the ray at which it enters or leaves
+    /// the sphere.
+    fn hit_distance(&self, ray: &Ray) -> Option<f32> {
+        // Solves |o + t d - c|² = r² with the half-b formulation, computing
+        // the discriminant from the distance of the centre to the line to
+        // limit cancellation.
+        let oc = ray.o - self.c;
+        let a = ray.d.length_squared();
+        let half_b = oc.dot(ray.d);
+        let to_line = oc - ray.d * (half_b / a);
+        let discriminant = a * (self.r * self.r - to_line.length_squared());
+        if discriminant < 0.0 {
+            return None;
+        }
+        // Roots q / a and c / q, avoiding the subtraction of close values.
+        let q = -(half_b + half_b.signum() * discriminant.sqrt());
+        if q == 0.0 {
+            return None;
+        }
+        let c = oc.length_squared() - self.r * self.r;
+        let (t0, t1) = (q / a, c / q);
+        let (t0, t1) = (t0.min(t1), t0.max(t1));
+        if t0 > 0.0 {
+            Some(t0)
+        } else if t1 > 0.0 {
+            Some(t1)
+        } else {
+            None
+        }
+    }
+
+    /// Longitude and colatitude of the unit normal `n` around the z axis, in
+    /// [0, 1].
+    fn uv(n: Vec3) -> Vec2 {
+        let phi = n.y.atan2(n.x).rem_euclid(TAU);
+        let theta = n.z.clamp(-1.0, 1.0).acos();
+        Vec2::new(phi / TAU, theta / PI)
+    }
+}
+
+impl Shape for Sphere {
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        self.hit_distance(ray).is_some()
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<IntersectRecord> {
+        let t = self.hit_distance(ray)?;
+        // Reprojected onto the surface, with the rounding errors of the
+        // reprojection and of the translation by the centre.
+        let q = ray.o + ray.d * t - self.c;
+        let q = q * (self.r / q.length());
+        let n = q / self.r;
+        Some(IntersectRecord {
+            t,
+            p: self.c + q,
+            p_error: (q.abs() + self.c.abs()) * mre(6),
+            n,
+            uv: Self::uv(n),
+            time: ray.time,
+        })
+    }
+
+    fn bounds(&self) -> Aabb {
+        Aabb::new(self.c - Vec3::splat(self.r), self.c + Vec3::splat(self.r))
+    }
+}
+
+impl SampleableShape for Sphere {
+    fn area(&self) -> f32 {
+        4.0 * PI * self.r * self.r
+    }
+
+    fn sample(&self, u: Vec2) -> SurfaceSample {
+        let n = uniform_sample_sphere(u);
+        SurfaceSample {
+            p: self.c + n * self.r,
+            n,
+            uv: Self::uv(n),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::shapes::tests::check_samples;
+
+    #[test]
+    fn hits_from_outside_and_inside() {
+        let sphere = Sphere::new(Vec3::new(0.0, 0.0, -5.0), 1.0);
+        let record = sphere.intersect(&Ray::new(Vec3::ZERO, -Vec3::Z)).unwrap();
+        assert!((record.t - 4.0).abs() < 1e-5);
+        assert!(record.n.abs_diff_eq(Vec3::Z, 1e-5));
+        let inside = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::X);
+        let record = sphere.intersect(&inside).unwrap();
+        assert!((record.t - 1.0).abs() < 1e-5);
+        assert!(record.n.abs_diff_eq(Vec3::X, 1e-5));
+        assert!(!sphere.intersect_p(&Ray::new(Vec3::ZERO, Vec3::Z)));
+        assert!(!sphere.intersect_p(&Ray::new(Vec3::new(0.0, 2.0, 0.0), -Vec3::Z)));
+        check_samples(&sphere);
+    }
+}
@@ -0,0 +1,275 @@
+use crate::{
+    core::rounding::mre,
+    rtc::{
+        aabb::Aabb,
+        ray::Ray,
+        shapes::{azimuth, local_frame},
+        transform::Transform,
+        IntersectRecord, SampleableShape, Shape, SurfaceSample,
+    },
+};
+use glam::{Vec2, Vec3};
+use std::f32::consts::TAU;
+
+/// Ring torus, swept by a circle of minor radius around a circle of larger
+/// major radius.
+pub struct Torus {
+    /// Frame centred on the torus, whose z axis is its axis of revolution.
+    frame: Transform,
+    major: f32,
+    minor: f32,
+}
+
+impl Torus {
+    pub fn new(c: Vec3, axis: Vec3, major: f32, minor: f32) -> Self {
+        debug_assert!(minor < major, "the torus must not self-intersect");
+        Self {
+            frame: local_frame(c, axis.normalize()),
+            major,
+            minor,
+        }
+    }
+
+    pub fn major_radius(&self) -> f32 {
+        self.major
+    }
+
+    pub fn minor_radius(&self) -> f32 {
+        self.minor
+    }
+
+    /// Distance along the ray and local position of the closest hit.
+    fn hit(&self, ray: &Ray) -> Option<(f32, Vec3)> {
+        let local = self.frame.inverse().transform_ray(ray);
+        // The quartic is solved along a unit direction, for its coefficients
+        // to have comparable magnitudes.
+        let length = local.d.length() as f64;
+        let (o, d) = (local.o.as_dvec3(), local.d.as_dvec3() / length);
+        let (r2, big_r2) = ((self.minor as f64).powi(2), (self.major as f64).powi(2));
+        // (|p|² + R² - r²)² = 4 R² (x² + y²), with |d| = 1.
+        let k = o.dot(d);
+        let a = o.length_squared() + big_r2 - r2;
+        let coefficients = [
+            4.0 * k,
+            4.0 * k * k + 2.0 * a - 4.0 * big_r2 * (d.x * d.x + d.y * d.y),
+            4.0 * k * a - 8.0 * big_r2 * (o.x * d.x + o.y * d.y),
+            a * a - 4.0 * big_r2 * (o.x * o.x + o.y * o.y),
+        ];
+        let (roots, n) = solve_quartic(coefficients);
+        let t = roots[..n].iter().copied().find(|&t| t > 0.0)?;
+        let p = o + d * t;
+        Some(((t / length) as f32, p.as_vec3()))
+    }
+
+    /// Outward normal at the local point `p`, pointing away from the major
+    /// circle.
+    fn normal(&self, p: Vec3) -> Vec3 {
+        let ring = Vec3::new(p.x, p.y, 0.0).normalize_or_zero() * self.major;
+        (p - ring).normalize()
+    }
+
+    /// Angles around the axis of revolution and around the tube.
+    fn uv(&self, p: Vec3) -> Vec2 {
+        let rho = p.x.hypot(p.y);
+        let theta = p.z.atan2(rho - self.major).rem_euclid(TAU);
+        Vec2::new(azimuth(p), theta / TAU)
+    }
+}
+
+impl Shape for Torus {
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        self.hit(ray).is_some()
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<IntersectRecord> {
+        let (t, p) = self.hit(ray)?;
+        // Reprojected onto the tube around the closest point of the major
+        // circle, with the rounding errors of both points.
+        let ring = Vec3::new(p.x, p.y, 0.0).normalize_or_zero() * self.major;
+        let tube = (p - ring).normalize() * self.minor;
+        let p = ring + tube;
+        let p_error = (ring.abs() + tube.abs()) * mre(10);
+        let (p_world, p_error) = self.frame.transform_point_with_error(p, p_error);
+        Some(IntersectRecord {
+            t,
+            p: p_world,
+            p_error,
+            n: self.frame.transform_vector(self.normal(p)),
+            uv: self.uv(p),
+            time: ray.time,
+        })
+    }
+
+    fn bounds(&self) -> Aabb {
+        let extent = Vec3::new(self.major + self.minor, self.major + self.minor, self.minor);
+        self.frame.transform_bounds(&Aabb::new(-extent, extent))
+    }
+}
+
+impl SampleableShape for Torus {
+    fn area(&self) -> f32 {
+        TAU * TAU * self.major * self.minor
+    }
+
+    fn sample(&self, u: Vec2) -> SurfaceSample {
+        // The area around the tube angle θ is proportional to R + r cos θ,
+        // whose integral R θ + r sin θ is inverted numerically.
+        use std::f64::consts::TAU;
+        let (big_r, r) = (self.major as f64, self.minor as f64);
+        let target = TAU * big_r * u.y as f64;
+        let (mut lo, mut hi) = (0.0, TAU);
+        let mut theta = u.y as f64 * TAU;
+        for _ in 0..32 {
+            let f = big_r * theta + r * theta.sin() - target;
+            if f.abs() < 1e-9 {
+                break;
+            }
+            if f > 0.0 {
+                hi = theta;
+            } else {
+                lo = theta;
+            }
+            // Newton steps, falling back to bisection when leaving the
+            // bracket.
+            theta -= f / (big_r + r * theta.cos());
+            if !(lo..=hi).contains(&theta) {
+                theta = 0.5 * (lo + hi);
+            }
+        }
+        let (sin_theta, cos_theta) = (theta as f32).sin_cos();
+        let (sin_phi, cos_phi) = (std::f32::consts::TAU * u.x).sin_cos();
+        let n = Vec3::new(cos_theta * cos_phi, cos_theta * sin_phi, sin_theta);
+        let p = Vec3::new(self.major * cos_phi, self.major * sin_phi, 0.0) + n * self.minor;
+        SurfaceSample {
+            p: self.frame.transform_point(p),
+            n: self.frame.transform_vector(n),
+            uv: self.uv(p),
+        }
+    }
+}
+
+/// Real roots of x⁴ + b x³ + c x² + d x + e, given as `[b, c, d, e]`, in
+/// increasing order, with their number. Solved with Ferrari's method, then
+/// refined by Newton iterations on the quartic.
+fn solve_quartic([b, c, d, e]: [f64; 4]) -> ([f64; 4], usize) {
+    // Depressed quartic y⁴ + p y² + q y + r, with x = y - b / 4.
+    let shift = -0.25 * b;
+    let b2 = b * b;
+    let p = c - 0.375 * b2;
+    let q = d - 0.5 * b * c + 0.125 * b2 * b;
+    let r = e - 0.25 * b * d + b2 * c / 16.0 - 3.0 * b2 * b2 / 256.0;
+
+    let mut roots = [0.0; 4];
+    let mut n = 0;
+    let push_quadratic = |qb: f64, qc: f64, roots: &mut [f64; 4], n: &mut usize| {
+        let discriminant = qb * qb - 4.0 * qc;
+        if discriminant >= 0.0 {
+            let s = discriminant.sqrt();
+            roots[*n] = 0.5 * (-qb - s);
+            roots[*n + 1] = 0.5 * (-qb + s);
+            *n += 2;
+        }
+    };
+    if q.abs() < 1e-12 * (1.0 + p.abs() + r.abs()) {
+        // Biquadratic: y² are the roots of z² + p z + r.
+        let discriminant = p * p - 4.0 * r;
+        if discriminant >= 0.0 {
+            let s = discriminant.sqrt();
+            for z in [0.5 * (-p - s), 0.5 * (-p + s)] {
+                if z >= 0.0 {
+                    roots[n] = -z.sqrt();
+                    roots[n + 1] = z.sqrt();
+                    n += 2;
+                }
+            }
+        }
+    } else {
+        // With m a positive root of the resolvent cubic, the quartic splits
+        // into y² ± s y + p / 2 + m ∓ q / (2 s), with s = √(2m).
+        let m = largest_cubic_root(p, 0.25 * p * p - r, -0.125 * q * q);
+        let s = (2.0 * m).sqrt();
+        push_quadratic(s, 0.5 * p + m - q / (2.0 * s), &mut roots, &mut n);
+        push_quadratic(-s, 0.5 * p + m + q / (2.0 * s), &mut roots, &mut n);
+    }
+
+    for root in &mut roots[..n] {
+        *root += shift;
+        for _ in 0..2 {
+            let x = *root;
+            let f = (((x + b) * x + c) * x + d) * x + e;
+            let df = ((4.0 * x + 3.0 * b) * x + 2.0 * c) * x + d;
+            if df != 0.0 {
+                *root = x - f / df;
+            }
+        }
+    }
+    roots[..n].sort_by(|a, b| a.total_cmp(b));
+    (roots, n)
+}
+
+/// Largest real root of x³ + a x² + b x + c.
+fn largest_cubic_root(a: f64, b: f64, c: f64) -> f64 {
+    // Depressed cubic t³ + p t + q, with x = t - a / 3.
+    let shift = -a / 3.0;
+    let p = b - a * a / 3.0;
+    let q = 2.0 * a * a * a / 27.0 - a * b / 3.0 + c;
+    let discriminant = 0.25 * q * q + p * p * p / 27.0;
+    let t = if discriminant > 0.0 {
+        // A single real root (Cardano).
+        let s = discriminant.sqrt();
+        (-0.5 * q + s).cbrt() + (-0.5 * q - s).cbrt()
+    } else {
+        // Three real roots (trigonometric method), the largest being the
+        // first.
+        let rho = (-p / 3.0).sqrt();
+        if rho == 0.0 {
+            0.0
+        } else {
+            let cos = (-0.5 * q / (rho * rho * rho)).clamp(-1.0, 1.0);
+            2.0 * rho * (cos.acos() / 3.0).cos()
+        }
+    };
+    t + shift
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::shapes::tests::check_samples;
+
+    #[test]
+    fn quartic_roots() {
+        // (x - 1)(x + 2)(x - 3)(x - 0.5)
+        let (roots, n) = solve_quartic([-2.5, -4.0, 8.5, -3.0]);
+        assert_eq!(n, 4);
+        for (root, expected) in roots.iter().zip([-2.0, 0.5, 1.0, 3.0]) {
+            assert!((root - expected).abs() < 1e-9, "{roots:?}");
+        }
+        // (x² + 1)(x - 2)², a double root only.
+        let (roots, n) = solve_quartic([-4.0, 5.0, -4.0, 4.0]);
+        assert!(n >= 1 && roots[..n].iter().all(|r| (r - 2.0).abs() < 1e-4));
+        // x⁴ + 1 has no real root.
+        assert_eq!(solve_quartic([0.0, 0.0, 0.0, 1.0]).1, 0);
+    }
+
+    #[test]
+    fn hits_the_tube() {
+        let torus = Torus::new(Vec3::ZERO, Vec3::Z, 2.0, 0.5);
+        // Along the x axis, through the tube, the hole and the tube again.
+        let record = torus
+            .intersect(&Ray::new(Vec3::new(5.0, 0.0, 0.0), -Vec3::X))
+            .unwrap();
+        assert!((record.t - 2.5).abs() < 1e-4);
+        assert!(record.n.abs_diff_eq(Vec3::X, 1e-4));
+        let record = torus.intersect(&Ray::new(Vec3::ZERO, Vec3::X)).unwrap();
+        assert!((record.t - 1.5).abs() < 1e-4);
+        assert!(record.n.abs_diff_eq(-Vec3::X, 1e-4));
+        assert!((record.uv.y - 0.5).abs() < 1e-4);
+        // Through the hole, and above the torus.
+        assert!(!torus.intersect_p(&Ray::new(Vec3::new(0.0, 0.0, 5.0), -Vec3::Z)));
+        assert!(!torus.intersect_p(&Ray::new(Vec3::new(5.0, 0.0, 0.6), -Vec3::X)));
+        // Grazing the top of the tube.
+        assert!(torus.intersect_p(&Ray::new(Vec3::new(5.0, 2.0, 0.49), -Vec3::X)));
+        check_samples(&torus);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{
+    core::rounding::mre,
+    rtc::{
+        aabb::Aabb, ray::Ray, sampling::uniform_sample_triangle, IntersectRecord, SampleableShape,
+        Shape, SurfaceSample,
+    },
+};
+use glam::{Vec2, Vec3};
+
+/// Two-sided triangle.
+pub struct Triangle {
+    pub p: [Vec3; 3],
+}
+
+impl Triangle {
+    pub fn new(p0: Vec3, p1: Vec3, p2: Vec3) -> Self {
+        Self { p: [p0, p1, p2] }
+    }
+
+    /// Normal given by the winding order of the vertices.
+    pub fn normal(&self) -> Vec3 {
+        (self.p[1] - self.p[0])
+            .cross(self.p[2] - self.p[0])
+            .normalize()
+    }
+
+    /// Distance along the ray and barycentric coordinates of the second and
+    /// third vertices at the hit (Möller-Trumbore).
+    fn hit(&self, ray: &Ray) -> Option<(f32, Vec2)> {
+        let e1 = self.p[1] - self.p[0];
+        let e2 = self.p[2] - self.p[0];
+        let pv = ray.d.cross(e2);
+        let det = e1.dot(pv);
+        if det == 0.0 {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+        let tv = ray.o - self.p[0];
+        let b1 = tv.dot(pv) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+        let qv = tv.cross(e1);
+        let b2 = ray.d.dot(qv) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+        let t = e2.dot(qv) * inv_det;
+        (t > 0.0).then_some((t, Vec2::new(b1, b2)))
+    }
+}
+
+impl Shape for Triangle {
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        self.hit(ray).is_some()
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<IntersectRecord> {
+        let (t, uv) = self.hit(ray)?;
+        // Interpolated from the vertices, the point stays close to the plane
+        // of the triangle whatever the error of the distance.
+        let terms = [
+            self.p[0] * (1.0 - uv.x - uv.y),
+            self.p[1] * uv.x,
+            self.p[2] * uv.y,
+        ];
+        Some(IntersectRecord {
+            t,
+            p: terms[0] + terms[1] + terms[2],
+            p_error: (terms[0].abs() + terms[1].abs() + terms[2].abs()) * mre(7),
+            n: self.normal(),
+            uv,
+            time: ray.time,
+        })
+    }
+
+    fn bounds(&self) -> Aabb {
+        Aabb::new(self.p[0], self.p[1]).union_point(self.p[2])
+    }
+}
+
+impl SampleableShape for Triangle {
+    fn area(&self) -> f32 {
+        0.5 * (self.p[1] - self.p[0])
+            .cross(self.p[2] - self.p[0])
+            .length()
+    }
+
+    fn sample(&self, u: Vec2) -> SurfaceSample {
+        let b = uniform_sample_triangle(u);
+        SurfaceSample {
+            p: self.p[0] * (1.0 - b.x - b.y) + self.p[1] * b.x + self.p[2] * b.y,
+            n: self.normal(),
+            uv: b,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::shapes::tests::check_samples;
+
+    #[test]
+    fn barycentrics_of_hits() {
+        let triangle = Triangle::new(Vec3::ZERO, Vec3::X, Vec3::Y);
+        let record = triangle
+            .intersect(&Ray::new(Vec3::new(0.25, 0.5, 1.0), -Vec3::Z))
+            .unwrap();
+        assert_eq!(record.t, 1.0);
+        assert_eq!(record.uv, Vec2::new(0.25, 0.5));
+        assert_eq!(record.n, Vec3::Z);
+        // Hit from the back side.
+        assert!(triangle.intersect_p(&Ray::new(Vec3::new(0.1, 0.1, -1.0), Vec3::Z)));
+        assert!(!triangle.intersect_p(&Ray::new(Vec3::new(0.6, 0.6, 1.0), -Vec3::Z)));
+        assert!(!triangle.intersect_p(&Ray::new(Vec3::new(0.1, 0.1, 1.0), Vec3::Z)));
+        assert_eq!(triangle.area(), 0.5);
+        check_samples(&triangle);
+    }
+}
@@ -0,0 +1,361 @@
+//! Textures evaluated at surface points.
+//!
+//! A texture maps a hit point and its surface parameterisation to a value,
+//! typically an `f32` for scalar quantities (roughness, blend masks) or a
+//! `Vec3` for colours. Procedural textures allow building test scenes without
+//! texture assets.
+
+mod noise;
+
+pub use noise::{fbm, perlin, simplex, turbulence, worley, NoiseBasis};
+
+use crate::core::image::{
+    texture::{MipMap, WrapMode},
+    Pixel, PixelBuffer,
+};
+use glam::{Vec2, Vec3};
+use std::ops::{Add, Mul};
+
+/// A value varying over surfaces.
+pub trait Texture<T>: Send + Sync {
+    /// Evaluates the texture at the hit point `p` with surface coordinates
+    /// `uv`.
+    fn evaluate(&self, p: Vec3, uv: Vec2) -> T;
+}
+
+/// How a texture derives its lookup point from a hit.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Mapping {
+    /// Uses the surface coordinates, as the point `(u, v, 0)`.
+    Uv,
+
+    /// Uses the hit point, so that the texture is carved out of a solid.
+    Solid,
+}
+
+impl Mapping {
+    /// Returns the lookup point of a hit.
+    pub fn point(&self, p: Vec3, uv: Vec2) -> Vec3 {
+        match self {
+            Mapping::Uv => uv.extend(0.0),
+            Mapping::Solid => p,
+        }
+    }
+}
+
+/// Texture with the same value everywhere.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Constant<T>(pub T);
+
+impl<T: Copy + Send + Sync> Texture<T> for Constant<T> {
+    fn evaluate(&self, _p: Vec3, _uv: Vec2) -> T {
+        self.0
+    }
+}
+
+/// Texture looking up an image at the surface coordinates.
+///
+/// Lookups are bilinearly filtered on the full resolution image. Scalar
+/// lookups return the first channel; colour lookups replicate the first
+/// channel of images with less than three channels.
+#[derive(Clone)]
+pub struct ImageTexture<P: Pixel> {
+    mipmap: MipMap<P>,
+}
+
+impl<P: Pixel> ImageTexture<P> {
+    pub fn new(buffer: &PixelBuffer<P>, wrap: WrapMode) -> Self {
+        Self {
+            mipmap: MipMap::new(buffer, wrap),
+        }
+    }
+
+    pub fn from_mipmap(mipmap: MipMap<P>) -> Self {
+        Self { mipmap }
+    }
+
+    pub fn mipmap(&self) -> &MipMap<P> {
+        &self.mipmap
+    }
+}
+
+impl<P: Pixel> Texture<f32> for ImageTexture<P>
+where
+    MipMap<P>: Send + Sync,
+{
+    fn evaluate(&self, _p: Vec3, uv: Vec2) -> f32 {
+        self.mipmap.sample(uv).channels()[0]
+    }
+}
+
+impl<P: Pixel> Texture<Vec3> for ImageTexture<P>
+where
+    MipMap<P>: Send + Sync,
+{
+    fn evaluate(&self, _p: Vec3, uv: Vec2) -> Vec3 {
+        let texel = self.mipmap.sample(uv);
+        match texel.channels() {
+            [r, g, b, ..] => Vec3::new(*r, *g, *b),
+            [v, ..] => Vec3::splat(*v),
+            [] => Vec3::ZERO,
+        }
+    }
+}
+
+/// Alternates between two textures on the cells of a regular grid.
+pub struct Checkerboard<T> {
+    even: Box<dyn Texture<T>>,
+    odd: Box<dyn Texture<T>>,
+    mapping: Mapping,
+    frequency: f32,
+}
+
+impl<T> Checkerboard<T> {
+    /// Creates a checkerboard with one cell per unit of the lookup point;
+    /// the cell containing the origin uses `even`.
+    pub fn new(
+        even: impl Texture<T> + 'static,
+        odd: impl Texture<T> + 'static,
+        mapping: Mapping,
+    ) -> Self {
+        Self {
+            even: Box::new(even),
+            odd: Box::new(odd),
+            mapping,
+            frequency: 1.0,
+        }
+    }
+
+    /// Sets the number of cells per unit of the lookup point.
+    pub fn with_frequency(mut self, frequency: f32) -> Self {
+        self.frequency = frequency;
+        self
+    }
+}
+
+impl<T> Texture<T> for Checkerboard<T> {
+    fn evaluate(&self, p: Vec3, uv: Vec2) -> T {
+        let q = (self.mapping.point(p, uv) * self.frequency).floor();
+        let parity = match self.mapping {
+            Mapping::Uv => q.x + q.y,
+            Mapping::Solid => q.x + q.y + q.z,
+        };
+        if (parity as i64).rem_euclid(2) == 0 {
+            self.even.evaluate(p, uv)
+        } else {
+            self.odd.evaluate(p, uv)
+        }
+    }
+}
+
+/// Linear blend of two textures, weighted by a scalar texture: `0` gives `a`
+/// and `1` gives `b`.
+pub struct Mix<T> {
+    a: Box<dyn Texture<T>>,
+    b: Box<dyn Texture<T>>,
+    amount: Box<dyn Texture<f32>>,
+}
+
+impl<T> Mix<T> {
+    pub fn new(
+        a: impl Texture<T> + 'static,
+        b: impl Texture<T> + 'static,
+        amount: impl Texture<f32> + 'static,
+    ) -> Self {
+        Self {
+            a: Box::new(a),
+            b: Box::new(b),
+            amount: Box::new(amount),
+        }
+    }
+}
+
+impl<T: Add<Output = T> + Mul<f32, Output = T>> Texture<T> for Mix<T> {
+    fn evaluate(&self, p: Vec3, uv: Vec2) -> T {
+        let t = self.amount.evaluate(p, uv);
+        self.a.evaluate(p, uv) * (1.0 - t) + self.b.evaluate(p, uv) * t
+    }
+}
+
+/// Single octave of gradient noise, remapped to roughly `[0, 1]`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GradientNoise {
+    pub basis: NoiseBasis,
+    pub mapping: Mapping,
+    pub frequency: f32,
+}
+
+impl GradientNoise {
+    pub fn new(basis: NoiseBasis) -> Self {
+        Self {
+            basis,
+            mapping: Mapping::Solid,
+            frequency: 1.0,
+        }
+    }
+}
+
+impl Texture<f32> for GradientNoise {
+    fn evaluate(&self, p: Vec3, uv: Vec2) -> f32 {
+        let q = self.mapping.point(p, uv) * self.frequency;
+        0.5 * (self.basis.eval(q) + 1.0)
+    }
+}
+
+/// Parameters shared by the fractal noise textures.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Octaves {
+    pub basis: NoiseBasis,
+    pub mapping: Mapping,
+
+    /// Frequency of the first octave.
+    pub frequency: f32,
+
+    /// Number of octaves summed.
+    pub octaves: u32,
+
+    /// Frequency ratio between successive octaves.
+    pub lacunarity: f32,
+
+    /// Amplitude ratio between successive octaves.
+    pub gain: f32,
+}
+
+impl Default for Octaves {
+    fn default() -> Self {
+        Self {
+            basis: NoiseBasis::Perlin,
+            mapping: Mapping::Solid,
+            frequency: 1.0,
+            octaves: 6,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+}
+
+impl Octaves {
+    fn point(&self, p: Vec3, uv: Vec2) -> Vec3 {
+        self.mapping.point(p, uv) * self.frequency
+    }
+}
+
+/// Fractal Brownian motion, remapped to roughly `[0, 1]` for gains below
+/// one.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Fbm(pub Octaves);
+
+impl Texture<f32> for Fbm {
+    fn evaluate(&self, p: Vec3, uv: Vec2) -> f32 {
+        let o = &self.0;
+        let value = fbm(o.basis, o.point(p, uv), o.octaves, o.lacunarity, o.gain);
+        // Sum of the octave amplitudes, bounding the magnitude of the sum.
+        let norm = (0..o.octaves).map(|i| o.gain.powi(i as i32)).sum::<f32>();
+        0.5 * (value / norm.max(f32::MIN_POSITIVE) + 1.0)
+    }
+}
+
+/// Turbulence, the sum of the absolute octaves. Not normalised.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Turbulence(pub Octaves);
+
+impl Texture<f32> for Turbulence {
+    fn evaluate(&self, p: Vec3, uv: Vec2) -> f32 {
+        let o = &self.0;
+        turbulence(o.basis, o.point(p, uv), o.octaves, o.lacunarity, o.gain)
+    }
+}
+
+/// Quantity of the feature point distances returned by [`Worley`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WorleyFeature {
+    /// Distance to the closest feature point.
+    F1,
+
+    /// Distance to the second closest feature point.
+    F2,
+
+    /// Difference of the two, outlining the cells.
+    F2MinusF1,
+}
+
+/// Worley cellular noise, in lattice units.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Worley {
+    pub feature: WorleyFeature,
+    pub mapping: Mapping,
+    pub frequency: f32,
+    pub seed: u32,
+}
+
+impl Worley {
+    pub fn new(feature: WorleyFeature) -> Self {
+        Self {
+            feature,
+            mapping: Mapping::Solid,
+            frequency: 1.0,
+            seed: 0,
+        }
+    }
+}
+
+impl Texture<f32> for Worley {
+    fn evaluate(&self, p: Vec3, uv: Vec2) -> f32 {
+        let (f1, f2) = worley(self.mapping.point(p, uv) * self.frequency, self.seed);
+        match self.feature {
+            WorleyFeature::F1 => f1,
+            WorleyFeature::F2 => f2,
+            WorleyFeature::F2MinusF1 => f2 - f1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Vec3 as Rgb;
+
+    #[test]
+    fn checkerboard_alternates() {
+        let board =
+            Checkerboard::new(Constant(0.0f32), Constant(1.0f32), Mapping::Uv).with_frequency(4.0);
+        let at = |u: f32, v: f32| board.evaluate(Vec3::ZERO, Vec2::new(u, v));
+        assert_eq!(at(0.1, 0.1), 0.0);
+        assert_eq!(at(0.3, 0.1), 1.0);
+        assert_eq!(at(0.3, 0.3), 0.0);
+        assert_eq!(at(-0.1, 0.1), 1.0);
+
+        let solid = Checkerboard::new(Constant(0.0f32), Constant(1.0f32), Mapping::Solid);
+        assert_eq!(solid.evaluate(Vec3::new(0.5, 0.5, 1.5), Vec2::ZERO), 1.0);
+    }
+
+    #[test]
+    fn mix_blends_textures() {
+        let mix = Mix::new(Constant(Vec3::ZERO), Constant(Vec3::ONE), Constant(0.25f32));
+        assert_eq!(mix.evaluate(Vec3::ZERO, Vec2::ZERO), Vec3::splat(0.25));
+    }
+
+    #[test]
+    fn image_texture_reads_channels() {
+        let mut buffer = PixelBuffer::<Rgb<u8>>::new(2, 1);
+        *buffer.pixel_at_mut(1, 0).unwrap() = Rgb::from([10, 20, 30]);
+        let texture = ImageTexture::new(&buffer, WrapMode::Clamp);
+        let colour: Vec3 = texture.evaluate(Vec3::ZERO, Vec2::new(0.75, 0.5));
+        assert_eq!(colour, Vec3::new(10.0, 20.0, 30.0));
+        let scalar: f32 = texture.evaluate(Vec3::ZERO, Vec2::new(0.25, 0.5));
+        assert_eq!(scalar, 0.0);
+    }
+
+    #[test]
+    fn fractal_noise_is_normalised() {
+        let fbm = Fbm(Octaves {
+            basis: NoiseBasis::Simplex,
+            ..Default::default()
+        });
+        for i in 0..64 {
+            let p = Vec3::new(i as f32 * 0.37, i as f32 * 0.11, 0.5);
+            let v = fbm.evaluate(p, Vec2::ZERO);
+            assert!((-0.05..=1.05).contains(&v));
+        }
+    }
+}
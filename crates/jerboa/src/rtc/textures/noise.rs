@@ -0,0 +1,285 @@
+//! Lattice noise functions used by the procedural textures.
+//!
+//! Gradient noises return values roughly in `[-1, 1]`; cellular noise returns
+//! distances in lattice units.
+
+use glam::Vec3;
+
+/// Ken Perlin's reference permutation of `0..256`.
+const PERMUTATION: [u8; 256] = [
+    151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225, 140, 36, 103, 30, 69,
+    142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148, 247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219,
+    203, 117, 35, 11, 32, 57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
+    74, 165, 71, 134, 139, 48, 27, 166, 77, 146, 158, 231, 83, 111, 229, 122, 60, 211, 133, 230,
+    220, 105, 92, 41, 55, 46, 245, 40, 244, 102, 143, 54, 65, 25, 63, 161, 1, 216, 80, 73, 209, 76,
+    132, 187, 208, 89, 18, 169, 200, 196, 135, 130, 116, 188, 159, 86, 164, 100, 109, 198, 173,
+    186, 3, 64, 52, 217, 226, 250, 124, 123, 5, 202, 38, 147, 118, 126, 255, 82, 85, 212, 207, 206,
+    59, 227, 47, 16, 58, 17, 182, 189, 28, 42, 223, 183, 170, 213, 119, 248, 152, 2, 44, 154, 163,
+    70, 221, 153, 101, 155, 167, 43, 172, 9, 129, 22, 39, 253, 19, 98, 108, 110, 79, 113, 224, 232,
+    178, 185, 112, 104, 218, 246, 97, 228, 251, 34, 242, 193, 238, 210, 144, 12, 191, 179, 162,
+    241, 81, 51, 145, 235, 249, 14, 239, 107, 49, 192, 214, 31, 181, 199, 106, 157, 184, 84, 204,
+    176, 115, 121, 50, 45, 127, 4, 150, 254, 138, 236, 205, 93, 222, 114, 67, 29, 24, 72, 243, 141,
+    128, 195, 78, 66, 215, 61, 156, 180,
+];
+
+/// Permutation lookup wrapping the index into `0..256`.
+#[inline]
+fn perm(i: i32) -> i32 {
+    PERMUTATION[(i & 255) as usize] as i32
+}
+
+/// Hashes a lattice point into `0..256`.
+#[inline]
+fn hash3(i: i32, j: i32, k: i32) -> i32 {
+    perm(perm(perm(i) + j) + k)
+}
+
+/// Edge midpoints of the unit cube, the gradient set shared by both gradient
+/// noises.
+const GRADIENTS: [[f32; 3]; 12] = [
+    [1.0, 1.0, 0.0],
+    [-1.0, 1.0, 0.0],
+    [1.0, -1.0, 0.0],
+    [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0],
+    [-1.0, 0.0, 1.0],
+    [1.0, 0.0, -1.0],
+    [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0],
+    [0.0, -1.0, 1.0],
+    [0.0, 1.0, -1.0],
+    [0.0, -1.0, -1.0],
+];
+
+#[inline]
+fn grad(hash: i32, d: Vec3) -> f32 {
+    let g = GRADIENTS[(hash % 12) as usize];
+    g[0] * d.x + g[1] * d.y + g[2] * d.z
+}
+
+/// Quintic interpolant `6t^5 - 15t^4 + 10t^3`.
+#[inline]
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+#[inline]
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+/// Improved Perlin noise (Perlin 2002). Zero at integer lattice points.
+pub fn perlin(p: Vec3) -> f32 {
+    let cell = p.floor();
+    let d = p - cell;
+    let (i, j, k) = (cell.x as i32, cell.y as i32, cell.z as i32);
+    let (u, v, w) = (fade(d.x), fade(d.y), fade(d.z));
+
+    let corner = |di: i32, dj: i32, dk: i32| {
+        let offset = Vec3::new(di as f32, dj as f32, dk as f32);
+        grad(hash3(i + di, j + dj, k + dk), d - offset)
+    };
+
+    let x00 = lerp(u, corner(0, 0, 0), corner(1, 0, 0));
+    let x10 = lerp(u, corner(0, 1, 0), corner(1, 1, 0));
+    let x01 = lerp(u, corner(0, 0, 1), corner(1, 0, 1));
+    let x11 = lerp(u, corner(0, 1, 1), corner(1, 1, 1));
+    lerp(w, lerp(v, x00, x10), lerp(v, x01, x11))
+}
+
+/// Simplex noise (Perlin 2001), following Gustavson's formulation.
+pub fn simplex(p: Vec3) -> f32 {
+    const F3: f32 = 1.0 / 3.0;
+    const G3: f32 = 1.0 / 6.0;
+
+    // Skews the input space to find the simplex cell.
+    let s = (p.x + p.y + p.z) * F3;
+    let cell = (p + Vec3::splat(s)).floor();
+    let t = (cell.x + cell.y + cell.z) * G3;
+    let d0 = p - (cell - Vec3::splat(t));
+
+    // Offsets of the second and third corners, depending on which of the six
+    // simplices of the skewed cube contains the point.
+    let (o1, o2) = if d0.x >= d0.y {
+        if d0.y >= d0.z {
+            ([1, 0, 0], [1, 1, 0])
+        } else if d0.x >= d0.z {
+            ([1, 0, 0], [1, 0, 1])
+        } else {
+            ([0, 0, 1], [1, 0, 1])
+        }
+    } else if d0.y < d0.z {
+        ([0, 0, 1], [0, 1, 1])
+    } else if d0.x < d0.z {
+        ([0, 1, 0], [0, 1, 1])
+    } else {
+        ([0, 1, 0], [1, 1, 0])
+    };
+
+    let (i, j, k) = (cell.x as i32, cell.y as i32, cell.z as i32);
+    let corners = [[0, 0, 0], o1, o2, [1, 1, 1]];
+    corners
+        .iter()
+        .enumerate()
+        .map(|(n, o)| {
+            let offset = Vec3::new(o[0] as f32, o[1] as f32, o[2] as f32);
+            let d = d0 - offset + Vec3::splat(n as f32 * G3);
+            let t = 0.6 - d.length_squared();
+            if t < 0.0 {
+                0.0
+            } else {
+                let t2 = t * t;
+                t2 * t2 * grad(hash3(i + o[0], j + o[1], k + o[2]), d)
+            }
+        })
+        .sum::<f32>()
+        * 32.0
+}
+
+/// Gradient noise used as the basis of fractal noises.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NoiseBasis {
+    Perlin,
+    Simplex,
+}
+
+impl NoiseBasis {
+    /// Evaluates the noise at `p`.
+    pub fn eval(&self, p: Vec3) -> f32 {
+        match self {
+            NoiseBasis::Perlin => perlin(p),
+            NoiseBasis::Simplex => simplex(p),
+        }
+    }
+}
+
+/// Fractal Brownian motion: sum of `octaves` copies of the basis with the
+/// frequency multiplied by `lacunarity` and the amplitude by `gain` at each
+/// octave.
+pub fn fbm(basis: NoiseBasis, p: Vec3, octaves: u32, lacunarity: f32, gain: f32) -> f32 {
+    let (mut sum, mut frequency, mut amplitude) = (0.0, 1.0, 1.0);
+    for _ in 0..octaves {
+        sum += amplitude * basis.eval(p * frequency);
+        frequency *= lacunarity;
+        amplitude *= gain;
+    }
+    sum
+}
+
+/// Same as [`fbm`] but summing the absolute value of each octave, giving
+/// creases where the basis crosses zero.
+pub fn turbulence(basis: NoiseBasis, p: Vec3, octaves: u32, lacunarity: f32, gain: f32) -> f32 {
+    let (mut sum, mut frequency, mut amplitude) = (0.0, 1.0, 1.0);
+    for _ in 0..octaves {
+        sum += amplitude * basis.eval(p * frequency).abs();
+        frequency *= lacunarity;
+        amplitude *= gain;
+    }
+    sum
+}
+
+/// Integer hash (the finaliser of MurmurHash3) of a lattice cell.
+#[inline]
+fn hash_cell(i: i32, j: i32, k: i32, seed: u32) -> u32 {
+    let mut h = seed
+        ^ (i as u32).wrapping_mul(0x8da6_b343)
+        ^ (j as u32).wrapping_mul(0xd816_3841)
+        ^ (k as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^ (h >> 16)
+}
+
+/// Feature point of a lattice cell, uniformly placed inside the cell.
+fn feature_point(i: i32, j: i32, k: i32, seed: u32) -> Vec3 {
+    let mut h = hash_cell(i, j, k, seed);
+    let mut next = || {
+        h = hash_cell(h as i32, 0x68e3_1da4, 0x1b56_c4e9, seed);
+        (h >> 8) as f32 / (1u32 << 24) as f32
+    };
+    Vec3::new(i as f32 + next(), j as f32 + next(), k as f32 + next())
+}
+
+/// Worley (cellular) noise with one feature point per lattice cell. Returns
+/// the distances to the closest and second closest feature points.
+pub fn worley(p: Vec3, seed: u32) -> (f32, f32) {
+    let cell = p.floor();
+    let (i, j, k) = (cell.x as i32, cell.y as i32, cell.z as i32);
+    let (mut f1, mut f2) = (f32::INFINITY, f32::INFINITY);
+    for dk in -1..=1 {
+        for dj in -1..=1 {
+            for di in -1..=1 {
+                let d = feature_point(i + di, j + dj, k + dk, seed).distance_squared(p);
+                if d < f1 {
+                    f2 = f1;
+                    f1 = d;
+                } else if d < f2 {
+                    f2 = d;
+                }
+            }
+        }
+    }
+    (f1.sqrt(), f2.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck::quickcheck;
+
+    fn point(x: i16, y: i16, z: i16) -> Vec3 {
+        Vec3::new(x as f32, y as f32, z as f32) * 0.0173
+    }
+
+    #[test]
+    fn permutation_is_complete() {
+        let mut seen = [false; 256];
+        PERMUTATION.iter().for_each(|&i| seen[i as usize] = true);
+        assert!(seen.iter().all(|&s| s));
+    }
+
+    #[test]
+    fn perlin_vanishes_on_lattice() {
+        for p in [
+            Vec3::ZERO,
+            Vec3::new(3.0, -2.0, 7.0),
+            Vec3::new(-11.0, 5.0, 1.0),
+        ] {
+            assert_eq!(perlin(p), 0.0);
+        }
+    }
+
+    #[test]
+    fn gradient_noise_is_continuous() {
+        let p = Vec3::new(0.31, 1.7, -2.4);
+        let e = Vec3::splat(1e-4);
+        assert!((perlin(p) - perlin(p + e)).abs() < 1e-2);
+        assert!((simplex(p) - simplex(p + e)).abs() < 1e-2);
+    }
+
+    quickcheck! {
+        fn gradient_noise_is_bounded(x: i16, y: i16, z: i16) -> bool {
+            let p = point(x, y, z);
+            perlin(p).abs() <= 1.1 && simplex(p).abs() <= 1.1
+        }
+
+        fn turbulence_is_non_negative(x: i16, y: i16, z: i16) -> bool {
+            turbulence(NoiseBasis::Perlin, point(x, y, z), 4, 2.0, 0.5) >= 0.0
+        }
+
+        fn worley_distances_are_ordered(x: i16, y: i16, z: i16) -> bool {
+            let (f1, f2) = worley(point(x, y, z), 7);
+            // The closest feature point lies within the 3x3x3 neighbourhood.
+            0.0 <= f1 && f1 <= f2 && f1 <= 3f32.sqrt()
+        }
+    }
+
+    #[test]
+    fn worley_is_zero_at_feature_points() {
+        let p = feature_point(2, -1, 4, 3);
+        assert_eq!(worley(p, 3).0, 0.0);
+        assert!(worley(p, 4).0 > 0.0);
+    }
+}
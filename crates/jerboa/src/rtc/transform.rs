@@ -0,0 +1,351 @@
+//! Affine transforms between object and world space, possibly animated.
+
+use crate::{
+    core::rounding::mre,
+    rtc::{aabb::Aabb, ray::Ray},
+};
+use glam::{Mat4, Quat, Vec3};
+use std::ops::Mul;
+
+/// Affine transform stored with its inverse, so that rays can be brought
+/// into object space and normals out of it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    m: Mat4,
+    m_inv: Mat4,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        m: Mat4::IDENTITY,
+        m_inv: Mat4::IDENTITY,
+    };
+
+    /// Creates a transform from an invertible affine matrix.
+    pub fn new(m: Mat4) -> Self {
+        Self {
+            m,
+            m_inv: m.inverse(),
+        }
+    }
+
+    pub fn from_translation(t: Vec3) -> Self {
+        Self {
+            m: Mat4::from_translation(t),
+            m_inv: Mat4::from_translation(-t),
+        }
+    }
+
+    /// Scales by `s`, whose components must not be zero.
+    pub fn from_scale(s: Vec3) -> Self {
+        Self {
+            m: Mat4::from_scale(s),
+            m_inv: Mat4::from_scale(s.recip()),
+        }
+    }
+
+    pub fn from_rotation(rotation: Quat) -> Self {
+        Self {
+            m: Mat4::from_quat(rotation),
+            m_inv: Mat4::from_quat(rotation.conjugate()),
+        }
+    }
+
+    /// Scales, then rotates, then translates.
+    pub fn from_scale_rotation_translation(scale: Vec3, rotation: Quat, translation: Vec3) -> Self {
+        Self::from_translation(translation)
+            * Self::from_rotation(rotation)
+            * Self::from_scale(scale)
+    }
+
+    pub fn matrix(&self) -> &Mat4 {
+        &self.m
+    }
+
+    pub fn inverse_matrix(&self) -> &Mat4 {
+        &self.m_inv
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            m: self.m_inv,
+            m_inv: self.m,
+        }
+    }
+
+    pub fn transform_point(&self, p: Vec3) -> Vec3 {
+        self.m.transform_point3(p)
+    }
+
+    /// Transforms the point `p`, known within `p_error` in each dimension,
+    /// with the bound on the error of the result, including rounding.
+    pub fn transform_point_with_error(&self, p: Vec3, p_error: Vec3) -> (Vec3, Vec3) {
+        let [c0, c1, c2, c3] =
+            [self.m.x_axis, self.m.y_axis, self.m.z_axis, self.m.w_axis].map(|c| c.truncate());
+        let rounding = (c0 * p.x).abs() + (c1 * p.y).abs() + (c2 * p.z).abs() + c3.abs();
+        let propagated = c0.abs() * p_error.x + c1.abs() * p_error.y + c2.abs() * p_error.z;
+        let gamma = mre(3);
+        let error = rounding * gamma + propagated * (1.0 + gamma);
+        (self.transform_point(p), error)
+    }
+
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        self.m.transform_vector3(v)
+    }
+
+    /// Transforms a normal by the inverse transpose of the matrix, so that
+    /// it stays perpendicular to the transformed surface. The result is not
+    /// normalised.
+    pub fn transform_normal(&self, n: Vec3) -> Vec3 {
+        self.m_inv.transpose().transform_vector3(n)
+    }
+
+    /// Transforms the origin and direction of the ray. The direction is not
+    /// normalised, so that distances along the ray are preserved.
+    pub fn transform_ray(&self, ray: &Ray) -> Ray {
+        Ray::new(self.transform_point(ray.o), self.transform_vector(ray.d)).with_time(ray.time)
+    }
+
+    /// Box bounding the transformed box. Empty and infinite boxes are
+    /// returned as they are.
+    pub fn transform_bounds(&self, b: &Aabb) -> Aabb {
+        if b.is_empty() || !b.is_finite() {
+            return *b;
+        }
+        (0..8).fold(Aabb::EMPTY, |bounds, i| {
+            let corner = Vec3::new(
+                if i & 1 == 0 { b.min.x } else { b.max.x },
+                if i & 2 == 0 { b.min.y } else { b.max.y },
+                if i & 4 == 0 { b.min.z } else { b.max.z },
+            );
+            bounds.union_point(self.transform_point(corner))
+        })
+    }
+}
+
+impl Mul for Transform {
+    type Output = Transform;
+
+    /// Composition applying `rhs` first.
+    fn mul(self, rhs: Transform) -> Transform {
+        Transform {
+            m: self.m * rhs.m,
+            m_inv: rhs.m_inv * self.m_inv,
+        }
+    }
+}
+
+/// Transform at a given time, decomposed into a scale, a rotation and a
+/// translation applied in that order.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Keyframe {
+    pub time: f32,
+    pub scale: Vec3,
+    pub rotation: Quat,
+    pub translation: Vec3,
+}
+
+impl Keyframe {
+    pub fn new(time: f32, scale: Vec3, rotation: Quat, translation: Vec3) -> Self {
+        Self {
+            time,
+            scale,
+            rotation,
+            translation,
+        }
+    }
+
+    /// Decomposes a transform, which must not have shear.
+    pub fn from_transform(time: f32, transform: &Transform) -> Self {
+        let (scale, rotation, translation) = transform.matrix().to_scale_rotation_translation();
+        Self::new(time, scale, rotation, translation)
+    }
+
+    pub fn transform(&self) -> Transform {
+        Transform::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+/// Transform interpolated between keyframes: scales and translations
+/// linearly, rotations along the shortest arc. Before the first keyframe and
+/// after the last one, the transform stays still.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimatedTransform {
+    /// Keyframes sorted by time.
+    keyframes: Vec<Keyframe>,
+}
+
+impl From<Transform> for AnimatedTransform {
+    fn from(transform: Transform) -> Self {
+        Self::new(vec![Keyframe::from_transform(0.0, &transform)])
+    }
+}
+
+impl AnimatedTransform {
+    /// Creates the animation through `keyframes`, of which there must be at
+    /// least one.
+    pub fn new(mut keyframes: Vec<Keyframe>) -> Self {
+        assert!(!keyframes.is_empty(), "an animation needs a keyframe");
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self { keyframes }
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Whether the transform changes over time.
+    pub fn is_animated(&self) -> bool {
+        self.keyframes.windows(2).any(|k| !is_still(&k[0], &k[1]))
+    }
+
+    pub fn interpolate(&self, time: f32) -> Transform {
+        let i = self.keyframes.partition_point(|k| k.time <= time);
+        if i == 0 {
+            return self.keyframes[0].transform();
+        }
+        if i == self.keyframes.len() {
+            return self.keyframes[i - 1].transform();
+        }
+        let (a, b) = (&self.keyframes[i - 1], &self.keyframes[i]);
+        let s = (time - a.time) / (b.time - a.time);
+        Transform::from_scale_rotation_translation(
+            a.scale.lerp(b.scale, s),
+            a.rotation.slerp(b.rotation, s),
+            a.translation.lerp(b.translation, s),
+        )
+    }
+
+    /// Box bounding `b` transformed at any time.
+    ///
+    /// Between keyframes of the same rotation, the corners of the box move
+    /// along segments, and the boxes at both keyframes bound the motion.
+    /// Otherwise, the box is bounded by the sphere it sweeps around the
+    /// translation, whose radius is that of the box under the largest of
+    /// both scales.
+    pub fn motion_bounds(&self, b: &Aabb) -> Aabb {
+        if b.is_empty() || !b.is_finite() {
+            return *b;
+        }
+        let first = self.keyframes[0].transform().transform_bounds(b);
+        self.keyframes.windows(2).fold(first, |bounds, k| {
+            let (k0, k1) = (&k[0], &k[1]);
+            if k0.rotation.dot(k1.rotation).abs() >= 1.0 - 1e-6 {
+                return bounds.union(&k1.transform().transform_bounds(b));
+            }
+            let scale = k0.scale.abs().max(k1.scale.abs());
+            let radius = (b.min * scale).abs().max((b.max * scale).abs()).length();
+            bounds.union(&Aabb::new(
+                k0.translation.min(k1.translation) - Vec3::splat(radius),
+                k0.translation.max(k1.translation) + Vec3::splat(radius),
+            ))
+        })
+    }
+}
+
+/// Whether the transform is the same at both keyframes.
+fn is_still(a: &Keyframe, b: &Keyframe) -> bool {
+    a.scale == b.scale
+        && a.translation == b.translation
+        && a.rotation.dot(b.rotation).abs() >= 1.0 - 1e-6
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+
+    #[test]
+    fn inverse_and_normals() {
+        let t = Transform::from_scale_rotation_translation(
+            Vec3::new(2.0, 1.0, 0.5),
+            Quat::from_rotation_z(0.7),
+            Vec3::new(1.0, -2.0, 3.0),
+        );
+        let p = Vec3::new(0.3, -0.4, 2.0);
+        assert!(t
+            .inverse()
+            .transform_point(t.transform_point(p))
+            .abs_diff_eq(p, 1e-5));
+        assert!((*t.matrix() * *t.inverse_matrix()).abs_diff_eq(Mat4::IDENTITY, 1e-5));
+        assert!(Transform::new(*t.matrix())
+            .inverse_matrix()
+            .abs_diff_eq(*t.inverse_matrix(), 1e-5));
+
+        // A normal stays perpendicular to the vectors of its plane under a
+        // non-uniform scale.
+        let (n, v) = (Vec3::new(1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0));
+        assert!(t.transform_normal(n).dot(t.transform_vector(v)).abs() < 1e-5);
+        assert!(t.transform_vector(n).dot(t.transform_vector(v)).abs() > 0.1);
+    }
+
+    #[test]
+    fn bounds() {
+        let t = Transform::from_rotation(Quat::from_rotation_z(FRAC_PI_4))
+            * Transform::from_scale(Vec3::splat(2.0));
+        let b = t.transform_bounds(&Aabb::new(-Vec3::ONE, Vec3::ONE));
+        let r = 2.0 * 2.0f32.sqrt();
+        assert!(b.max.abs_diff_eq(Vec3::new(r, r, 2.0), 1e-5));
+        assert_eq!(t.transform_bounds(&Aabb::INFINITE), Aabb::INFINITE);
+        assert!(t.transform_bounds(&Aabb::EMPTY).is_empty());
+    }
+
+    #[test]
+    fn interpolation() {
+        let animation = AnimatedTransform::new(vec![
+            Keyframe::new(
+                1.0,
+                Vec3::splat(2.0),
+                Quat::from_rotation_z(FRAC_PI_2),
+                Vec3::X,
+            ),
+            Keyframe::new(0.0, Vec3::ONE, Quat::IDENTITY, Vec3::ZERO),
+        ]);
+        assert!(animation.is_animated());
+        assert_eq!(animation.keyframes()[0].time, 0.0);
+        let halfway = animation.interpolate(0.5);
+        let expected = Transform::from_scale_rotation_translation(
+            Vec3::splat(1.5),
+            Quat::from_rotation_z(FRAC_PI_4),
+            Vec3::new(0.5, 0.0, 0.0),
+        );
+        assert!(halfway.matrix().abs_diff_eq(*expected.matrix(), 1e-5));
+        // Still outside of the keyframes.
+        assert_eq!(animation.interpolate(-1.0), Transform::IDENTITY);
+        assert!(animation
+            .interpolate(2.0)
+            .transform_point(Vec3::X)
+            .abs_diff_eq(Vec3::new(1.0, 2.0, 0.0), 1e-5));
+
+        let still = AnimatedTransform::from(Transform::from_translation(Vec3::Y));
+        assert!(!still.is_animated());
+        assert!(still
+            .interpolate(3.0)
+            .transform_point(Vec3::ZERO)
+            .abs_diff_eq(Vec3::Y, 1e-6));
+    }
+
+    #[test]
+    fn motion_bounds() {
+        let b = Aabb::new(Vec3::new(0.5, -0.2, -0.1), Vec3::new(1.5, 0.2, 0.1));
+        for rotation in [Quat::IDENTITY, Quat::from_rotation_z(3.0)] {
+            let animation = AnimatedTransform::new(vec![
+                Keyframe::new(0.0, Vec3::ONE, Quat::IDENTITY, Vec3::ZERO),
+                Keyframe::new(1.0, Vec3::splat(0.5), rotation, Vec3::new(1.0, 2.0, 0.0)),
+                Keyframe::new(2.0, Vec3::ONE, rotation, Vec3::new(-1.0, 0.0, 0.0)),
+            ]);
+            let bounds = animation.motion_bounds(&b);
+            for i in 0..=100 {
+                let moved = animation.interpolate(i as f32 / 50.0).transform_bounds(&b);
+                assert!(bounds.min.cmple(moved.min + 1e-5).all());
+                assert!(bounds.max.cmpge(moved.max - 1e-5).all());
+            }
+        }
+    }
+}